@@ -1,6 +1,17 @@
-use crate::{config::AppConfig, ingest, pricing_remote, storage::Storage, tui};
-use anyhow::Result;
-use std::sync::Arc;
+use crate::{
+    cli::{IngestArgs, ReportArgs},
+    config::AppConfig,
+    doctor,
+    fx::{self, DisplayCurrency},
+    git, ingest, pricing_remote, report,
+    storage::Storage,
+    tui,
+};
+use anyhow::{Context, Result};
+use std::{future::Future, io::Write, sync::Arc, time::Duration};
+use tokio::{task::JoinHandle, time};
+
+const PRICING_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 /// High-level application orchestrator.
 pub struct App {
@@ -9,18 +20,16 @@ pub struct App {
 
 impl App {
     pub async fn new(config: AppConfig) -> Result<Self> {
+        let currency = DisplayCurrency::from_config(&config.pricing.fx)
+            .with_context(|| "failed to resolve display currency")?;
+        fx::install(currency);
         Ok(Self {
             config: Arc::new(config),
         })
     }
 
-    pub async fn run(self, rebuild: bool) -> Result<()> {
-        let storage = Storage::connect(&self.config.storage.database_path).await?;
-        storage.ensure_schema().await?;
-        if rebuild {
-            tracing::info!("Rebuild requested: truncating usage tables");
-            storage.truncate_usage_tables().await?;
-        }
+    pub async fn run(self, rebuild: bool, ingest_enabled: bool) -> Result<()> {
+        let storage = self.open_storage(rebuild).await?;
 
         let pricing_config = self.config.pricing.clone();
         let pricing_storage = storage.clone();
@@ -32,12 +41,259 @@ impl App {
             }
         });
 
-        let ingest_handle = ingest::spawn(self.config.clone(), storage.clone()).await?;
+        let ingest_handle = if ingest_enabled {
+            Some(ingest::spawn(self.config.clone(), storage.clone()).await?)
+        } else {
+            tracing::info!("Ingest disabled; TUI will only read the existing database");
+            None
+        };
+        let git_task = if ingest_enabled {
+            self.spawn_commit_attribution(&storage)
+        } else {
+            None
+        };
 
         tracing::info!("Launching interactive TUI (requires an attached terminal)");
         tui::run(self.config.clone(), storage.clone()).await?;
 
+        if let Some(git_task) = git_task {
+            git_task.abort();
+        }
+        if let Some(ingest_handle) = ingest_handle {
+            ingest_handle.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    /// Runs the ingestor and periodic pricing refresh without a terminal until
+    /// SIGINT or SIGTERM is received.
+    pub async fn run_daemon(self, rebuild: bool) -> Result<()> {
+        self.run_daemon_until(rebuild, wait_for_shutdown_signal())
+            .await
+    }
+
+    async fn run_daemon_until(
+        self,
+        rebuild: bool,
+        shutdown: impl Future<Output = Result<()>>,
+    ) -> Result<()> {
+        let storage = self.open_storage(rebuild).await?;
+
+        let pricing_config = self.config.pricing.clone();
+        let pricing_storage = storage.clone();
+        let pricing_task = tokio::spawn(async move {
+            let mut ticker = time::interval(PRICING_CHECK_INTERVAL);
+            ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(err) =
+                    pricing_remote::sync_if_needed(&pricing_config, &pricing_storage).await
+                {
+                    tracing::warn!(error = %err, "failed to sync pricing");
+                }
+            }
+        });
+
+        let git_task = self.spawn_commit_attribution(&storage);
+        let ingest_handle = ingest::spawn(self.config.clone(), storage.clone()).await?;
+        tracing::info!(
+            database = %self.config.storage.database_path.display(),
+            "Running headless; waiting for SIGINT/SIGTERM"
+        );
+
+        let signal_result = shutdown.await;
+        tracing::info!("Shutdown signal received; stopping ingest");
+
+        pricing_task.abort();
+        if let Some(git_task) = git_task {
+            git_task.abort();
+        }
         ingest_handle.shutdown().await?;
+        signal_result
+    }
+
+    /// Prints aggregates for the requested range without starting ingest.
+    /// Prices are read as the last ingesting process stored them.
+    pub async fn run_report(self, args: &ReportArgs) -> Result<()> {
+        let storage = self.connect_storage().await?;
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        report::run(&storage, args, &mut out).await
+    }
+
+    pub async fn run_doctor(self) -> Result<()> {
+        let storage = self.open_storage(false).await?;
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        doctor::run(&self.config, &storage, &mut out).await
+    }
+
+    /// Links sessions to the git commits authored while they ran.
+    pub async fn run_attribute_commits(self) -> Result<()> {
+        let storage = self.open_storage(false).await?;
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        git::run(&storage, &mut out).await
+    }
+
+    /// Ingests a transcript stream from stdin or a named pipe until it ends or
+    /// SIGINT/SIGTERM is received.
+    pub async fn run_ingest_stream(self, args: &IngestArgs) -> Result<()> {
+        let storage = self.open_storage(false).await?;
+        let input = match &args.fifo {
+            Some(path) => ingest::StreamInput::Fifo(path.clone()),
+            None => ingest::StreamInput::Stdin,
+        };
+        let options = ingest::StreamOptions {
+            label: args.label.clone(),
+            session_id: args.session_id.clone(),
+            model: args.model.clone(),
+        };
+        let shutdown = async {
+            if let Err(err) = wait_for_shutdown_signal().await {
+                tracing::warn!(error = %err, "failed to listen for shutdown signals");
+                std::future::pending::<()>().await;
+            }
+        };
+        let summary = ingest::ingest_stream(&storage, input, &options, shutdown).await?;
+        tracing::info!(
+            lines = summary.lines,
+            session_id = summary.session_id.as_deref().unwrap_or("-"),
+            "Stream ingest finished"
+        );
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        writeln!(
+            out,
+            "Ingested {} lines into session {}",
+            summary.lines,
+            summary.session_id.as_deref().unwrap_or("(none)")
+        )?;
         Ok(())
     }
+
+    /// Starts the periodic commit attribution pass when enabled in `[git]`.
+    fn spawn_commit_attribution(&self, storage: &Storage) -> Option<JoinHandle<()>> {
+        if !self.config.git.attribute_commits {
+            return None;
+        }
+        let storage = storage.clone();
+        let interval = Duration::from_secs(self.config.git.interval_secs.max(1));
+        Some(tokio::spawn(async move {
+            let mut ticker = time::interval(interval);
+            ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = git::attribute_commits(&storage).await {
+                    tracing::warn!(error = %err, "failed to attribute git commits");
+                }
+            }
+        }))
+    }
+
+    /// Connects to the database with costs read in the display currency.
+    async fn connect_storage(&self) -> Result<Storage> {
+        let storage = Storage::connect(&self.config.storage.database_path)
+            .await?
+            .with_display_currency(fx::display().clone());
+        storage.ensure_schema().await?;
+        Ok(storage)
+    }
+
+    async fn open_storage(&self, rebuild: bool) -> Result<Storage> {
+        let storage = self.connect_storage().await?;
+        if rebuild {
+            tracing::info!("Rebuild requested: truncating usage tables");
+            storage.truncate_usage_tables().await?;
+        }
+        pricing_remote::sync_overrides(&self.config.pricing, &storage)
+            .await
+            .with_context(|| "failed to apply price overrides")?;
+        Ok(storage)
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_shutdown_signal() -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate =
+        signal(SignalKind::terminate()).with_context(|| "failed to install SIGTERM handler")?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result.with_context(|| "failed to listen for SIGINT")?;
+        }
+        _ = terminate.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown_signal() -> Result<()> {
+    tokio::signal::ctrl_c()
+        .await
+        .with_context(|| "failed to listen for Ctrl+C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn daemon_ingests_until_shutdown() {
+        let dir = tempdir().unwrap();
+        let sessions = dir.path().join("sessions");
+        fs::create_dir_all(&sessions).unwrap();
+        let ts = "2025-06-01T12:00:00.000Z";
+        fs::write(
+            sessions.join("rollout-a.jsonl"),
+            format!(
+                "{{\"timestamp\":\"{ts}\",\"type\":\"session_meta\",\"payload\":{{\"id\":\"sess-a\",\"timestamp\":\"{ts}\"}}}}\n\
+                 {{\"timestamp\":\"{ts}\",\"type\":\"turn_context\",\"payload\":{{\"model\":\"gpt-5\"}}}}\n\
+                 {{\"timestamp\":\"{ts}\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{{\"input_tokens\":100,\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":100}}}}}}}}\n"
+            ),
+        )
+        .unwrap();
+
+        let mut config = AppConfig::default();
+        config.storage.database_path = dir.path().join("usage.db");
+        config.sessions.root_dir = sessions;
+        config.pricing.remote.url = dir.path().join("missing.json").display().to_string();
+        let app = App {
+            config: Arc::new(config),
+        };
+
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        let daemon = tokio::spawn(app.run_daemon_until(false, async move {
+            let _ = stop_rx.await;
+            Ok(())
+        }));
+
+        let storage = Storage::connect(&dir.path().join("usage.db"))
+            .await
+            .unwrap();
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let mut total_tokens = 0;
+        for _ in 0..100 {
+            time::sleep(Duration::from_millis(50)).await;
+            if let Ok(totals) = storage.totals_between_timestamps(start, end).await {
+                total_tokens = totals.total_tokens;
+                if total_tokens > 0 {
+                    break;
+                }
+            }
+        }
+        assert_eq!(total_tokens, 100);
+
+        stop_tx.send(()).unwrap();
+        time::timeout(Duration::from_secs(10), daemon)
+            .await
+            .expect("daemon did not stop after shutdown")
+            .unwrap()
+            .unwrap();
+    }
 }
@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 /// Command-line interface for configuring the dashboard.
@@ -6,9 +7,161 @@ use std::path::PathBuf;
 #[command(author, version, about = "Local Codex dashboard and TUI", long_about = None)]
 pub struct Cli {
     /// Path to a TOML configuration file (defaults to ./codex-usage.toml if present).
-    #[arg(long, value_name = "FILE")]
+    #[arg(long, value_name = "FILE", global = true)]
     pub config_path: Option<PathBuf>,
     /// Rebuild usage data by truncating all non-pricing tables before ingesting.
     #[arg(long)]
     pub rebuild: bool,
+    /// Run ingestion and pricing refresh without the TUI (same as `daemon`).
+    #[arg(long)]
+    pub headless: bool,
+    /// Start the TUI without its own ingestor (use when a daemon is already running).
+    #[arg(long)]
+    pub no_ingest: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Parses the process arguments, exiting with a usage error on failure.
+    pub fn parse_args() -> Self {
+        Self::try_parse_args(std::env::args_os()).unwrap_or_else(|err| err.exit())
+    }
+
+    /// Like `try_parse_from`, but also rejects `--rebuild` with a subcommand:
+    /// clap accepts a top-level flag before any subcommand, and only the TUI
+    /// (and `daemon --rebuild`) rebuild anything.
+    fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let cli = Self::try_parse_from(args)?;
+        if cli.rebuild && cli.command.is_some() {
+            return Err(Self::command().error(
+                ErrorKind::ArgumentConflict,
+                "--rebuild cannot be used with a subcommand (use `daemon --rebuild` for the daemon)",
+            ));
+        }
+        Ok(cli)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Ingest session logs and refresh pricing in the background until SIGINT/SIGTERM.
+    Daemon(DaemonArgs),
+    /// Print usage aggregates for a time range to stdout.
+    Report(ReportArgs),
+    /// Check session roots and list lines that failed to ingest.
+    Doctor,
+    /// Link sessions to the git commits authored in their working directory.
+    AttributeCommits,
+    /// Ingest a transcript stream from stdin or a named pipe instead of session files.
+    Ingest(IngestArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct DaemonArgs {
+    /// Rebuild usage data by truncating all non-pricing tables before ingesting.
+    #[arg(long)]
+    pub rebuild: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("input").required(true).args(["stdin", "fifo"])))]
+pub struct IngestArgs {
+    /// Read transcript lines from standard input until it is closed.
+    #[arg(long)]
+    pub stdin: bool,
+    /// Read transcript lines from a named pipe, reopening it after each writer
+    /// closes it, until SIGINT/SIGTERM.
+    #[arg(long, value_name = "PATH")]
+    pub fifo: Option<PathBuf>,
+    /// Session root label stored on the stream's sessions.
+    #[arg(long, value_name = "LABEL")]
+    pub label: Option<String>,
+    /// Record all events under this session id instead of the stream's own.
+    #[arg(long, value_name = "ID")]
+    pub session_id: Option<String>,
+    /// Model to price turns with until the stream names one. Needed for
+    /// `codex exec --json` output, which does not.
+    #[arg(long, value_name = "MODEL")]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Time range ending at the current day, week, month or year.
+    #[arg(long, value_enum, default_value_t = ReportRange::Week)]
+    pub range: ReportRange,
+    /// Dimension to aggregate by.
+    #[arg(long, value_enum, default_value_t = ReportGroupBy::Model)]
+    pub group_by: ReportGroupBy,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    /// Maximum number of rows to print.
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+    /// Only count sessions from the session root with this label.
+    #[arg(long, value_name = "LABEL")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportRange {
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportGroupBy {
+    Model,
+    Repo,
+    Session,
+    Day,
+    Label,
+    Effort,
+    Approval,
+    Commit,
+    Branch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuild_is_only_accepted_by_the_tui_and_daemon() {
+        let parse = |args: &[&str]| Cli::try_parse_args(["codex-dashboard"].iter().chain(args));
+        assert!(parse(&["--rebuild"]).unwrap().rebuild);
+        assert!(parse(&["--headless", "--rebuild"]).unwrap().rebuild);
+        let Some(Command::Daemon(args)) = parse(&["daemon", "--rebuild"]).unwrap().command else {
+            panic!("expected the daemon subcommand");
+        };
+        assert!(args.rebuild);
+
+        for args in [
+            &["report", "--rebuild"][..],
+            &["--rebuild", "report"],
+            &["--rebuild", "doctor"],
+            &["attribute-commits", "--rebuild"],
+            &["ingest", "--stdin", "--rebuild"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} was accepted");
+        }
+        assert!(parse(&["--config-path", "a.toml", "report"]).is_ok());
+        assert!(parse(&["report", "--config-path", "a.toml"]).is_ok());
+    }
 }
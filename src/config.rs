@@ -1,12 +1,22 @@
 #![allow(dead_code)]
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, de::Error as _};
 use std::{
-    env, fs,
+    collections::BTreeMap,
+    env,
+    ffi::OsStr,
+    fs,
     path::{Path, PathBuf},
 };
 
+/// Label given to sessions from `root_dir` or an unlabelled env root.
+pub const DEFAULT_ROOT_LABEL: &str = "default";
+
+/// Sibling of the Codex `sessions` directory that archived logs are moved to.
+const ARCHIVED_SESSIONS_DIR: &str = "archived_sessions";
+
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
@@ -19,6 +29,8 @@ pub struct AppConfig {
     pub pricing: PricingConfig,
     #[serde(default)]
     pub alerts: AlertConfig,
+    #[serde(default)]
+    pub git: GitConfig,
 }
 
 impl AppConfig {
@@ -52,6 +64,14 @@ impl AppConfig {
         }
         if let Ok(sessions_dir) = env::var("CODEX_USAGE_SESSIONS_DIR") {
             self.sessions.root_dir = PathBuf::from(sessions_dir);
+            self.sessions.roots.clear();
+        }
+        if let Ok(session_roots) = env::var("CODEX_USAGE_SESSION_ROOTS") {
+            let roots = parse_env_roots(&session_roots);
+            if let Some(first) = roots.first() {
+                self.sessions.root_dir = first.path.clone();
+            }
+            self.sessions.roots = roots;
         }
         if let Ok(poll_interval) = env::var("CODEX_USAGE_SESSIONS_POLL_INTERVAL_SECS")
             && let Ok(value) = poll_interval.parse::<u64>()
@@ -99,15 +119,74 @@ impl Default for DisplayConfig {
 pub struct SessionsConfig {
     #[serde(default = "default_sessions_root")]
     pub root_dir: PathBuf,
+    /// Labelled session roots; when empty, `root_dir` is the only root.
+    #[serde(default)]
+    pub roots: Vec<SessionRootConfig>,
     #[serde(default = "default_sessions_poll_interval")]
     pub poll_interval_secs: u64,
+    #[serde(default = "default_sessions_watch")]
+    pub watch: bool,
+    #[serde(default = "default_sessions_reconcile_interval")]
+    pub reconcile_interval_secs: u64,
 }
 
 impl Default for SessionsConfig {
     fn default() -> Self {
         Self {
             root_dir: default_sessions_root(),
+            roots: Vec::new(),
             poll_interval_secs: default_sessions_poll_interval(),
+            watch: default_sessions_watch(),
+            reconcile_interval_secs: default_sessions_reconcile_interval(),
+        }
+    }
+}
+
+impl SessionsConfig {
+    /// Roots to ingest, falling back to `root_dir` under the default label.
+    /// When `root_dir` is a Codex `sessions` directory, its sibling
+    /// `archived_sessions` (where Codex moves archived logs) is included too.
+    pub fn session_roots(&self) -> Vec<SessionRootConfig> {
+        if !self.roots.is_empty() {
+            return self.roots.clone();
+        }
+        let mut roots = vec![SessionRootConfig {
+            path: self.root_dir.clone(),
+            label: DEFAULT_ROOT_LABEL.to_string(),
+            source: LogSourceKind::default(),
+        }];
+        if self.root_dir.file_name() == Some(OsStr::new("sessions")) {
+            roots.push(SessionRootConfig {
+                path: self.root_dir.with_file_name(ARCHIVED_SESSIONS_DIR),
+                label: DEFAULT_ROOT_LABEL.to_string(),
+                source: LogSourceKind::default(),
+            });
+        }
+        roots
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionRootConfig {
+    pub path: PathBuf,
+    /// Stored on each session found under `path`.
+    pub label: String,
+    /// Transcript format of the logs under `path`.
+    #[serde(default)]
+    pub source: LogSourceKind,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSourceKind {
+    #[default]
+    Codex,
+}
+
+impl LogSourceKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            LogSourceKind::Codex => "codex",
         }
     }
 }
@@ -118,6 +197,11 @@ pub struct PricingConfig {
     pub currency: String,
     #[serde(default)]
     pub remote: RemotePricingConfig,
+    /// Per-1M prices keyed by model prefix; these win over remote prices.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, PriceOverrideConfig>,
+    #[serde(default)]
+    pub fx: FxConfig,
 }
 
 impl Default for PricingConfig {
@@ -125,6 +209,8 @@ impl Default for PricingConfig {
         Self {
             currency: default_currency(),
             remote: RemotePricingConfig::default(),
+            overrides: BTreeMap::new(),
+            fx: FxConfig::default(),
         }
     }
 }
@@ -151,12 +237,96 @@ impl Default for RemotePricingConfig {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceOverrideConfig {
+    pub prompt_per_1m: f64,
+    #[serde(default)]
+    pub cached_prompt_per_1m: Option<f64>,
+    pub completion_per_1m: f64,
+    /// Prompt size above which the `long_*` rates apply to the whole turn.
+    #[serde(default)]
+    pub long_context_threshold: Option<u64>,
+    #[serde(default)]
+    pub long_prompt_per_1m: Option<f64>,
+    #[serde(default)]
+    pub long_cached_prompt_per_1m: Option<f64>,
+    #[serde(default)]
+    pub long_completion_per_1m: Option<f64>,
+    /// First local day the price applies to; omitted means all usage.
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    pub effective_from: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct FxConfig {
+    /// Currency costs and budgets are shown in. Each turn's or day's USD cost
+    /// is converted at the rate in force on its own date when read.
+    #[serde(default)]
+    pub display_currency: Option<String>,
+    /// Units of each currency per 1 USD, used for dates before the first
+    /// `rates_file` row (or all dates without one).
+    #[serde(default)]
+    pub rates: BTreeMap<String, f64>,
+    /// CSV of `date,currency,rate` rows, each in force from its date until the
+    /// next row for that currency.
+    #[serde(default)]
+    pub rates_file: Option<PathBuf>,
+}
+
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct AlertConfig {
+    /// Limits in USD, converted at the exchange rate of each budgeted period.
     #[serde(default)]
     pub daily_budget_usd: Option<f64>,
     #[serde(default)]
     pub monthly_budget_usd: Option<f64>,
+    /// Limits in the display currency; these win over the USD ones.
+    #[serde(default)]
+    pub daily_budget: Option<f64>,
+    #[serde(default)]
+    pub monthly_budget: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitConfig {
+    /// Periodically link sessions to commits authored in their `cwd`.
+    #[serde(default)]
+    pub attribute_commits: bool,
+    #[serde(default = "default_git_interval")]
+    pub interval_secs: u64,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            attribute_commits: false,
+            interval_secs: default_git_interval(),
+        }
+    }
+}
+
+/// Accepts both TOML dates (`2025-11-01`) and quoted `YYYY-MM-DD` strings.
+fn deserialize_optional_date<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DateValue {
+        Toml(toml::value::Datetime),
+        Text(String),
+    }
+
+    let text = match Option::<DateValue>::deserialize(deserializer)? {
+        Some(DateValue::Toml(value)) => value.to_string(),
+        Some(DateValue::Text(value)) => value,
+        None => return Ok(None),
+    };
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map(Some)
+        .map_err(|err| D::Error::custom(format!("invalid date {text:?}: {err}")))
 }
 
 fn default_database_path() -> PathBuf {
@@ -183,10 +353,43 @@ fn default_sessions_root() -> PathBuf {
     }
 }
 
+/// Parses `CODEX_USAGE_SESSION_ROOTS`: a list of paths in the platform's
+/// `PATH` format, each optionally prefixed with `label=`.
+fn parse_env_roots(value: &str) -> Vec<SessionRootConfig> {
+    env::split_paths(value)
+        .filter(|path| !path.as_os_str().is_empty())
+        .map(|path| {
+            let entry = path.to_string_lossy();
+            match entry.split_once('=') {
+                Some((label, root)) if !label.trim().is_empty() && !root.is_empty() => {
+                    SessionRootConfig {
+                        path: PathBuf::from(root),
+                        label: label.trim().to_string(),
+                        source: LogSourceKind::default(),
+                    }
+                }
+                _ => SessionRootConfig {
+                    path,
+                    label: DEFAULT_ROOT_LABEL.to_string(),
+                    source: LogSourceKind::default(),
+                },
+            }
+        })
+        .collect()
+}
+
 fn default_sessions_poll_interval() -> u64 {
     2
 }
 
+fn default_sessions_watch() -> bool {
+    true
+}
+
+fn default_sessions_reconcile_interval() -> u64 {
+    300
+}
+
 fn default_currency() -> String {
     "USD".to_string()
 }
@@ -204,6 +407,10 @@ fn default_pricing_timeout_secs() -> u64 {
     5
 }
 
+fn default_git_interval() -> u64 {
+    600
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,6 +441,15 @@ mod tests {
             url = "https://example.com/pricing.json"
             refresh_interval_hours = 12
             timeout_secs = 9
+
+            [pricing.overrides."gpt-5.1-codex"]
+            prompt_per_1m = 1.0
+            completion_per_1m = 8.0
+            effective_from = 2025-11-01
+
+            [pricing.fx]
+            display_currency = "EUR"
+            rates = { EUR = 0.92 }
         "#;
         fs::write(file.path(), toml).unwrap();
 
@@ -247,6 +463,12 @@ mod tests {
         );
         assert_eq!(config.pricing.remote.refresh_interval_hours, 12);
         assert_eq!(config.pricing.remote.timeout_secs, 9);
+        let custom = &config.pricing.overrides["gpt-5.1-codex"];
+        assert_eq!(custom.completion_per_1m, 8.0);
+        assert_eq!(custom.cached_prompt_per_1m, None);
+        assert_eq!(custom.effective_from, NaiveDate::from_ymd_opt(2025, 11, 1));
+        assert_eq!(config.pricing.fx.display_currency.as_deref(), Some("EUR"));
+        assert_eq!(config.pricing.fx.rates["EUR"], 0.92);
     }
 
     #[test]
@@ -261,6 +483,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sessions_dir_env_is_a_single_path() {
+        let _lock = ENV_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap();
+        let _roots_guard = EnvGuard::unset("CODEX_USAGE_SESSION_ROOTS");
+        let _dir_guard = EnvGuard::set("CODEX_USAGE_SESSIONS_DIR", "/srv/a=b:c/logs");
+
+        let config = AppConfig::load(None).unwrap();
+        assert_eq!(
+            config.sessions.session_roots(),
+            vec![SessionRootConfig {
+                path: PathBuf::from("/srv/a=b:c/logs"),
+                label: DEFAULT_ROOT_LABEL.to_string(),
+                source: LogSourceKind::default(),
+            }]
+        );
+
+        let _roots_guard = EnvGuard::set("CODEX_USAGE_SESSION_ROOTS", "work=/srv/work");
+        let config = AppConfig::load(None).unwrap();
+        let roots = config.sessions.session_roots();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].label, "work");
+    }
+
+    #[test]
+    fn session_roots_fall_back_to_root_dir() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [sessions]
+            root_dir = "/home/me/.codex/sessions"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.sessions.session_roots(),
+            vec![
+                SessionRootConfig {
+                    path: PathBuf::from("/home/me/.codex/sessions"),
+                    label: DEFAULT_ROOT_LABEL.to_string(),
+                    source: LogSourceKind::default(),
+                },
+                SessionRootConfig {
+                    path: PathBuf::from("/home/me/.codex/archived_sessions"),
+                    label: DEFAULT_ROOT_LABEL.to_string(),
+                    source: LogSourceKind::default(),
+                },
+            ]
+        );
+
+        let config: AppConfig = toml::from_str(
+            r#"
+            [[sessions.roots]]
+            path = "/home/me/.codex-work/sessions"
+            label = "work"
+
+            [[sessions.roots]]
+            path = "/mnt/alex/sessions"
+            label = "alex"
+        "#,
+        )
+        .unwrap();
+        let labels: Vec<String> = config
+            .sessions
+            .session_roots()
+            .into_iter()
+            .map(|root| root.label)
+            .collect();
+        assert_eq!(labels, ["work", "alex"]);
+
+        let roots = parse_env_roots("work=/srv/work:/srv/other");
+        assert_eq!(roots[0].label, "work");
+        assert_eq!(roots[0].path, PathBuf::from("/srv/work"));
+        assert_eq!(roots[1].label, DEFAULT_ROOT_LABEL);
+    }
+
     struct EnvGuard {
         key: &'static str,
         previous: Option<String>,
@@ -0,0 +1,151 @@
+use crate::{
+    config::AppConfig,
+    ingest,
+    storage::{IngestErrorRow, Storage, UsageMismatchRow},
+};
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local, Utc};
+use std::{collections::HashSet, io::Write, path::PathBuf};
+
+const SAMPLE_MAX_CHARS: usize = 120;
+const RECENT_MISMATCHES: usize = 10;
+
+/// Prints where data comes from and what could not be ingested. Fails when
+/// ingest errors are recorded so scripts can alert on format drift.
+pub async fn run(config: &AppConfig, storage: &Storage, out: &mut impl Write) -> Result<()> {
+    writeln!(
+        out,
+        "Database:      {}",
+        config.storage.database_path.display()
+    )?;
+    writeln!(
+        out,
+        "Last ingest:   {}",
+        format_time(storage.last_ingest_activity().await?)
+    )?;
+    writeln!(
+        out,
+        "Latest event:  {}",
+        format_time(storage.last_ingest_timestamp().await?)
+    )?;
+
+    let tracked: HashSet<PathBuf> = storage
+        .load_ingest_state()
+        .await?
+        .into_iter()
+        .map(|state| state.path)
+        .collect();
+    writeln!(out)?;
+    writeln!(out, "Session roots:")?;
+    let mut untracked = 0usize;
+    for root in config.sessions.session_roots() {
+        let status = if root.path.exists() {
+            let files = ingest::log_files_in_root(&root)?;
+            untracked += files.iter().filter(|path| !tracked.contains(*path)).count();
+            format!("{} files", files.len())
+        } else {
+            "missing".to_string()
+        };
+        writeln!(
+            out,
+            "  {:<12} {:<6} {}  ({status})",
+            root.label,
+            root.source.label(),
+            root.path.display()
+        )?;
+    }
+    writeln!(out, "Tracked files: {}", tracked.len())?;
+    if untracked > 0 {
+        writeln!(out, "Not yet ingested: {untracked}")?;
+    }
+
+    // Mismatches are worth a look but are not failures: the ingestor already
+    // records the reported per-request usage.
+    let mismatches = storage.usage_mismatch_summary().await?;
+    writeln!(out)?;
+    if mismatches.count == 0 {
+        writeln!(out, "Usage cross-check: no mismatches")?;
+    } else {
+        let drift = mismatches.reported_tokens as i128 - mismatches.cumulative_tokens as i128;
+        writeln!(
+            out,
+            "Usage cross-check: {} turn(s) in {} session(s) where last_token_usage differs from the cumulative delta ({drift:+} tokens)",
+            mismatches.count, mismatches.sessions
+        )?;
+        for mismatch in storage.recent_usage_mismatches(RECENT_MISMATCHES).await? {
+            write_mismatch(out, &mismatch)?;
+        }
+    }
+
+    let errors = storage.list_ingest_errors().await?;
+    writeln!(out)?;
+    if errors.is_empty() {
+        writeln!(out, "Ingest errors: none")?;
+        return Ok(());
+    }
+    let total: u64 = errors.iter().map(|error| error.count).sum();
+    writeln!(
+        out,
+        "Ingest errors: {total} in {} file(s), most recent first",
+        errors
+            .iter()
+            .map(|error| &error.path)
+            .collect::<HashSet<_>>()
+            .len()
+    )?;
+    for error in &errors {
+        write_error(out, error)?;
+    }
+    bail!("{total} ingest error(s) recorded");
+}
+
+fn write_mismatch(out: &mut impl Write, mismatch: &UsageMismatchRow) -> Result<()> {
+    writeln!(
+        out,
+        "  {}  {}  {}  reported {} vs cumulative {} ({})",
+        format_time(Some(mismatch.timestamp)),
+        mismatch.session_id,
+        mismatch.model,
+        mismatch.reported_tokens,
+        mismatch.cumulative_tokens,
+        mismatch.fields
+    )?;
+    Ok(())
+}
+
+fn write_error(out: &mut impl Write, error: &IngestErrorRow) -> Result<()> {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "  [{}] x{}  {} @ byte {}",
+        error.kind,
+        error.count,
+        error.path.display(),
+        error.offset
+    )?;
+    writeln!(out, "    {}", error.message)?;
+    writeln!(
+        out,
+        "    first seen {}, last seen {}",
+        format_time(Some(error.first_seen)),
+        format_time(Some(error.last_seen))
+    )?;
+    if !error.sample.is_empty() {
+        let mut sample: String = error.sample.chars().take(SAMPLE_MAX_CHARS).collect();
+        if error.sample.chars().count() > SAMPLE_MAX_CHARS {
+            sample.push('…');
+        }
+        writeln!(out, "    sample: {sample}")?;
+    }
+    Ok(())
+}
+
+fn format_time(value: Option<DateTime<Utc>>) -> String {
+    value
+        .map(|ts| {
+            ts.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "never".to_string())
+}
@@ -0,0 +1,214 @@
+use crate::config::FxConfig;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::{path::Path, sync::OnceLock};
+
+const BASE_CURRENCY: &str = "USD";
+
+static DISPLAY: OnceLock<DisplayCurrency> = OnceLock::new();
+
+/// Currency costs are shown in. Costs are priced and stored in USD; storage
+/// converts them when reading, at the rate in force on each turn's or day's
+/// date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayCurrency {
+    code: String,
+    /// Units of `code` per 1 USD before the first dated rate (or always, when
+    /// there are none).
+    base_rate: f64,
+    /// Rates from the `rates_file`, each in force from its date, sorted.
+    dated_rates: Vec<(NaiveDate, f64)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateRecord {
+    date: NaiveDate,
+    currency: String,
+    rate: f64,
+}
+
+impl DisplayCurrency {
+    pub fn usd() -> Self {
+        Self {
+            code: BASE_CURRENCY.to_string(),
+            base_rate: 1.0,
+            dated_rates: Vec::new(),
+        }
+    }
+
+    /// Loads the display currency with its `rates_file` rows and static
+    /// `rates` entry. Dates before the first row use the static rate, or the
+    /// first row's rate when there is none.
+    pub fn from_config(config: &FxConfig) -> Result<Self> {
+        let code = match config.display_currency.as_deref().map(str::trim) {
+            Some(code) if !code.is_empty() => code.to_ascii_uppercase(),
+            _ => return Ok(Self::usd()),
+        };
+        if code == BASE_CURRENCY {
+            return Ok(Self::usd());
+        }
+
+        let dated_rates = match &config.rates_file {
+            Some(path) => file_rates(path, &code)?,
+            None => Vec::new(),
+        };
+        let static_rate = config
+            .rates
+            .iter()
+            .find(|(currency, _)| currency.eq_ignore_ascii_case(&code))
+            .map(|(_, rate)| *rate);
+        let Some(base_rate) = static_rate.or_else(|| dated_rates.first().map(|(_, rate)| *rate))
+        else {
+            bail!("no exchange rate configured for display currency {code}");
+        };
+        for rate in std::iter::once(base_rate).chain(dated_rates.iter().map(|(_, rate)| *rate)) {
+            if !rate.is_finite() || rate <= 0.0 {
+                bail!("invalid exchange rate {rate} for {code}");
+            }
+        }
+        Ok(Self {
+            code,
+            base_rate,
+            dated_rates,
+        })
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Units of the display currency per 1 USD on `date`.
+    pub fn rate_on(&self, date: NaiveDate) -> f64 {
+        self.dated_rates
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= date)
+            .map_or(self.base_rate, |(_, rate)| *rate)
+    }
+
+    /// The rate before the first dated one, and the dated rates oldest first;
+    /// `1.0` and none for USD.
+    pub fn rates(&self) -> (f64, &[(NaiveDate, f64)]) {
+        (self.base_rate, &self.dated_rates)
+    }
+
+    /// Formats an amount in the display currency with its symbol.
+    pub fn format(&self, amount: f64, decimals: usize) -> String {
+        match symbol(&self.code) {
+            Some(symbol) => format!("{symbol}{amount:.decimals$}"),
+            None => format!("{amount:.decimals$} {}", self.code),
+        }
+    }
+}
+
+/// Sets the display currency for the rest of the process; later calls are ignored.
+pub fn install(currency: DisplayCurrency) {
+    let _ = DISPLAY.set(currency);
+}
+
+/// Display currency installed at startup, or USD if none was.
+pub fn display() -> &'static DisplayCurrency {
+    DISPLAY.get_or_init(DisplayCurrency::usd)
+}
+
+fn symbol(code: &str) -> Option<&'static str> {
+    match code {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" => Some("¥"),
+        _ => None,
+    }
+}
+
+fn file_rates(path: &Path, code: &str) -> Result<Vec<(NaiveDate, f64)>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("failed to read exchange rates {}", path.display()))?;
+    let mut rates = Vec::new();
+    for record in reader.deserialize::<RateRecord>() {
+        let record =
+            record.with_context(|| format!("invalid exchange rate row in {}", path.display()))?;
+        if record.currency.trim().eq_ignore_ascii_case(code) {
+            rates.push((record.date, record.rate));
+        }
+    }
+    // A later row for the same date wins.
+    rates.sort_by_key(|(date, _)| *date);
+    rates.reverse();
+    rates.dedup_by_key(|(date, _)| *date);
+    rates.reverse();
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::BTreeMap, fs};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn rates_file_rows_are_kept_with_their_dates() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(
+            file.path(),
+            "date,currency,rate\n\
+             2025-01-01,EUR,0.90\n\
+             2025-06-01,eur,0.95\n\
+             2025-12-01,EUR,0.99\n\
+             2025-06-01,GBP,0.80\n",
+        )
+        .unwrap();
+        let mut config = FxConfig {
+            display_currency: Some("eur".to_string()),
+            rates: BTreeMap::from([("EUR".to_string(), 0.5)]),
+            rates_file: Some(file.path().to_path_buf()),
+        };
+        let date = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+
+        let currency = DisplayCurrency::from_config(&config).unwrap();
+        assert_eq!(currency.code(), "EUR");
+        assert_eq!(currency.rate_on(date(7, 1)), 0.95);
+        assert_eq!(currency.rate_on(date(12, 1)), 0.99);
+        // Before the first dated row the static rate applies.
+        assert_eq!(
+            currency.rate_on(NaiveDate::from_ymd_opt(2024, 12, 1).unwrap()),
+            0.5
+        );
+        assert_eq!(
+            currency.rates(),
+            (
+                0.5,
+                [(date(1, 1), 0.90), (date(6, 1), 0.95), (date(12, 1), 0.99)].as_slice()
+            )
+        );
+        assert_eq!(currency.format(9.5, 2), "€9.50");
+
+        // Without a static rate the first row covers earlier dates too.
+        config.rates.clear();
+        let currency = DisplayCurrency::from_config(&config).unwrap();
+        assert_eq!(currency.rates().0, 0.90);
+        assert_eq!(
+            currency.rate_on(NaiveDate::from_ymd_opt(2024, 12, 1).unwrap()),
+            0.90
+        );
+    }
+
+    #[test]
+    fn missing_rate_is_an_error_and_usd_needs_none() {
+        let mut config = FxConfig {
+            display_currency: Some("CHF".to_string()),
+            ..FxConfig::default()
+        };
+        assert!(DisplayCurrency::from_config(&config).is_err());
+
+        config.rates.insert("CHF".to_string(), 0.8);
+        let currency = DisplayCurrency::from_config(&config).unwrap();
+        assert_eq!(currency.format(0.8, 2), "0.80 CHF");
+
+        config.display_currency = Some("USD".to_string());
+        let currency = DisplayCurrency::from_config(&config).unwrap();
+        assert_eq!(currency, DisplayCurrency::usd());
+        assert_eq!(currency.rates(), (1.0, [].as_slice()));
+    }
+}
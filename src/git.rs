@@ -0,0 +1,342 @@
+use crate::storage::{GitScanTarget, SessionCommit, Storage};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::Command,
+};
+use tokio::task;
+
+pub const SCAN_OK: &str = "ok";
+/// The session's `cwd` no longer exists (moved or deleted).
+pub const SCAN_MISSING: &str = "missing";
+/// The `cwd` exists but `git log` failed there, e.g. it is no longer a repo.
+pub const SCAN_FAILED: &str = "failed";
+
+const FIELD_SEPARATOR: char = '\u{1f}';
+const LOG_FORMAT: &str = "--format=%H%x1f%aI%x1f%ae%x1f%S%x1f%s";
+const DETAIL_MAX_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttributionSummary {
+    pub sessions: usize,
+    pub commits: usize,
+    pub missing: usize,
+    pub failed: usize,
+}
+
+enum ScanOutcome {
+    Commits(Vec<SessionCommit>),
+    Missing,
+    Failed(String),
+}
+
+/// Links every session with new events since its last scan to the commits
+/// authored in its `cwd` between its first and last event. Sessions whose
+/// directory is gone or no longer a repo keep the commits found earlier.
+pub async fn attribute_commits(storage: &Storage) -> Result<AttributionSummary> {
+    let targets = storage.sessions_pending_git_scan().await?;
+    let mut summary = AttributionSummary::default();
+    for target in targets {
+        let scan_target = target.clone();
+        let outcome = task::spawn_blocking(move || scan_session(&scan_target))
+            .await
+            .with_context(|| "git scan task panicked")??;
+        let now = Utc::now();
+        summary.sessions += 1;
+        match outcome {
+            ScanOutcome::Commits(commits) => {
+                summary.commits += commits.len();
+                storage
+                    .record_git_scan(&target, now, SCAN_OK, None, Some(&commits))
+                    .await?;
+            }
+            ScanOutcome::Missing => {
+                summary.missing += 1;
+                storage
+                    .record_git_scan(&target, now, SCAN_MISSING, None, None)
+                    .await?;
+            }
+            ScanOutcome::Failed(detail) => {
+                summary.failed += 1;
+                tracing::debug!(
+                    session_id = %target.session_id,
+                    cwd = %target.cwd.display(),
+                    %detail,
+                    "git scan failed"
+                );
+                storage
+                    .record_git_scan(&target, now, SCAN_FAILED, Some(&detail), None)
+                    .await?;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Runs one attribution pass and prints what it found.
+pub async fn run(storage: &Storage, out: &mut impl Write) -> Result<()> {
+    let summary = attribute_commits(storage).await?;
+    writeln!(out, "Sessions scanned: {}", summary.sessions)?;
+    writeln!(out, "Commits linked:   {}", summary.commits)?;
+    if summary.missing > 0 {
+        writeln!(out, "Missing repos:    {}", summary.missing)?;
+    }
+    if summary.failed > 0 {
+        writeln!(out, "Failed scans:     {}", summary.failed)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn scan_session(target: &GitScanTarget) -> Result<ScanOutcome> {
+    if !target.cwd.is_dir() {
+        return Ok(ScanOutcome::Missing);
+    }
+    // `--since` filters on the committer date, which is never before the
+    // author date, so the author window is applied after parsing.
+    let since = format!("--since={}", target.started_at.to_rfc3339());
+    let output = match git(
+        &target.cwd,
+        &["log", "--branches", "--source", &since, LOG_FORMAT],
+    )? {
+        Ok(output) => output,
+        Err(detail) => return Ok(ScanOutcome::Failed(detail)),
+    };
+    let author = git(&target.cwd, &["config", "user.email"])?
+        .ok()
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+    Ok(ScanOutcome::Commits(parse_log(
+        &output,
+        author.as_deref(),
+        target.started_at,
+        target.last_event_at,
+    )))
+}
+
+/// Runs git in `dir`. The outer error means git itself could not be run; the
+/// inner one carries stderr of a failed command.
+fn git(dir: &Path, args: &[&str]) -> Result<std::result::Result<String, String>> {
+    let output = match Command::new("git").arg("-C").arg(dir).args(args).output() {
+        Ok(output) => output,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            bail!("git is not installed or not on PATH")
+        }
+        Err(err) => return Err(err).with_context(|| "failed to run git"),
+    };
+    if output.status.success() {
+        Ok(Ok(String::from_utf8_lossy(&output.stdout).into_owned()))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Ok(Err(stderr.trim().chars().take(DETAIL_MAX_CHARS).collect()))
+    }
+}
+
+/// Parses `git log` output in `LOG_FORMAT`, keeping commits by `author` (when
+/// known) authored within `[start, end]`.
+fn parse_log(
+    output: &str,
+    author: Option<&str>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<SessionCommit> {
+    let mut commits: Vec<SessionCommit> = Vec::new();
+    for line in output.lines() {
+        let mut fields = line.splitn(5, FIELD_SEPARATOR);
+        let (Some(sha), Some(authored_at), Some(email), Some(source), Some(subject)) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) else {
+            continue;
+        };
+        if author.is_some_and(|author| !author.eq_ignore_ascii_case(email)) {
+            continue;
+        }
+        let Ok(authored_at) = DateTime::parse_from_rfc3339(authored_at) else {
+            continue;
+        };
+        let authored_at = authored_at.with_timezone(&Utc);
+        if authored_at < start || authored_at > end {
+            continue;
+        }
+        // A commit on several branches is listed once, under the first branch
+        // that reaches it.
+        if commits.iter().any(|commit| commit.sha == sha) {
+            continue;
+        }
+        let branch = source.strip_prefix("refs/heads/").unwrap_or(source);
+        commits.push(SessionCommit {
+            sha: sha.to_string(),
+            authored_at,
+            branch: (!branch.is_empty()).then(|| branch.to_string()),
+            subject: subject.to_string(),
+        });
+    }
+    commits.sort_by_key(|commit| commit.authored_at);
+    commits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SessionMeta;
+    use chrono::{Duration, TimeZone};
+    use tempfile::NamedTempFile;
+
+    fn log_line(sha: &str, authored_at: &str, email: &str, source: &str, subject: &str) -> String {
+        [sha, authored_at, email, source, subject].join("\u{1f}")
+    }
+
+    #[test]
+    fn parse_log_keeps_own_commits_inside_the_session_window() {
+        let start = Utc.with_ymd_and_hms(2025, 11, 3, 9, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 11, 3, 12, 0, 0).unwrap();
+        let output = [
+            log_line(
+                "c3",
+                "2025-11-03T13:30:00+01:00",
+                "me@x.dev",
+                "feat",
+                "after",
+            ),
+            log_line(
+                "c2",
+                "2025-11-03T11:00:00+01:00",
+                "Me@X.dev",
+                "refs/heads/feat",
+                "fix: b",
+            ),
+            log_line(
+                "c1",
+                "2025-11-03T09:30:00Z",
+                "me@x.dev",
+                "main",
+                "feat: a | b",
+            ),
+            log_line(
+                "o1",
+                "2025-11-03T10:00:00Z",
+                "other@x.dev",
+                "main",
+                "theirs",
+            ),
+            log_line("c0", "2025-11-03T08:59:59Z", "me@x.dev", "main", "before"),
+            log_line(
+                "c1",
+                "2025-11-03T09:30:00Z",
+                "me@x.dev",
+                "feat",
+                "feat: a | b",
+            ),
+        ]
+        .join("\n");
+
+        let commits = parse_log(&output, Some("me@x.dev"), start, end);
+        let summary: Vec<(&str, Option<&str>, &str)> = commits
+            .iter()
+            .map(|c| (c.sha.as_str(), c.branch.as_deref(), c.subject.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("c1", Some("main"), "feat: a | b"),
+                ("c2", Some("feat"), "fix: b"),
+            ]
+        );
+        assert_eq!(
+            commits[1].authored_at,
+            Utc.with_ymd_and_hms(2025, 11, 3, 10, 0, 0).unwrap()
+        );
+
+        let anyone = parse_log(&output, None, start, end);
+        assert_eq!(anyone.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn moved_repo_keeps_commits_from_earlier_scans() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+        let repo_dir = tempfile::tempdir().unwrap();
+        let started_at = Utc.with_ymd_and_hms(2025, 11, 3, 9, 0, 0).unwrap();
+        let mut meta = SessionMeta {
+            session_id: "sess-1".to_string(),
+            started_at,
+            last_event_at: started_at + Duration::hours(1),
+            cwd: Some(repo_dir.path().to_string_lossy().into_owned()),
+            repo_url: None,
+            repo_branch: Some("main".to_string()),
+            repo_commit: None,
+            model_provider: None,
+            subagent: None,
+            last_model: None,
+            root_label: None,
+        };
+        let mut tx = storage.begin_tx().await.unwrap();
+        storage
+            .upsert_session_meta_tx(&mut tx, &meta)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let targets = storage.sessions_pending_git_scan().await.unwrap();
+        assert_eq!(targets.len(), 1);
+        let commit = SessionCommit {
+            sha: "abc123".to_string(),
+            authored_at: started_at + Duration::minutes(30),
+            branch: Some("main".to_string()),
+            subject: "Add feature".to_string(),
+        };
+        storage
+            .record_git_scan(
+                &targets[0],
+                Utc::now(),
+                SCAN_OK,
+                None,
+                Some(std::slice::from_ref(&commit)),
+            )
+            .await
+            .unwrap();
+        assert!(
+            storage
+                .sessions_pending_git_scan()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        drop(repo_dir);
+        meta.last_event_at += Duration::hours(1);
+        let mut tx = storage.begin_tx().await.unwrap();
+        storage
+            .upsert_session_meta_tx(&mut tx, &meta)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let summary = attribute_commits(&storage).await.unwrap();
+        assert_eq!(
+            summary,
+            AttributionSummary {
+                sessions: 1,
+                missing: 1,
+                ..AttributionSummary::default()
+            }
+        );
+        assert_eq!(storage.session_commits("sess-1").await.unwrap(), [commit]);
+        let scan = storage.session_git_scan("sess-1").await.unwrap().unwrap();
+        assert_eq!(scan.status, SCAN_MISSING);
+        assert!(
+            storage
+                .sessions_pending_git_scan()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
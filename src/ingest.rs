@@ -1,78 +1,77 @@
 use crate::{
-    config::AppConfig,
-    storage::{IngestStateRow, SessionMeta, Storage},
+    config::{AppConfig, DEFAULT_ROOT_LABEL, LogSourceKind, SessionRootConfig},
+    log_source::{self, LogEvent, LogSource, ParseError, ParsedLine, TokenTotals},
+    storage::{
+        BackfillProgress, DEFAULT_SERVICE_TIER, IngestStateRow, NewIngestError, NewTurn,
+        NewUsageMismatch, RateLimitSnapshot, SessionLinkSource, SessionMeta, Storage, ToolOutcome,
+        TurnPolicy,
+    },
 };
 use anyhow::{Context, Result, anyhow};
 use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde_json::Value;
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{Read, Seek, SeekFrom},
+    collections::{HashMap, HashSet},
+    fs::{File, Metadata},
+    future::Future,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
     time::Duration,
 };
-use tokio::{sync::oneshot, task::JoinHandle, time};
+use tokio::{
+    sync::{Mutex, mpsc, oneshot},
+    task::{JoinHandle, JoinSet},
+    time,
+};
 
 const TITLE_MAX_CHARS: usize = 200;
 const SUMMARY_MAX_CHARS: usize = 160;
 const MESSAGE_DEDUPE_WINDOW_SECS: i64 = 2;
+const HEAD_SCAN_BYTES: u64 = 64 * 1024;
+/// Lines applied and committed per transaction; bounds memory and lets an
+/// interrupted backfill resume after the last committed chunk.
+const CHUNK_MAX_LINES: usize = 1000;
+const CHUNK_MAX_BYTES: usize = 8 * 1024 * 1024;
+const BACKFILL_MAX_WORKERS: usize = 8;
+const BACKFILL_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// `ingest_errors` kind for compressed files that fail to decompress.
+const READ_FAILED_ERROR: &str = "read_failed";
+/// `ingest_errors` kind for parsed events that could not be stored.
+const EVENT_FAILED_ERROR: &str = "event_failed";
+/// `session_events` kind for a cumulative token counter that went backwards.
+pub const TOKEN_RESET_EVENT: &str = "token_reset";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SessionFileKind {
+    Plain,
+    Gzip,
+    Zstd,
+}
 
-#[derive(Clone, Copy, Debug, Default)]
-struct TokenTotals {
-    input_tokens: u64,
-    cached_input_tokens: u64,
-    output_tokens: u64,
-    reasoning_output_tokens: u64,
-    total_tokens: u64,
-}
-
-impl TokenTotals {
-    fn from_value(value: &Value) -> Option<Self> {
-        Some(Self {
-            input_tokens: value.get("input_tokens")?.as_u64()?,
-            cached_input_tokens: value
-                .get("cached_input_tokens")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0),
-            output_tokens: value.get("output_tokens")?.as_u64()?,
-            reasoning_output_tokens: value
-                .get("reasoning_output_tokens")
-                .and_then(|v| v.as_u64())
-                .unwrap_or(0),
-            total_tokens: value.get("total_tokens")?.as_u64()?,
-        })
-    }
-
-    fn is_zero(self) -> bool {
-        self.input_tokens == 0
-            && self.cached_input_tokens == 0
-            && self.output_tokens == 0
-            && self.reasoning_output_tokens == 0
-            && self.total_tokens == 0
+impl SessionFileKind {
+    /// How to read `path` if `source` accepts its name once any compression
+    /// suffix is removed.
+    fn of(path: &Path, source: &dyn LogSource) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (kind, base) = if let Some(base) = name.strip_suffix(".gz") {
+            (Self::Gzip, base)
+        } else if let Some(base) = name.strip_suffix(".zst") {
+            (Self::Zstd, base)
+        } else {
+            (Self::Plain, name)
+        };
+        source.is_log_file(base).then_some(kind)
     }
 
-    fn any_decreased(self, previous: Self) -> bool {
-        self.input_tokens < previous.input_tokens
-            || self.cached_input_tokens < previous.cached_input_tokens
-            || self.output_tokens < previous.output_tokens
-            || self.reasoning_output_tokens < previous.reasoning_output_tokens
-            || self.total_tokens < previous.total_tokens
-    }
-
-    fn saturating_sub(self, previous: Self) -> Self {
-        Self {
-            input_tokens: self.input_tokens.saturating_sub(previous.input_tokens),
-            cached_input_tokens: self
-                .cached_input_tokens
-                .saturating_sub(previous.cached_input_tokens),
-            output_tokens: self.output_tokens.saturating_sub(previous.output_tokens),
-            reasoning_output_tokens: self
-                .reasoning_output_tokens
-                .saturating_sub(previous.reasoning_output_tokens),
-            total_tokens: self.total_tokens.saturating_sub(previous.total_tokens),
-        }
+    fn in_roots(roots: &[SessionRootConfig], path: &Path) -> Option<Self> {
+        let root = root_for(roots, path)?;
+        Self::of(path, log_source::source_for(root.source))
     }
 }
 
@@ -96,15 +95,24 @@ struct FileState {
     last_committed: TokenTotals,
     current_model: Option<String>,
     current_effort: Option<String>,
+    current_service_tier: Option<String>,
+    current_approval_policy: Option<String>,
+    current_sandbox_policy: Option<String>,
+    current_cwd: Option<String>,
     current_message_id: Option<i64>,
     current_message_seq: u64,
     pending_note: Option<String>,
     pending_note_seq: u64,
     used_note_seq: u64,
+    /// Patches were recorded that no turn has counted yet.
+    unattributed_patches: bool,
     pending_title: Option<String>,
     pending_summary: Option<String>,
     pending_messages: Vec<PendingMessage>,
     last_user_message: Option<LastUserMessage>,
+    completed: bool,
+    identity: Option<(u64, u64)>,
+    head_fingerprint: Option<String>,
 }
 
 impl FileState {
@@ -116,15 +124,23 @@ impl FileState {
             last_committed: TokenTotals::default(),
             current_model: None,
             current_effort: None,
+            current_service_tier: None,
+            current_approval_policy: None,
+            current_sandbox_policy: None,
+            current_cwd: None,
             current_message_id: None,
             current_message_seq: 0,
             pending_note: None,
             pending_note_seq: 0,
             used_note_seq: 0,
+            unattributed_patches: false,
             pending_title: None,
             pending_summary: None,
             pending_messages: Vec::new(),
             last_user_message: None,
+            completed: false,
+            identity: None,
+            head_fingerprint: None,
         }
     }
 
@@ -148,15 +164,24 @@ impl FileState {
             },
             current_model: state.current_model.clone(),
             current_effort: state.current_effort.clone(),
+            current_service_tier: state.current_service_tier.clone(),
+            current_approval_policy: state.current_approval_policy.clone(),
+            current_sandbox_policy: state.current_sandbox_policy.clone(),
+            current_cwd: state.current_cwd.clone(),
             current_message_id: state.current_message_id,
             current_message_seq: state.current_message_seq,
             pending_note: None,
             pending_note_seq: 0,
             used_note_seq: 0,
+            // Patches recorded before a restart may still be waiting.
+            unattributed_patches: true,
             pending_title: None,
             pending_summary: None,
             pending_messages: Vec::new(),
             last_user_message: None,
+            completed: state.completed,
+            identity: state.device.zip(state.inode),
+            head_fingerprint: state.head_fingerprint.clone(),
         }
     }
 
@@ -179,6 +204,14 @@ impl FileState {
             current_message_seq: self.current_message_seq,
             current_model: self.current_model.clone(),
             current_effort: self.current_effort.clone(),
+            current_service_tier: self.current_service_tier.clone(),
+            current_approval_policy: self.current_approval_policy.clone(),
+            current_sandbox_policy: self.current_sandbox_policy.clone(),
+            current_cwd: self.current_cwd.clone(),
+            completed: self.completed,
+            device: self.identity.map(|(device, _)| device),
+            inode: self.identity.map(|(_, inode)| inode),
+            head_fingerprint: self.head_fingerprint.clone(),
         }
     }
 }
@@ -202,25 +235,71 @@ impl IngestHandle {
 
 pub async fn spawn(config: Arc<AppConfig>, storage: Storage) -> Result<IngestHandle> {
     let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
-    let root = config.sessions.root_dir.clone();
+    let roots = config.sessions.session_roots();
     let poll_interval = Duration::from_secs(config.sessions.poll_interval_secs.max(1));
+    let reconcile_interval = Duration::from_secs(config.sessions.reconcile_interval_secs.max(1));
+    let watch_enabled = config.sessions.watch;
 
-    let mut ingestor = SessionIngestor::new(root, storage).await?;
+    let mut ingestor = SessionIngestor::new(roots.clone(), storage).await?;
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
 
     let join = tokio::spawn(async move {
-        let mut ticker = time::interval(poll_interval);
+        let mut watcher = if watch_enabled {
+            start_watcher(&roots, event_tx.clone())
+        } else {
+            None
+        };
+        let mut ticker = time::interval(if watcher.is_some() {
+            reconcile_interval
+        } else {
+            poll_interval
+        });
         loop {
+            let mut watcher_started = false;
             tokio::select! {
                 _ = ticker.tick() => {
                     if let Err(err) = ingestor.scan_once().await {
                         tracing::warn!(error = %err, "session ingest scan failed");
                     }
+                    if watch_enabled && watcher.is_none() {
+                        watcher = start_watcher(&roots, event_tx.clone());
+                        watcher_started = watcher.is_some();
+                    }
+                }
+                Some(event) = event_rx.recv() => {
+                    let mut changed = HashSet::new();
+                    let mut rescan = false;
+                    let mut next = Some(event);
+                    while let Some(event) = next {
+                        match event {
+                            WatchEvent::Changed(path) => {
+                                changed.insert(path);
+                            }
+                            WatchEvent::Rescan => rescan = true,
+                        }
+                        next = event_rx.try_recv().ok();
+                    }
+                    let result = if rescan {
+                        ingestor.scan_once().await
+                    } else {
+                        ingestor.process_changed(changed).await
+                    };
+                    if let Err(err) = result {
+                        tracing::warn!(error = %err, "session ingest update failed");
+                    }
                 }
                 _ = &mut shutdown_rx => {
                     break;
                 }
             }
+            if watcher_started {
+                ticker = time::interval_at(
+                    time::Instant::now() + reconcile_interval,
+                    reconcile_interval,
+                );
+            }
         }
+        drop(watcher);
         Ok(())
     });
 
@@ -230,256 +309,1089 @@ pub async fn spawn(config: Arc<AppConfig>, storage: Storage) -> Result<IngestHan
     })
 }
 
+enum WatchEvent {
+    Changed(PathBuf),
+    Rescan,
+}
+
+/// Watches the session trees for appended or newly created logs. Returns
+/// `None` when no root exists or the platform watcher cannot be set up, in
+/// which case the caller falls back to polling. Roots created later are only
+/// picked up by the reconcile scan.
+fn start_watcher(
+    roots: &[SessionRootConfig],
+    events: mpsc::UnboundedSender<WatchEvent>,
+) -> Option<RecommendedWatcher> {
+    let existing: Vec<&Path> = roots
+        .iter()
+        .map(|root| root.path.as_path())
+        .filter(|path| path.exists())
+        .collect();
+    if existing.is_empty() {
+        tracing::debug!("no session root found; watcher not started");
+        return None;
+    }
+    let handler_roots = roots.to_vec();
+    let handler = move |result: notify::Result<notify::Event>| match result {
+        Ok(event) => {
+            if event.need_rescan() {
+                let _ = events.send(WatchEvent::Rescan);
+                return;
+            }
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            for path in event.paths {
+                if SessionFileKind::in_roots(&handler_roots, &path).is_some() {
+                    let _ = events.send(WatchEvent::Changed(path));
+                }
+            }
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "session watcher error");
+            let _ = events.send(WatchEvent::Rescan);
+        }
+    };
+    let mut watcher = match notify::recommended_watcher(handler) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to create session watcher; falling back to polling");
+            return None;
+        }
+    };
+    for root in existing {
+        if let Err(err) = watcher.watch(root, RecursiveMode::Recursive) {
+            tracing::warn!(error = %err, root = %root.display(), "failed to watch session root; falling back to polling");
+            return None;
+        }
+        tracing::info!(root = %root.display(), "watching session logs for changes");
+    }
+    Some(watcher)
+}
+
+/// Most specific configured root containing `path`.
+fn root_for<'a>(roots: &'a [SessionRootConfig], path: &Path) -> Option<&'a SessionRootConfig> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(&root.path))
+        .max_by_key(|root| root.path.components().count())
+}
+
+fn root_label_for<'a>(roots: &'a [SessionRootConfig], path: &Path) -> Option<&'a str> {
+    root_for(roots, path).map(|root| root.label.as_str())
+}
+
 struct SessionIngestor {
-    root: PathBuf,
-    storage: Storage,
+    worker: FileWorker,
     files: HashMap<PathBuf, FileState>,
+    /// Whether the startup scan, reported as a backfill, has run.
+    backfilled: bool,
+}
+
+/// A file with unread data, taken out of the ingestor's map while a worker
+/// reads it.
+struct FileJob {
+    path: PathBuf,
+    kind: SessionFileKind,
+    len: u64,
+    state: FileState,
+    start_offset: u64,
+    pending_bytes: u64,
+}
+
+/// Reads, parses and stores one file at a time. Clones share the database
+/// write lock, so a scan can run several workers concurrently.
+#[derive(Clone)]
+struct FileWorker {
+    roots: Arc<[SessionRootConfig]>,
+    storage: Storage,
+    /// SQLite has a single writer: workers read and parse concurrently but
+    /// apply and commit one chunk at a time, in file order per file.
+    write_lock: Arc<Mutex<()>>,
+    /// Bytes of plain files read so far by all workers, for backfill progress.
+    bytes_read: Arc<AtomicU64>,
 }
 
 impl SessionIngestor {
-    async fn new(root: PathBuf, storage: Storage) -> Result<Self> {
+    async fn new(roots: Vec<SessionRootConfig>, storage: Storage) -> Result<Self> {
         let mut files = HashMap::new();
+        let mut labels = Vec::new();
         let states = storage.load_ingest_state().await?;
         for state in states {
+            if let (Some(session_id), Some(label)) = (
+                state.session_id.as_ref(),
+                root_label_for(&roots, &state.path),
+            ) {
+                labels.push((session_id.clone(), label.to_string()));
+            }
             files.insert(state.path.clone(), FileState::from_state(&state));
         }
+        storage
+            .apply_session_root_labels(&labels)
+            .await
+            .with_context(|| "failed to label sessions by root")?;
+        // Left behind if a previous ingestor stopped mid-backfill.
+        storage.set_backfill_progress(None).await?;
         Ok(Self {
-            root,
-            storage,
+            worker: FileWorker {
+                roots: roots.into(),
+                storage,
+                write_lock: Arc::new(Mutex::new(())),
+                bytes_read: Arc::new(AtomicU64::new(0)),
+            },
             files,
+            backfilled: false,
         })
     }
 
     async fn scan_once(&mut self) -> Result<()> {
-        if !self.root.exists() {
-            tracing::debug!(root = %self.root.display(), "session root not found");
-            return Ok(());
+        let mut paths = Vec::new();
+        for root in self.worker.roots.iter() {
+            if !root.path.exists() {
+                tracing::debug!(root = %root.path.display(), "session root not found");
+                continue;
+            }
+            paths.extend(log_source::source_for(root.source).log_files(&root.path)?);
         }
 
-        let mut paths = Vec::new();
-        collect_jsonl_files(&self.root, &mut paths)?;
+        let mut jobs = Vec::new();
+        for path in paths {
+            if let Some(job) = self.prepare_file(&path).await? {
+                jobs.push(job);
+            }
+        }
+        let report_progress = !self.backfilled;
+        self.backfilled = true;
+        self.run_jobs(jobs, report_progress).await
+    }
 
+    /// Ingests the files reported by the watcher. A file that fails is logged
+    /// and left for a later event or the reconcile scan; the others go ahead.
+    async fn process_changed(&mut self, paths: HashSet<PathBuf>) -> Result<()> {
         for path in paths {
-            self.process_file(&path).await?;
+            if !path.is_file() {
+                continue;
+            }
+            let mut job = match self.prepare_file(&path).await {
+                Ok(Some(job)) => job,
+                Ok(None) => continue,
+                Err(err) => {
+                    tracing::warn!(error = %err, path = %path.display(), "failed to prepare session file");
+                    continue;
+                }
+            };
+            match self.worker.run(&mut job).await {
+                Ok(()) => {
+                    self.files.insert(job.path, job.state);
+                }
+                // The state may be ahead of what was committed; leaving it out
+                // makes the next attempt reload it from `ingest_state`.
+                Err(err) => {
+                    tracing::warn!(error = %err, path = %job.path.display(), "failed to ingest session file");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs jobs on up to `backfill_workers()` concurrent workers, returning
+    /// each file's state to the map as it finishes. The state of a failed job
+    /// is dropped, so the file is reloaded from `ingest_state` next time. With `report_progress`,
+    /// progress is stored for the TUI until all jobs are done.
+    async fn run_jobs(&mut self, jobs: Vec<FileJob>, report_progress: bool) -> Result<()> {
+        if jobs.is_empty() {
+            return Ok(());
+        }
+        let report_progress = report_progress && jobs.len() > 1;
+        let started_at = Utc::now();
+        let mut progress = BackfillProgress {
+            files_total: jobs.len() as u64,
+            files_done: 0,
+            bytes_total: jobs.iter().map(|job| job.pending_bytes).sum(),
+            bytes_done: 0,
+            started_at,
+            updated_at: started_at,
+        };
+        let mut finished_bytes = 0;
+        self.worker.bytes_read.store(0, Ordering::Relaxed);
+        if report_progress {
+            tracing::info!(
+                files = progress.files_total,
+                bytes = progress.bytes_total,
+                "backfilling session logs"
+            );
+            self.worker.store_progress(Some(&progress)).await?;
+        }
+
+        let workers = backfill_workers();
+        let mut pending = jobs.into_iter();
+        let mut running = JoinSet::new();
+        let mut ticker = time::interval(BACKFILL_PROGRESS_INTERVAL);
+        ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            while running.len() < workers
+                && let Some(mut job) = pending.next()
+            {
+                let worker = self.worker.clone();
+                running.spawn(async move {
+                    let result = worker.run(&mut job).await;
+                    (job, result)
+                });
+            }
+            let tick = tokio::select! {
+                joined = running.join_next() => {
+                    let Some(joined) = joined else {
+                        break;
+                    };
+                    let (job, result) = joined.map_err(|err| anyhow!(err))?;
+                    if job.kind == SessionFileKind::Plain {
+                        let read = job.state.last_offset.saturating_sub(job.start_offset);
+                        self.worker.bytes_read.fetch_sub(read, Ordering::Relaxed);
+                    }
+                    finished_bytes += job.pending_bytes;
+                    progress.files_done += 1;
+                    match result {
+                        Ok(()) => {
+                            self.files.insert(job.path, job.state);
+                        }
+                        Err(err) => {
+                            tracing::warn!(error = %err, path = %job.path.display(), "failed to ingest session file");
+                        }
+                    }
+                    false
+                }
+                _ = ticker.tick(), if report_progress => true,
+            };
+            if tick {
+                let in_flight = self.worker.bytes_read.load(Ordering::Relaxed);
+                progress.bytes_done = (finished_bytes + in_flight).min(progress.bytes_total);
+                progress.updated_at = Utc::now();
+                self.worker.store_progress(Some(&progress)).await?;
+            }
         }
 
+        if report_progress {
+            tracing::info!(
+                files = progress.files_total,
+                elapsed_secs = (Utc::now() - started_at).num_seconds(),
+                "backfill finished"
+            );
+            self.worker.store_progress(None).await?;
+        }
         Ok(())
     }
 
-    async fn process_file(&mut self, path: &Path) -> Result<()> {
+    /// Takes the file's state out of the map if it has unread data; otherwise
+    /// leaves it in place and returns `None`.
+    async fn prepare_file(&mut self, path: &Path) -> Result<Option<FileJob>> {
+        let Some(kind) = SessionFileKind::in_roots(&self.worker.roots, path) else {
+            return Ok(None);
+        };
         let metadata = match path.metadata() {
             Ok(meta) => meta,
             Err(err) => {
                 tracing::warn!(error = %err, path = %path.display(), "failed to stat session file");
-                return Ok(());
+                return Ok(None);
             }
         };
         let len = metadata.len();
-        let mut state = self.files.remove(path).unwrap_or_else(FileState::new);
+        let identity = file_identity(&metadata);
+        let mut state = match self.files.remove(path) {
+            Some(state) => state,
+            None => match self.worker.storage.load_ingest_state_for(path).await? {
+                Some(stored) => FileState::from_state(&stored),
+                None => self.take_moved_state(path, kind, identity).await?,
+            },
+        };
+        state.identity = identity.or(state.identity);
 
-        if len == state.last_offset {
+        let done = if kind == SessionFileKind::Plain {
+            len == state.last_offset
+        } else {
+            state.completed
+        };
+        if done {
             self.files.insert(path.to_path_buf(), state);
-            return Ok(());
-        }
-
-        if len < state.last_offset {
-            state.last_offset = 0;
-            state.last_seen = TokenTotals::default();
-            state.last_committed = TokenTotals::default();
-            state.current_model = None;
-            state.pending_note = None;
-            state.pending_note_seq = 0;
-            state.used_note_seq = 0;
-            state.current_message_id = None;
-            state.current_message_seq = 0;
-            state.pending_messages.clear();
-            state.last_user_message = None;
+            return Ok(None);
         }
 
-        let (lines, new_offset) = read_new_lines(path, state.last_offset)?;
-        if lines.is_empty() {
-            self.files.insert(path.to_path_buf(), state);
-            return Ok(());
+        if kind == SessionFileKind::Plain && len < state.last_offset {
+            // Truncated or rewritten: read it again as a new file, keeping only
+            // what identifies it.
+            state = FileState {
+                session_id: state.session_id.take(),
+                identity: state.identity,
+                head_fingerprint: state.head_fingerprint.take(),
+                ..FileState::new()
+            };
         }
 
-        let mut tx = self.storage.begin_tx().await?;
+        let pending_bytes = match kind {
+            SessionFileKind::Plain => len - state.last_offset,
+            SessionFileKind::Gzip | SessionFileKind::Zstd => len,
+        };
+        Ok(Some(FileJob {
+            path: path.to_path_buf(),
+            kind,
+            len,
+            start_offset: state.last_offset,
+            state,
+            pending_bytes,
+        }))
+    }
 
-        for line in lines {
-            if line.trim().is_empty() {
+    /// State for a path seen for the first time. If it is a tracked file that
+    /// was moved (same head, old path gone, and same inode or session id), the
+    /// stored state is re-keyed to the new path so reading resumes at its
+    /// offset; otherwise a fresh state carrying the file's fingerprint.
+    async fn take_moved_state(
+        &mut self,
+        path: &Path,
+        kind: SessionFileKind,
+        identity: Option<(u64, u64)>,
+    ) -> Result<FileState> {
+        let head = match read_file_head(path, kind) {
+            Ok(Some(head)) => head,
+            Ok(None) => return Ok(FileState::new()),
+            Err(err) => {
+                tracing::warn!(error = %err, path = %path.display(), "failed to read session file head");
+                return Ok(FileState::new());
+            }
+        };
+        let candidates = self
+            .worker
+            .storage
+            .ingest_paths_matching_head(&head.fingerprint)
+            .await?;
+        for old_path in candidates {
+            if old_path == path || old_path.exists() {
                 continue;
             }
-            let Ok(value) = serde_json::from_str::<Value>(&line) else {
+            let Some(state) = self.files.get(&old_path) else {
                 continue;
             };
-            if let Err(err) = process_event(&self.storage, &mut tx, &mut state, &value).await {
-                tracing::warn!(error = %err, path = %path.display(), "failed to process session event");
+            // A file that only shares the session id may have been rewritten,
+            // so the old offset means nothing in it; it is read from the start.
+            if state.head_fingerprint.as_deref() != Some(head.fingerprint.as_str()) {
+                continue;
+            }
+            let same_inode = identity.is_some() && state.identity == identity;
+            let same_session = head.session_id.is_some() && state.session_id == head.session_id;
+            // Compressed heads are raw bytes of an immutable file, so a matching
+            // fingerprint is enough.
+            let immutable = kind != SessionFileKind::Plain;
+            if !(same_inode || same_session || immutable) {
+                continue;
             }
+
+            let mut state = self.files.remove(&old_path).unwrap_or_else(FileState::new);
+            state.identity = identity;
+            state.head_fingerprint = Some(head.fingerprint);
+            self.worker
+                .storage
+                .move_ingest_state(&old_path, &state.to_state_row(path))
+                .await?;
+            let label = root_label_for(&self.worker.roots, path);
+            if let (Some(session_id), Some(label)) = (state.session_id.as_ref(), label)
+                && root_label_for(&self.worker.roots, &old_path) != Some(label)
+            {
+                self.worker
+                    .storage
+                    .apply_session_root_labels(&[(session_id.clone(), label.to_string())])
+                    .await?;
+            }
+            tracing::info!(
+                from = %old_path.display(),
+                to = %path.display(),
+                "session file moved; resuming from stored offset"
+            );
+            return Ok(state);
+        }
+
+        let mut state = FileState::new();
+        state.head_fingerprint = Some(head.fingerprint);
+        Ok(state)
+    }
+}
+
+impl FileWorker {
+    async fn store_progress(&self, progress: Option<&BackfillProgress>) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        self.storage.set_backfill_progress(progress).await
+    }
+
+    async fn run(&self, job: &mut FileJob) -> Result<()> {
+        if job.kind == SessionFileKind::Plain {
+            self.read_plain_file(job).await
+        } else {
+            self.process_compressed_file(job).await
+        }
+    }
+
+    async fn read_plain_file(&self, job: &mut FileJob) -> Result<()> {
+        let FileJob {
+            path, kind, state, ..
+        } = job;
+        let mut file = File::open(&path)
+            .with_context(|| format!("failed to open session file {}", path.display()))?;
+        file.seek(SeekFrom::Start(state.last_offset))
+            .with_context(|| "failed to seek session file")?;
+        let mut reader = LineReader::new(BufReader::new(file), state.last_offset, false);
+        while let Some(lines) = reader
+            .next_chunk()
+            .with_context(|| format!("failed to read session file {}", path.display()))?
+        {
+            if state.head_fingerprint.is_none() {
+                state.head_fingerprint = read_file_head(path, *kind)
+                    .ok()
+                    .flatten()
+                    .map(|head| head.fingerprint);
+            }
+            let read = reader.offset() - state.last_offset;
+            self.ingest_chunk(path, state, reader.offset(), lines)
+                .await?;
+            self.bytes_read.fetch_add(read, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Compressed logs are treated as immutable: decompressed and read through
+    /// once, then marked completed so later scans skip them. Until then
+    /// `last_offset` counts decompressed bytes already committed, so a file
+    /// that does not decompress cleanly (e.g. still being written) is resumed
+    /// from there on a later scan.
+    async fn process_compressed_file(&self, job: &mut FileJob) -> Result<()> {
+        let FileJob {
+            path,
+            kind,
+            len,
+            state,
+            ..
+        } = job;
+        if let Err(err) = self.read_compressed_file(path, *kind, state).await? {
+            tracing::warn!(error = %err, path = %path.display(), "failed to decompress session file");
+            let message = format!("{err:#}");
+            let _guard = self.write_lock.lock().await;
+            self.storage
+                .record_ingest_error(
+                    &NewIngestError {
+                        path,
+                        kind: READ_FAILED_ERROR,
+                        offset: state.last_offset,
+                        message: &message,
+                        sample: "",
+                    },
+                    Utc::now(),
+                )
+                .await?;
+            return Ok(());
+        }
+        {
+            let _guard = self.write_lock.lock().await;
+            self.storage
+                .clear_ingest_errors(path, READ_FAILED_ERROR)
+                .await?;
+        }
+        state.completed = true;
+        self.ingest_chunk(path, state, *len, Vec::new()).await
+    }
+
+    /// Ingests the lines after `last_offset`. The outer error is a chunk that
+    /// failed to commit, which leaves `state` unusable; the inner one means the
+    /// file could not be decompressed (yet) and is resumed later.
+    async fn read_compressed_file(
+        &self,
+        path: &Path,
+        kind: SessionFileKind,
+        state: &mut FileState,
+    ) -> Result<Result<()>> {
+        let mut reader = match open_compressed(path, kind, state.last_offset) {
+            Ok(reader) => reader,
+            Err(err) => return Ok(Err(err)),
+        };
+        loop {
+            let lines = match reader
+                .next_chunk()
+                .with_context(|| format!("failed to decompress session file {}", path.display()))
+            {
+                Ok(Some(lines)) => lines,
+                Ok(None) => return Ok(Ok(())),
+                Err(err) => return Ok(Err(err)),
+            };
+            self.ingest_chunk(path, state, reader.offset(), lines)
+                .await?;
+        }
+    }
+
+    /// Parses one chunk of lines, then applies and commits it together with
+    /// the file's state. `last_offset` moves to `end_offset`, just past the
+    /// chunk, only once the commit succeeds; on error the rest of `state` may
+    /// already reflect the chunk and must be discarded. A chunk starting at
+    /// offset 0 replaces the errors recorded for an earlier read of the file.
+    async fn ingest_chunk(
+        &self,
+        path: &Path,
+        state: &mut FileState,
+        end_offset: u64,
+        lines: Vec<(u64, String)>,
+    ) -> Result<()> {
+        let root = root_for(&self.roots, path);
+        let source = log_source::source_for(root.map(|root| root.source).unwrap_or_default());
+        let origin = LineOrigin {
+            path,
+            root_label: root.map(|root| root.label.as_str()),
+        };
+        let rereads = lines.first().is_some_and(|(offset, _)| *offset == 0);
+        let records = tokio::task::spawn_blocking(move || parse_lines(source, lines))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let _guard = self.write_lock.lock().await;
+        let mut tx = self.storage.begin_tx().await?;
+        if rereads {
+            self.storage
+                .clear_file_ingest_errors_tx(&mut tx, path)
+                .await?;
+        }
+        for record in records {
+            apply_record(&self.storage, &mut tx, state, &origin, record).await?;
         }
 
-        state.last_offset = new_offset;
-        let ingest_state = state.to_state_row(path);
+        let mut ingest_state = state.to_state_row(path);
+        ingest_state.last_offset = end_offset;
         self.storage
             .update_ingest_activity_tx(&mut tx, Utc::now())
             .await?;
-        self.storage.upsert_ingest_state_tx(&mut tx, &ingest_state).await?;
+        self.storage
+            .upsert_ingest_state_tx(&mut tx, &ingest_state)
+            .await?;
         tx.commit().await?;
-        self.files.insert(path.to_path_buf(), state);
+        state.last_offset = end_offset;
         Ok(())
     }
 }
 
-async fn process_event(
+/// Where a stream of transcript lines is read from.
+#[derive(Debug, Clone)]
+pub enum StreamInput {
+    /// Standard input, until it is closed.
+    Stdin,
+    /// A named pipe, reopened for the next writer each time one closes it.
+    Fifo(PathBuf),
+}
+
+impl StreamInput {
+    /// Path recorded with ingest errors for lines from this input.
+    fn error_path(&self) -> PathBuf {
+        match self {
+            StreamInput::Stdin => PathBuf::from("<stdin>"),
+            StreamInput::Fifo(path) => path.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// Session root label stored on the stream's sessions.
+    pub label: Option<String>,
+    /// Records every event under this session instead of the stream's own
+    /// `session_meta` id.
+    pub session_id: Option<String>,
+    /// Model of turns before the stream names one; `codex exec --json`
+    /// streams never do.
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamSummary {
+    pub lines: u64,
+    pub session_id: Option<String>,
+}
+
+type StreamLine = io::Result<(u64, String)>;
+
+/// Ingests transcript lines from a pipe instead of session files. Lines go
+/// through the same parsing and event handling as a file, with one in-memory
+/// file state for the whole stream; nothing is stored in `ingest_state`, so
+/// the stream is read once from wherever it starts. Both rollout records and
+/// `codex exec --json` events are accepted; lines without a timestamp, such
+/// as exec events, are dated when they are received. Returns when the input
+/// ends or `shutdown` completes, after committing the lines already read; a
+/// read error is returned once the lines before it are committed.
+pub async fn ingest_stream(
     storage: &Storage,
-    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-    state: &mut FileState,
-    value: &Value,
-) -> Result<()> {
-    let Some(kind) = value.get("type").and_then(|v| v.as_str()) else {
-        return Ok(());
-    };
-    let timestamp = value
-        .get("timestamp")
-        .and_then(|v| v.as_str())
-        .and_then(parse_timestamp);
-
-    match kind {
-        "session_meta" => {
-            if let Some(meta) = parse_session_meta(value, timestamp) {
-                state.session_id = Some(meta.session_id.clone());
-                storage.upsert_session_meta_tx(tx, &meta).await?;
-                if let Some(title) = state.pending_title.take() {
-                    storage
-                        .set_session_title_if_empty_tx(tx, &meta.session_id, &title)
-                        .await?;
+    input: StreamInput,
+    options: &StreamOptions,
+    shutdown: impl Future<Output = ()>,
+) -> Result<StreamSummary> {
+    if let StreamInput::Fifo(path) = &input {
+        check_fifo(path)?;
+    }
+    let path = input.error_path();
+    let (tx, rx) = mpsc::channel(CHUNK_MAX_LINES);
+    // A plain thread rather than a blocking task: reads from a pipe cannot be
+    // cancelled, and the runtime would wait for a blocking task on shutdown.
+    std::thread::spawn(move || read_stream(input, tx));
+    ingest_stream_lines(storage, &path, options, rx, shutdown).await
+}
+
+#[cfg(unix)]
+fn check_fifo(path: &Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("failed to open named pipe {}", path.display()))?;
+    if !metadata.file_type().is_fifo() {
+        return Err(anyhow!(
+            "{} is not a named pipe (create one with mkfifo)",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_fifo(_path: &Path) -> Result<()> {
+    Err(anyhow!("named pipes are only supported on Unix"))
+}
+
+fn read_stream(input: StreamInput, tx: mpsc::Sender<StreamLine>) {
+    let mut offset = 0;
+    match input {
+        StreamInput::Stdin => {
+            send_lines(io::stdin().lock(), &mut offset, &tx);
+        }
+        StreamInput::Fifo(path) => loop {
+            // Opening blocks until a writer connects.
+            match File::open(&path) {
+                Ok(file) => {
+                    if !send_lines(BufReader::new(file), &mut offset, &tx) {
+                        return;
+                    }
                 }
-                if let Some(summary) = state.pending_summary.take() {
-                    storage
-                        .set_session_summary_tx(tx, &meta.session_id, &summary, meta.last_event_at)
-                        .await?;
+                Err(err) => {
+                    let _ = tx.blocking_send(Err(err));
+                    return;
                 }
-                if !state.pending_messages.is_empty() {
-                    flush_pending_messages(
-                        storage,
-                        tx,
-                        state,
-                        &meta.session_id,
-                        meta.last_event_at,
-                    )
-                        .await?;
+            }
+        },
+    }
+}
+
+/// Sends each line as soon as it is read. Returns false when reading failed
+/// or nobody is receiving any more.
+fn send_lines(reader: impl BufRead, offset: &mut u64, tx: &mpsc::Sender<StreamLine>) -> bool {
+    let mut reader = LineReader::new(reader, *offset, true);
+    reader.max_lines = 1;
+    loop {
+        match reader.next_chunk() {
+            Ok(Some(lines)) => {
+                for line in lines {
+                    if tx.blocking_send(Ok(line)).is_err() {
+                        return false;
+                    }
                 }
             }
+            Ok(None) => {
+                *offset = reader.offset();
+                return true;
+            }
+            Err(err) => {
+                let _ = tx.blocking_send(Err(err));
+                return false;
+            }
         }
-        "turn_context" => {
-            if let Some(payload) = value.get("payload") {
-                if let Some(model) = payload.get("model").and_then(|m| m.as_str()) {
-                    state.current_model = Some(normalize_model_id(model));
+    }
+}
+
+/// Commits lines as they arrive, batching whatever is already waiting up to
+/// the usual chunk size.
+async fn ingest_stream_lines(
+    storage: &Storage,
+    path: &Path,
+    options: &StreamOptions,
+    mut rx: mpsc::Receiver<StreamLine>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<StreamSummary> {
+    let label = options.label.as_deref().unwrap_or(DEFAULT_ROOT_LABEL);
+    let origin = LineOrigin {
+        path,
+        root_label: Some(label),
+    };
+    let mut state = FileState::new();
+    state.session_id = options.session_id.clone();
+    state.current_model = options.model.as_deref().map(normalize_model_id);
+    let mut summary = StreamSummary::default();
+    let mut closed = false;
+    tokio::pin!(shutdown);
+    loop {
+        let first = tokio::select! {
+            biased;
+            _ = &mut shutdown, if !closed => {
+                // Stops the reader; lines it already sent are still received
+                // and committed below.
+                rx.close();
+                closed = true;
+                continue;
+            }
+            line = rx.recv() => line,
+        };
+        let Some(first) = first else {
+            break;
+        };
+        let received_at = Utc::now();
+        let mut lines = Vec::new();
+        let mut read_error = None;
+        let mut next = Some(first);
+        while let Some(line) = next {
+            match line {
+                Ok(line) => lines.push(line),
+                Err(err) => {
+                    read_error = Some(err);
+                    break;
                 }
-                state.current_effort = payload
-                    .get("effort")
-                    .and_then(|v| v.as_str())
-                    .map(|value| value.trim())
-                    .filter(|value| !value.is_empty())
-                    .map(|value| value.to_string());
-            }
-        }
-        "event_msg" => {
-            if let Some(payload) = value.get("payload") {
-                match payload.get("type").and_then(|v| v.as_str()) {
-                    Some("token_count") => {
-                        handle_token_count(storage, tx, state, payload, timestamp).await?;
-                    }
-                    Some("user_message") => {
-                        if let Some(message) = payload.get("message").and_then(|v| v.as_str()) {
-                            handle_user_message(storage, tx, state, message, timestamp).await?;
-                        }
-                    }
-                    Some("agent_message") => {
-                        if let Some(message) = payload.get("message").and_then(|v| v.as_str()) {
-                            if let Some(summary) = format_snippet(message, SUMMARY_MAX_CHARS) {
-                                apply_summary(storage, tx, state, &summary, timestamp).await?;
-                            }
-                            if let Some(note) = format_snippet(message, SUMMARY_MAX_CHARS) {
-                                state.pending_note = Some(note);
-                                state.pending_note_seq = state.pending_note_seq.saturating_add(1);
-                            }
-                        }
-                    }
-                    Some("agent_reasoning") => {
-                        if let Some(text) = payload.get("text").and_then(|v| v.as_str())
-                            && let Some(note) = format_snippet(text, SUMMARY_MAX_CHARS)
-                        {
-                            state.pending_note = Some(format_reasoning_note(&note));
-                            state.pending_note_seq = state.pending_note_seq.saturating_add(1);
-                        }
-                    }
-                    _ => {}
+            }
+            next = if lines.len() < CHUNK_MAX_LINES {
+                rx.try_recv().ok()
+            } else {
+                None
+            };
+        }
+        summary.lines += lines.len() as u64;
+        if !lines.is_empty() {
+            ingest_stream_chunk(storage, &mut state, &origin, options, received_at, lines).await?;
+        }
+        if let Some(err) = read_error {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    }
+    summary.session_id = state.session_id;
+    Ok(summary)
+}
+
+async fn ingest_stream_chunk(
+    storage: &Storage,
+    state: &mut FileState,
+    origin: &LineOrigin<'_>,
+    options: &StreamOptions,
+    received_at: DateTime<Utc>,
+    lines: Vec<(u64, String)>,
+) -> Result<()> {
+    let source = log_source::source_for(LogSourceKind::default());
+    let mut records = tokio::task::spawn_blocking(move || parse_lines(source, lines))
+        .await
+        .map_err(|err| anyhow!(err))?;
+    for parsed in records
+        .iter_mut()
+        .filter_map(|record| record.parsed.as_mut().ok())
+    {
+        parsed.timestamp.get_or_insert(received_at);
+        if let Some(session_id) = options.session_id.as_deref() {
+            for event in parsed.events.iter_mut() {
+                if let LogEvent::Session(info) = event {
+                    info.session_id = session_id.to_string();
                 }
             }
         }
-        "response_item" => {
-            if let Some(payload) = value.get("payload") {
-                match payload.get("type").and_then(|v| v.as_str()) {
-                    Some("message") => {
-                        if let Some(role) = payload.get("role").and_then(|v| v.as_str())
-                            && let Some(text) = extract_message_text(payload)
-                        {
-                            if role.eq_ignore_ascii_case("user") {
-                                handle_user_message(storage, tx, state, &text, timestamp).await?;
-                            } else if role.eq_ignore_ascii_case("assistant") {
-                                if let Some(summary) = format_snippet(&text, SUMMARY_MAX_CHARS) {
-                                    apply_summary(storage, tx, state, &summary, timestamp).await?;
-                                }
-                                if let Some(note) = format_snippet(&text, SUMMARY_MAX_CHARS) {
-                                    state.pending_note = Some(note);
-                                    state.pending_note_seq =
-                                        state.pending_note_seq.saturating_add(1);
-                                }
-                            }
-                        }
-                    }
-                    Some("function_call") | Some("custom_tool_call") => {
-                        if let Some(note) = tool_call_note(payload) {
-                            state.pending_note = Some(note);
-                            state.pending_note_seq = state.pending_note_seq.saturating_add(1);
-                        }
-                        if let Some(name) = payload.get("name").and_then(|v| v.as_str()) {
-                            record_tool_event(storage, tx, state, timestamp, name).await?;
-                        }
-                    }
-                    Some("web_search_call") => {
-                        if let Some(note) = web_search_note(payload) {
-                            state.pending_note = Some(note);
-                            state.pending_note_seq = state.pending_note_seq.saturating_add(1);
-                        }
-                        record_tool_event(storage, tx, state, timestamp, "web_search").await?;
-                    }
-                    Some("local_shell_call") => {
-                        if let Some(action_type) = payload
-                            .get("action")
-                            .and_then(|v| v.get("type"))
-                            .and_then(|v| v.as_str())
-                        {
-                            record_tool_event(storage, tx, state, timestamp, action_type).await?;
-                        } else {
-                            record_tool_event(storage, tx, state, timestamp, "local_shell").await?;
-                        }
-                    }
-                    Some("reasoning") => {
-                        if let Some(note) = reasoning_summary_note(payload) {
-                            state.pending_note = Some(note);
-                            state.pending_note_seq = state.pending_note_seq.saturating_add(1);
-                        }
-                    }
-                    _ => {}
+    }
+
+    let mut tx = storage.begin_tx().await?;
+    for record in records {
+        apply_record(storage, &mut tx, state, origin, record).await?;
+    }
+    storage
+        .update_ingest_activity_tx(&mut tx, Utc::now())
+        .await?;
+    tx.commit().await?;
+
+    // Sessions only created by their turns (no `session_meta` in the stream)
+    // have no label yet.
+    if let (Some(session_id), Some(label)) = (state.session_id.as_ref(), origin.root_label) {
+        storage
+            .apply_session_root_labels(&[(session_id.clone(), label.to_string())])
+            .await?;
+    }
+    Ok(())
+}
+
+fn backfill_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(BACKFILL_MAX_WORKERS)
+}
+
+/// Where a transcript line came from.
+struct LineOrigin<'a> {
+    path: &'a Path,
+    root_label: Option<&'a str>,
+}
+
+/// A transcript line and the result of parsing it.
+struct ParsedRecord {
+    offset: u64,
+    line: String,
+    parsed: Result<ParsedLine, ParseError>,
+}
+
+/// Parses a chunk of lines with the root's source, skipping blank lines.
+fn parse_lines(source: &dyn LogSource, lines: Vec<(u64, String)>) -> Vec<ParsedRecord> {
+    lines
+        .into_iter()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(offset, line)| ParsedRecord {
+            offset,
+            parsed: source.parse_line(&line),
+            line,
+        })
+        .collect()
+}
+
+/// Applies the events of one parsed line. Lines that failed to parse and
+/// events that fail to apply are skipped and recorded in `ingest_errors`;
+/// only failing to record them is an error.
+async fn apply_record(
+    storage: &Storage,
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    state: &mut FileState,
+    origin: &LineOrigin<'_>,
+    record: ParsedRecord,
+) -> Result<()> {
+    let ParsedRecord {
+        offset,
+        line,
+        parsed,
+    } = record;
+    let parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            let message = err.to_string();
+            let error = NewIngestError {
+                path: origin.path,
+                kind: err.kind(),
+                offset,
+                message: &message,
+                sample: &line,
+            };
+            return storage.record_ingest_error_tx(tx, &error, Utc::now()).await;
+        }
+    };
+    for event in parsed.events {
+        if let Err(err) = apply_event(
+            storage,
+            tx,
+            state,
+            origin.root_label,
+            parsed.timestamp,
+            event,
+        )
+        .await
+        {
+            tracing::warn!(error = %err, path = %origin.path.display(), "failed to process session event");
+            let message = format!("{err:#}");
+            let error = NewIngestError {
+                path: origin.path,
+                kind: EVENT_FAILED_ERROR,
+                offset,
+                message: &message,
+                sample: &line,
+            };
+            storage
+                .record_ingest_error_tx(tx, &error, Utc::now())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn apply_event(
+    storage: &Storage,
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    state: &mut FileState,
+    root_label: Option<&str>,
+    timestamp: Option<DateTime<Utc>>,
+    event: LogEvent,
+) -> Result<()> {
+    match event {
+        LogEvent::Session(info) => {
+            if let Some(tier) = info.service_tier.as_deref() {
+                state.current_service_tier = Some(normalize_service_tier(tier));
+            }
+            let Some(started_at) = info.started_at.or(timestamp) else {
+                return Ok(());
+            };
+            let meta = SessionMeta {
+                session_id: info.session_id,
+                started_at,
+                last_event_at: started_at,
+                cwd: info.cwd,
+                repo_url: info.repo_url,
+                repo_branch: info.repo_branch,
+                repo_commit: info.repo_commit,
+                model_provider: info.model_provider,
+                subagent: info.subagent,
+                last_model: None,
+                root_label: root_label.map(str::to_string),
+            };
+            state.session_id = Some(meta.session_id.clone());
+            storage.upsert_session_meta_tx(tx, &meta).await?;
+            if let Some(parent) = info.parent_session_id.as_deref() {
+                storage
+                    .link_session_tx(tx, &meta.session_id, parent, SessionLinkSource::Meta)
+                    .await?;
+            }
+            if let Some(title) = state.pending_title.take() {
+                storage
+                    .set_session_title_if_empty_tx(tx, &meta.session_id, &title)
+                    .await?;
+            }
+            if let Some(summary) = state.pending_summary.take() {
+                storage
+                    .set_session_summary_tx(tx, &meta.session_id, &summary, meta.last_event_at)
+                    .await?;
+            }
+            if !state.pending_messages.is_empty() {
+                flush_pending_messages(storage, tx, state, &meta.session_id, meta.last_event_at)
+                    .await?;
+            }
+        }
+        LogEvent::TurnContext {
+            model,
+            effort,
+            service_tier,
+            approval_policy,
+            sandbox_policy,
+            cwd,
+        } => {
+            if let Some(model) = model {
+                state.current_model = Some(normalize_model_id(&model));
+            }
+            state.current_effort = effort;
+            if let Some(tier) = service_tier {
+                state.current_service_tier = Some(normalize_service_tier(&tier));
+            }
+            if approval_policy.is_some() {
+                state.current_approval_policy = approval_policy;
+            }
+            if sandbox_policy.is_some() {
+                state.current_sandbox_policy = sandbox_policy;
+            }
+            if cwd.is_some() {
+                state.current_cwd = cwd;
+            }
+        }
+        LogEvent::TokenCount {
+            totals,
+            last,
+            context_window,
+        } => {
+            handle_token_count(storage, tx, state, totals, last, context_window, timestamp).await?;
+        }
+        LogEvent::RateLimits {
+            primary,
+            secondary,
+            plan_type,
+        } => {
+            if let (Some(session_id), Some(ts)) = (state.session_id.as_deref(), timestamp) {
+                let snapshot = RateLimitSnapshot {
+                    session_id: session_id.to_string(),
+                    timestamp: ts,
+                    primary,
+                    secondary,
+                    plan_type,
+                };
+                storage.record_rate_limits_tx(tx, &snapshot).await?;
+            }
+        }
+        LogEvent::UserMessage(message) => {
+            handle_user_message(storage, tx, state, &message, timestamp).await?;
+        }
+        LogEvent::AgentMessage(message) => {
+            if let Some(summary) = format_snippet(&message, SUMMARY_MAX_CHARS) {
+                apply_summary(storage, tx, state, &summary, timestamp).await?;
+                set_pending_note(state, summary);
+            }
+        }
+        LogEvent::Reasoning(text) => {
+            if let Some(snippet) = format_snippet(&text, SUMMARY_MAX_CHARS) {
+                set_pending_note(state, format_reasoning_note(&snippet));
+            }
+        }
+        LogEvent::ToolCall {
+            name,
+            detail,
+            call_id,
+        } => {
+            let detail = detail.and_then(|detail| format_snippet(&detail, SUMMARY_MAX_CHARS));
+            let note = match detail.as_deref() {
+                Some(detail) => format!("tool: {} ({})", name, detail),
+                None => format!("tool: {}", name),
+            };
+            set_pending_note(state, note);
+            record_tool_event(
+                storage,
+                tx,
+                state,
+                timestamp,
+                &name,
+                call_id.as_deref(),
+                detail.as_deref(),
+            )
+            .await?;
+        }
+        LogEvent::ToolOutput(output) => {
+            if let Some(session_id) = state.session_id.as_deref() {
+                let outcome = ToolOutcome {
+                    call_id: &output.call_id,
+                    exit_code: output.exit_code,
+                    success: output.succeeded(),
+                    duration_ms: output.duration_ms,
+                    output_bytes: output.output_bytes,
+                };
+                storage
+                    .record_tool_outcome_tx(tx, session_id, &outcome)
+                    .await?;
+                if output.succeeded() == Some(false) {
+                    storage
+                        .discard_patch_tx(tx, session_id, &output.call_id)
+                        .await?;
                 }
             }
         }
-        _ => {}
+        LogEvent::Patch { call_id, files } => {
+            if let (Some(session_id), Some(ts)) = (state.session_id.as_deref(), timestamp) {
+                storage
+                    .record_patch_files_tx(tx, session_id, ts, call_id.as_deref(), &files)
+                    .await?;
+                state.unattributed_patches = true;
+            }
+        }
+        LogEvent::SubagentSpawned { session_id: child } => {
+            if let Some(session_id) = state.session_id.as_deref() {
+                storage
+                    .link_session_tx(tx, &child, session_id, SessionLinkSource::Spawn)
+                    .await?;
+            }
+        }
+        LogEvent::WebSearch { query } => {
+            let note = match query.and_then(|query| format_snippet(&query, SUMMARY_MAX_CHARS)) {
+                Some(query) => format!("web_search: {}", query),
+                None => "web_search".to_string(),
+            };
+            set_pending_note(state, note);
+            record_tool_event(storage, tx, state, timestamp, "web_search", None, None).await?;
+        }
     }
 
     Ok(())
 }
 
+/// Note attached to the next recorded turn.
+fn set_pending_note(state: &mut FileState, note: String) {
+    state.pending_note = Some(note);
+    state.pending_note_seq = state.pending_note_seq.saturating_add(1);
+}
+
 async fn apply_title(
     storage: &Storage,
     tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
@@ -598,42 +1510,41 @@ fn should_skip_duplicate_message(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_token_count(
     storage: &Storage,
     tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
     state: &mut FileState,
-    payload: &Value,
+    totals: TokenTotals,
+    last: Option<TokenTotals>,
+    context_window: Option<u64>,
     timestamp: Option<DateTime<Utc>>,
 ) -> Result<()> {
-    let Some(info) = payload.get("info") else {
-        return Ok(());
-    };
-    let context_window = info
-        .get("model_context_window")
-        .and_then(|v| v.as_u64())
-        .or_else(|| {
-            info.get("model_context_window")
-                .and_then(|v| v.as_i64())
-                .map(|v| v as u64)
-        });
-    let totals_value = info.get("total_token_usage");
-    let Some(totals_value) = totals_value else {
-        return Ok(());
-    };
-    let Some(totals) = TokenTotals::from_value(totals_value) else {
-        return Ok(());
-    };
-
+    // Counters restart after a context compaction or when a session is
+    // resumed. Start a new segment, so everything in the new totals is
+    // recorded as usage.
     if totals.any_decreased(state.last_seen) {
-        state.last_seen = totals;
-        state.last_committed = totals;
-        return Ok(());
+        if let (Some(session_id), Some(ts)) = (state.session_id.as_deref(), timestamp) {
+            let detail = format!(
+                "{} -> {} tokens",
+                state.last_seen.total_tokens, totals.total_tokens
+            );
+            storage
+                .record_session_event_tx(tx, session_id, ts, TOKEN_RESET_EVENT, Some(&detail))
+                .await?;
+        }
+        state.last_seen = TokenTotals::default();
+        state.last_committed = TokenTotals::default();
     }
 
     if totals == state.last_seen {
         return Ok(());
     }
 
+    let step = totals.saturating_sub(state.last_seen);
+    // Usage seen earlier but not yet recorded, e.g. before the first
+    // `turn_context` named a model.
+    let uncommitted = state.last_seen.saturating_sub(state.last_committed);
     state.last_seen = totals;
 
     let Some(model) = state.current_model.as_deref() else {
@@ -644,14 +1555,39 @@ async fn handle_token_count(
         None => return Ok(()),
     };
 
-    let delta = totals.saturating_sub(state.last_committed);
-    if delta.is_zero() {
-        return Ok(());
-    }
-
     let Some(ts) = timestamp else {
         return Ok(());
     };
+
+    // The per-request usage is exact; the difference of cumulative snapshots
+    // is not when events are duplicated, reordered or reset. Keep both honest
+    // by recording where they disagree.
+    let delta = match last {
+        Some(last) => {
+            if last != step {
+                let fields = last.differing_fields(step).join(",");
+                storage
+                    .record_usage_mismatch_tx(
+                        tx,
+                        &NewUsageMismatch {
+                            session_id,
+                            timestamp: ts,
+                            model,
+                            fields: &fields,
+                            reported_tokens: last.total_tokens,
+                            cumulative_tokens: step.total_tokens,
+                        },
+                    )
+                    .await?;
+            }
+            last.saturating_add(uncommitted)
+        }
+        None => totals.saturating_sub(state.last_committed),
+    };
+    if delta.is_zero() {
+        state.last_committed = totals;
+        return Ok(());
+    }
     let note = if state.pending_note_seq > state.used_note_seq {
         state.pending_note.as_deref()
     } else {
@@ -660,20 +1596,34 @@ async fn handle_token_count(
     storage
         .record_turn_tx(
             tx,
-            session_id,
-            ts,
-            model,
-            note,
-            context_window,
-            state.current_effort.as_deref(),
-            delta.input_tokens,
-            delta.cached_input_tokens,
-            delta.output_tokens,
-            delta.reasoning_output_tokens,
-            delta.total_tokens,
-            state.current_message_id,
+            &NewTurn {
+                session_id,
+                timestamp: ts,
+                model,
+                note,
+                context_window,
+                reasoning_effort: state.current_effort.as_deref(),
+                service_tier: state.current_service_tier.as_deref(),
+                policy: TurnPolicy {
+                    approval_policy: state.current_approval_policy.as_deref(),
+                    sandbox_policy: state.current_sandbox_policy.as_deref(),
+                    cwd: state.current_cwd.as_deref(),
+                },
+                prompt_tokens: delta.input_tokens,
+                cached_prompt_tokens: delta.cached_input_tokens,
+                completion_tokens: delta.output_tokens,
+                reasoning_tokens: delta.reasoning_output_tokens,
+                total_tokens: delta.total_tokens,
+                message_id: state.current_message_id,
+            },
         )
         .await?;
+    if state.unattributed_patches {
+        storage
+            .attribute_patches_to_turn_tx(tx, session_id, ts, model)
+            .await?;
+        state.unattributed_patches = false;
+    }
 
     state.last_committed = totals;
     if state.pending_note_seq > state.used_note_seq {
@@ -682,141 +1632,156 @@ async fn handle_token_count(
     Ok(())
 }
 
-fn collect_jsonl_files(root: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
-    let mut stack = vec![root.to_path_buf()];
-    while let Some(path) = stack.pop() {
-        let entries = match path.read_dir() {
-            Ok(entries) => entries,
-            Err(err) => {
-                tracing::warn!(error = %err, path = %path.display(), "failed to read session directory");
-                continue;
-            }
-        };
-        for entry in entries {
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(err) => {
-                    tracing::warn!(error = %err, "failed to read directory entry");
-                    continue;
-                }
-            };
-            let path = entry.path();
-            if path.is_dir() {
-                stack.push(path);
-                continue;
-            }
-            if path.extension().and_then(|ext| ext.to_str()) == Some("jsonl") {
-                out.push(path);
-            }
-        }
-    }
-    Ok(())
+/// Transcript files currently under `root`, as a scan would find them.
+pub fn log_files_in_root(root: &SessionRootConfig) -> Result<Vec<PathBuf>> {
+    log_source::source_for(root.source).log_files(&root.path)
 }
 
-fn read_new_lines(path: &Path, offset: u64) -> Result<(Vec<String>, u64)> {
-    let mut file = File::open(path)
+/// Line reader over a compressed file, positioned `offset` decompressed bytes
+/// in.
+fn open_compressed(
+    path: &Path,
+    kind: SessionFileKind,
+    offset: u64,
+) -> Result<LineReader<BufReader<Box<dyn Read + Send>>>> {
+    let file = File::open(path)
         .with_context(|| format!("failed to open session file {}", path.display()))?;
-    file.seek(SeekFrom::Start(offset))
-        .with_context(|| "failed to seek session file")?;
-
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)
-        .with_context(|| "failed to read session file")?;
-    if buf.is_empty() {
-        return Ok((Vec::new(), offset));
+    let mut decoder: Box<dyn Read + Send> = match kind {
+        SessionFileKind::Plain => Box::new(file),
+        SessionFileKind::Gzip => Box::new(MultiGzDecoder::new(file)),
+        SessionFileKind::Zstd => {
+            Box::new(zstd::Decoder::new(file).with_context(|| "failed to create zstd decoder")?)
+        }
+    };
+    let skipped = io::copy(&mut decoder.by_ref().take(offset), &mut io::sink())
+        .with_context(|| format!("failed to decompress session file {}", path.display()))?;
+    if skipped < offset {
+        return Err(anyhow!(
+            "session file {} is shorter than its stored offset",
+            path.display()
+        ));
     }
+    Ok(LineReader::new(BufReader::new(decoder), offset, true))
+}
+
+/// Buffered line reader that hands out complete lines in bounded chunks,
+/// each line paired with its byte offset.
+struct LineReader<R> {
+    reader: R,
+    /// Offset just past the last line returned.
+    offset: u64,
+    /// Whether a final line without a trailing newline counts as complete;
+    /// false for live files, where it may still be being written.
+    accept_unterminated: bool,
+    finished: bool,
+    max_lines: usize,
+    max_bytes: usize,
+}
 
-    let mut last_newline = None;
-    for (idx, byte) in buf.iter().enumerate() {
-        if *byte == b'\n' {
-            last_newline = Some(idx);
+impl<R: BufRead> LineReader<R> {
+    fn new(reader: R, offset: u64, accept_unterminated: bool) -> Self {
+        Self {
+            reader,
+            offset,
+            accept_unterminated,
+            finished: false,
+            max_lines: CHUNK_MAX_LINES,
+            max_bytes: CHUNK_MAX_BYTES,
         }
     }
 
-    let Some(last_newline) = last_newline else {
-        return Ok((Vec::new(), offset));
-    };
+    fn offset(&self) -> u64 {
+        self.offset
+    }
 
-    let slice = &buf[..=last_newline];
-    let text = String::from_utf8_lossy(slice);
-    let mut lines = Vec::new();
-    for line in text.split_terminator('\n') {
-        lines.push(line.to_string());
+    /// The next chunk of lines, or `None` once the input (or its last
+    /// complete line) is reached.
+    fn next_chunk(&mut self) -> io::Result<Option<Vec<(u64, String)>>> {
+        let mut lines = Vec::new();
+        let mut bytes = 0;
+        let mut buf = Vec::new();
+        while !self.finished && lines.len() < self.max_lines && bytes < self.max_bytes {
+            buf.clear();
+            let read = self.reader.read_until(b'\n', &mut buf)?;
+            if read == 0 || (buf.last() != Some(&b'\n') && !self.accept_unterminated) {
+                self.finished = true;
+                break;
+            }
+            let text = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let text = text.strip_suffix(b"\r").unwrap_or(text);
+            lines.push((self.offset, String::from_utf8_lossy(text).into_owned()));
+            self.offset += read as u64;
+            bytes += read;
+        }
+        Ok((!lines.is_empty()).then_some(lines))
     }
+}
 
-    let new_offset = offset + (last_newline as u64) + 1;
-    Ok((lines, new_offset))
+struct FileHead {
+    fingerprint: String,
+    session_id: Option<String>,
 }
 
-fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
-    DateTime::parse_from_rfc3339(value)
+/// Fingerprints a session file by its first line (the `session_meta` record),
+/// or the first `HEAD_SCAN_BYTES` when that line is longer. Returns `None`
+/// while the first line is still incomplete. Compressed files are hashed on
+/// their raw bytes.
+fn read_file_head(path: &Path, kind: SessionFileKind) -> Result<Option<FileHead>> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open session file {}", path.display()))?;
+    let mut buf = Vec::new();
+    file.take(HEAD_SCAN_BYTES)
+        .read_to_end(&mut buf)
+        .with_context(|| format!("failed to read session file {}", path.display()))?;
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    if kind != SessionFileKind::Plain {
+        return Ok(Some(FileHead {
+            fingerprint: fingerprint(&buf),
+            session_id: None,
+        }));
+    }
+    let head = match buf.iter().position(|byte| *byte == b'\n') {
+        Some(end) => &buf[..end],
+        None if buf.len() as u64 == HEAD_SCAN_BYTES => &buf[..],
+        None => return Ok(None),
+    };
+    let session_id = serde_json::from_slice::<Value>(head)
         .ok()
-        .map(|dt| dt.with_timezone(&Utc))
+        .filter(|value| value.get("type").and_then(|v| v.as_str()) == Some("session_meta"))
+        .and_then(|value| {
+            value
+                .get("payload")
+                .and_then(|payload| payload.get("id"))
+                .and_then(|id| id.as_str())
+                .map(str::to_string)
+        });
+    Ok(Some(FileHead {
+        fingerprint: fingerprint(head),
+        session_id,
+    }))
 }
 
-fn parse_session_meta(value: &Value, fallback_ts: Option<DateTime<Utc>>) -> Option<SessionMeta> {
-    let payload = value.get("payload")?;
-    let session_id = payload.get("id")?.as_str()?.to_string();
-    let ts = payload
-        .get("timestamp")
-        .and_then(|v| v.as_str())
-        .and_then(parse_timestamp)
-        .or(fallback_ts)?;
+/// FNV-1a; stored in the database, so it must not change between builds.
+fn fingerprint(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
 
-    Some(SessionMeta {
-        session_id,
-        started_at: ts,
-        last_event_at: ts,
-        cwd: payload
-            .get("cwd")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        repo_url: payload
-            .get("git")
-            .and_then(|v| v.get("repository_url"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        repo_branch: payload
-            .get("git")
-            .and_then(|v| v.get("branch"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        repo_commit: payload
-            .get("git")
-            .and_then(|v| v.get("commit_hash"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        model_provider: payload
-            .get("model_provider")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        subagent: payload
-            .get("source")
-            .and_then(|v| v.get("subagent"))
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        last_model: None,
-    })
+#[cfg(unix)]
+fn file_identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
 }
 
-fn extract_message_text(payload: &Value) -> Option<String> {
-    let content = payload.get("content")?;
-    if let Some(arr) = content.as_array() {
-        let mut acc = String::new();
-        for entry in arr {
-            if let Some(text) = entry.get("text").and_then(|v| v.as_str())
-                && !text.trim().is_empty()
-            {
-                if !acc.is_empty() {
-                    acc.push(' ');
-                }
-                acc.push_str(text.trim());
-            }
-        }
-        if acc.is_empty() { None } else { Some(acc) }
-    } else {
-        content.as_str().map(|text| text.to_string())
-    }
+#[cfg(not(unix))]
+fn file_identity(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
 }
 
 fn format_snippet(text: &str, max_chars: usize) -> Option<String> {
@@ -863,6 +1828,16 @@ fn normalize_model_id(model: &str) -> String {
     }
 }
 
+/// Maps the tier names Codex writes onto the tiers prices are keyed by.
+fn normalize_service_tier(tier: &str) -> String {
+    let tier = tier.trim().to_ascii_lowercase();
+    match tier.as_str() {
+        "" | "auto" | "default" | "standard" => DEFAULT_SERVICE_TIER.to_string(),
+        "batches" => "batch".to_string(),
+        _ => tier,
+    }
+}
+
 fn filter_title_candidate(text: &str) -> Option<String> {
     let lower = text.to_ascii_lowercase();
     if lower.starts_with("<environment_context>")
@@ -879,78 +1854,6 @@ fn filter_title_candidate(text: &str) -> Option<String> {
     Some(text.to_string())
 }
 
-fn tool_call_note(payload: &Value) -> Option<String> {
-    let name = payload.get("name").and_then(|v| v.as_str())?;
-    let mut detail = None;
-
-    if let Some(args) = payload.get("arguments").and_then(|v| v.as_str()) {
-        if let Ok(parsed) = serde_json::from_str::<Value>(args) {
-            if let Some(command) = parsed.get("command").and_then(|v| v.as_str()) {
-                detail = format_snippet(command, SUMMARY_MAX_CHARS);
-            } else if let Some(query) = parsed.get("query").and_then(|v| v.as_str()) {
-                detail = format_snippet(query, SUMMARY_MAX_CHARS);
-            } else if let Some(path) = parsed.get("path").and_then(|v| v.as_str()) {
-                detail = format_snippet(path, SUMMARY_MAX_CHARS);
-            } else if let Some(input) = parsed.get("input").and_then(|v| v.as_str()) {
-                detail = format_snippet(input, SUMMARY_MAX_CHARS);
-            } else if let Some(code) = parsed.get("code").and_then(|v| v.as_str()) {
-                detail = format_snippet(code, SUMMARY_MAX_CHARS);
-            }
-        } else {
-            detail = format_snippet(args, SUMMARY_MAX_CHARS);
-        }
-    } else if let Some(input) = payload.get("input").and_then(|v| v.as_str()) {
-        detail = format_snippet(input, SUMMARY_MAX_CHARS);
-    }
-
-    if let Some(detail) = detail {
-        Some(format!("tool: {} ({})", name, detail))
-    } else {
-        Some(format!("tool: {}", name))
-    }
-}
-
-fn web_search_note(payload: &Value) -> Option<String> {
-    let query = payload
-        .get("action")
-        .and_then(|v| v.get("query"))
-        .and_then(|v| v.as_str());
-    let snippet = query.and_then(|q| format_snippet(q, SUMMARY_MAX_CHARS));
-    if let Some(snippet) = snippet {
-        Some(format!("web_search: {}", snippet))
-    } else {
-        Some("web_search".to_string())
-    }
-}
-
-fn reasoning_summary_note(payload: &Value) -> Option<String> {
-    if let Some(summary) = payload.get("summary").and_then(|v| v.as_array()) {
-        for entry in summary {
-            if let Some(text) = entry.get("text").and_then(|v| v.as_str())
-                && let Some(snippet) = format_snippet(text, SUMMARY_MAX_CHARS)
-            {
-                return Some(format_reasoning_note(&snippet));
-            } else if let Some(text) = entry.as_str()
-                && let Some(snippet) = format_snippet(text, SUMMARY_MAX_CHARS)
-            {
-                return Some(format_reasoning_note(&snippet));
-            }
-        }
-    }
-
-    if let Some(content) = payload.get("content").and_then(|v| v.as_array()) {
-        for entry in content {
-            if let Some(text) = entry.get("text").and_then(|v| v.as_str())
-                && let Some(snippet) = format_snippet(text, SUMMARY_MAX_CHARS)
-            {
-                return Some(format_reasoning_note(&snippet));
-            }
-        }
-    }
-
-    None
-}
-
 fn format_reasoning_note(text: &str) -> String {
     let trimmed = text.trim();
     let stripped = trimmed.trim_matches('*').trim_matches('_').trim();
@@ -967,6 +1870,8 @@ async fn record_tool_event(
     state: &FileState,
     timestamp: Option<DateTime<Utc>>,
     tool_name: &str,
+    call_id: Option<&str>,
+    detail: Option<&str>,
 ) -> Result<()> {
     let Some(session_id) = state.session_id.as_deref() else {
         return Ok(());
@@ -974,17 +1879,965 @@ async fn record_tool_event(
     let Some(ts) = timestamp else {
         return Ok(());
     };
-    storage.record_tool_call_tx(tx, session_id, ts, tool_name).await
+    storage
+        .record_tool_call_tx(tx, session_id, ts, tool_name, call_id, detail)
+        .await
 }
 
-impl PartialEq for TokenTotals {
-    fn eq(&self, other: &Self) -> bool {
-        self.input_tokens == other.input_tokens
-            && self.cached_input_tokens == other.cached_input_tokens
-            && self.output_tokens == other.output_tokens
-            && self.reasoning_output_tokens == other.reasoning_output_tokens
-            && self.total_tokens == other.total_tokens
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::LogSourceKind,
+        storage::{LineChangeGroup, SessionTreeOrder},
+    };
+    use std::{fs, io::Write};
+    use tempfile::{NamedTempFile, TempDir, tempdir};
+
+    fn session_log(session_id: &str, input_tokens: u64) -> String {
+        let ts = "2025-06-01T12:00:00.000Z";
+        format!(
+            "{{\"timestamp\":\"{ts}\",\"type\":\"session_meta\",\"payload\":{{\"id\":\"{session_id}\",\"timestamp\":\"{ts}\"}}}}\n\
+             {{\"timestamp\":\"{ts}\",\"type\":\"turn_context\",\"payload\":{{\"model\":\"gpt-5\"}}}}\n\
+             {{\"timestamp\":\"{ts}\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{{\"input_tokens\":{input_tokens},\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":{input_tokens}}}}}}}}}"
+        )
+    }
+
+    /// Storage on a fresh database plus an empty session directory, ingested
+    /// as the single root `default`.
+    struct TestEnv {
+        /// Keeps the database file alive for the test.
+        _db_file: NamedTempFile,
+        storage: Storage,
+        root: TempDir,
+    }
+
+    impl TestEnv {
+        async fn new() -> Self {
+            let db_file = NamedTempFile::new().unwrap();
+            let storage = Storage::connect(db_file.path()).await.unwrap();
+            storage.ensure_schema().await.unwrap();
+            Self {
+                _db_file: db_file,
+                storage,
+                root: tempdir().unwrap(),
+            }
+        }
+
+        fn roots(&self) -> Vec<SessionRootConfig> {
+            vec![SessionRootConfig {
+                path: self.root.path().to_path_buf(),
+                label: DEFAULT_ROOT_LABEL.to_string(),
+                source: LogSourceKind::Codex,
+            }]
+        }
+
+        async fn ingestor(&self) -> SessionIngestor {
+            SessionIngestor::new(self.roots(), self.storage.clone())
+                .await
+                .unwrap()
+        }
+    }
+
+    /// Usage object with `tokens` input tokens and nothing else.
+    fn usage(tokens: u64) -> String {
+        format!(
+            "{{\"input_tokens\":{tokens},\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":{tokens}}}"
+        )
+    }
+
+    /// `token_count` event with a cumulative total of `tokens`.
+    fn token_count(ts: &str, tokens: u64) -> String {
+        format!(
+            "{{\"timestamp\":\"{ts}\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{}}}}}}}",
+            usage(tokens)
+        )
+    }
+
+    #[test]
+    fn line_reader_yields_bounded_chunks_of_complete_lines() {
+        let input = b"a\nbb\r\n\nccc\npartial";
+        let mut reader = LineReader::new(&input[..], 100, false);
+        reader.max_lines = 2;
+        let chunk = reader.next_chunk().unwrap().unwrap();
+        assert_eq!(chunk, [(100, "a".to_string()), (102, "bb".to_string())]);
+        assert_eq!(reader.offset(), 106);
+        let chunk = reader.next_chunk().unwrap().unwrap();
+        assert_eq!(chunk, [(106, String::new()), (107, "ccc".to_string())]);
+        assert!(reader.next_chunk().unwrap().is_none());
+        assert_eq!(reader.offset(), 111);
+
+        let mut reader = LineReader::new(&input[..], 0, true);
+        let chunk = reader.next_chunk().unwrap().unwrap();
+        assert_eq!(chunk.last(), Some(&(11, "partial".to_string())));
+        assert_eq!(reader.offset(), input.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn compressed_logs_are_read_once() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let gz_path = root.path().join("rollout-a.jsonl.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder
+            .write_all(session_log("sess-gz", 100).as_bytes())
+            .unwrap();
+        encoder.finish().unwrap();
+        let zst_path = root.path().join("rollout-b.jsonl.zst");
+        let compressed = zstd::encode_all(session_log("sess-zst", 200).as_bytes(), 0).unwrap();
+        fs::write(&zst_path, compressed).unwrap();
+        fs::write(root.path().join("notes.txt.gz"), b"ignored").unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots.clone(), storage.clone())
+            .await
+            .unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 300);
+
+        let states = storage.load_ingest_state().await.unwrap();
+        assert_eq!(states.len(), 2);
+        assert!(states.iter().all(|state| state.completed));
+
+        // A restarted ingestor skips completed files instead of decompressing them again.
+        fs::write(&gz_path, b"not gzip anymore").unwrap();
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 300);
+    }
+
+    #[tokio::test]
+    async fn startup_scan_backfills_files_concurrently() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        for idx in 0..12 {
+            let path = root.path().join(format!("rollout-{idx}.jsonl"));
+            fs::write(&path, session_log(&format!("sess-{idx}"), 10) + "\n").unwrap();
+        }
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 120);
+        assert_eq!(ingestor.files.len(), 12);
+        assert!(ingestor.files.values().all(|state| state.last_offset > 0));
+        assert!(storage.backfill_progress().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn unparseable_lines_are_recorded_and_skipped() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        let log = session_log("sess-a", 100);
+        let (head, last) = log.rsplit_once('\n').unwrap();
+        let drifted = r#"{"timestamp":"2025-06-01T12:01:00.000Z","type":"event_msg","payload":{"type":"token_count","info":{"usage":{}}}}"#;
+        fs::write(&path, format!("{head}\n{{\"truncated\n{drifted}\n{last}\n")).unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 100);
+
+        let mut errors = storage.list_ingest_errors().await.unwrap();
+        errors.sort_by_key(|error| error.offset);
+        let kinds: Vec<_> = errors.iter().map(|error| error.kind.as_str()).collect();
+        assert_eq!(kinds, ["invalid_json", "unrecognized"]);
+        assert_eq!(errors[0].offset, head.len() as u64 + 1);
+        assert_eq!(errors[0].sample, "{\"truncated");
+        assert_eq!(errors[1].offset, head.len() as u64 + 13);
+        assert_eq!(storage.ingest_error_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn ingest_errors_keep_the_latest_line_and_reset_on_reread() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        let log = session_log("sess-a", 100);
+        fs::write(&path, format!("{log}\n{{\"first\n")).unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{{\"second").unwrap();
+        drop(file);
+        ingestor.scan_once().await.unwrap();
+
+        let errors = storage.list_ingest_errors().await.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].count, 2);
+        assert_eq!(errors[0].offset, log.len() as u64 + 9);
+        assert_eq!(errors[0].sample, "{\"second");
+
+        // A truncated file is read again from the start; its earlier errors
+        // no longer apply and the ones still present are counted once.
+        fs::write(&path, "{\"third\n").unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let errors = storage.list_ingest_errors().await.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].count, 1);
+        assert_eq!(errors[0].offset, 0);
+        assert_eq!(errors[0].sample, "{\"third");
+    }
+
+    #[tokio::test]
+    async fn token_counter_reset_starts_a_new_segment() {
+        let env = TestEnv::new().await;
+        let storage = &env.storage;
+
+        let path = env.root.path().join("rollout-a.jsonl");
+        let log = format!(
+            "{}\n{}\n{}\n",
+            session_log("sess-a", 100),
+            token_count("2025-06-01T12:05:00.000Z", 30),
+            token_count("2025-06-01T12:06:00.000Z", 50),
+        );
+        fs::write(&path, log).unwrap();
+
+        let mut ingestor = env.ingestor().await;
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 150);
+
+        let events = storage.session_events("sess-a").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, TOKEN_RESET_EVENT);
+        assert_eq!(events[0].detail.as_deref(), Some("100 -> 30 tokens"));
+    }
+
+    #[tokio::test]
+    async fn last_token_usage_is_preferred_and_cross_checked() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        let usage = |tokens: u64| {
+            format!(
+                "{{\"input_tokens\":{tokens},\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":{tokens}}}"
+            )
+        };
+        let token_count = |ts: &str, total: u64, last: u64| {
+            format!(
+                "{{\"timestamp\":\"{ts}\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{},\"last_token_usage\":{}}}}}}}",
+                usage(total),
+                usage(last)
+            )
+        };
+        let log = session_log("sess-a", 0);
+        let (head, _) = log.rsplit_once('\n').unwrap();
+        let log = format!(
+            "{head}\n{}\n{}\n",
+            token_count("2025-06-01T12:01:00.000Z", 100, 100),
+            token_count("2025-06-01T12:02:00.000Z", 160, 50),
+        );
+        fs::write(&path, log).unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 150);
+
+        let summary = storage.usage_mismatch_summary().await.unwrap();
+        assert_eq!(summary.count, 1);
+        let mismatches = storage.recent_usage_mismatches(10).await.unwrap();
+        assert_eq!(mismatches[0].fields, "input,total");
+        assert_eq!(mismatches[0].reported_tokens, 50);
+        assert_eq!(mismatches[0].cumulative_tokens, 60);
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_paired_with_their_outputs() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        let ts = "2025-06-01T12:01:00.000Z";
+        let call = |id: &str, command: &str| {
+            format!(
+                r#"{{"timestamp":"{ts}","type":"response_item","payload":{{"type":"function_call","name":"shell","arguments":"{{\"command\":\"{command}\"}}","call_id":"{id}"}}}}"#
+            )
+        };
+        let end = |id: &str, exit_code: i64, secs: u64| {
+            format!(
+                r#"{{"timestamp":"{ts}","type":"event_msg","payload":{{"type":"exec_command_end","call_id":"{id}","exit_code":{exit_code},"duration":{{"secs":{secs},"nanos":0}}}}}}"#
+            )
+        };
+        let log = [
+            session_log("sess-a", 10),
+            call("c1", "cargo test"),
+            end("c1", 101, 30),
+            call("c2", "cargo test"),
+            end("c2", 0, 20),
+            call("c3", "ls"),
+        ]
+        .join("\n");
+        fs::write(&path, log + "\n").unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let tools = storage.session_tool_counts("sess-a").await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].count, 3);
+        assert_eq!(tools[0].finished, 2);
+        assert_eq!(tools[0].failed, 1);
+        assert_eq!(tools[0].duration_ms, 50_000);
+        assert_eq!(tools[0].failure_rate(), Some(0.5));
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let failing = storage
+            .failing_tool_commands_between(start, end, 10)
+            .await
+            .unwrap();
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].detail, "cargo test");
+        assert_eq!((failing[0].failed, failing[0].count), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn applied_patches_count_towards_turns_and_failed_ones_do_not() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        let patch = |ts: &str, id: &str, body: &str| {
+            let input = format!("*** Begin Patch\n{body}\n*** End Patch");
+            serde_json::json!({
+                "timestamp": ts,
+                "type": "response_item",
+                "payload": {"type": "custom_tool_call", "name": "apply_patch", "call_id": id, "input": input},
+            })
+            .to_string()
+        };
+        let failed = |ts: &str, id: &str| {
+            format!(
+                r#"{{"timestamp":"{ts}","type":"event_msg","payload":{{"type":"patch_apply_end","call_id":"{id}","success":false}}}}"#
+            )
+        };
+        let token_count = |ts: &str, tokens: u64| {
+            format!(
+                r#"{{"timestamp":"{ts}","type":"event_msg","payload":{{"type":"token_count","info":{{"total_token_usage":{{"input_tokens":{tokens},"output_tokens":0,"total_tokens":{tokens}}}}}}}}}"#
+            )
+        };
+        let log = [
+            session_log("sess-a", 100),
+            patch(
+                "2025-06-01T12:01:00.000Z",
+                "p1",
+                "*** Update File: a.rs\n@@\n-old\n+new\n+more",
+            ),
+            token_count("2025-06-01T12:02:00.000Z", 150),
+            patch(
+                "2025-06-01T12:03:00.000Z",
+                "p2",
+                "*** Add File: b.rs\n+x\n+y",
+            ),
+            failed("2025-06-01T12:03:01.000Z", "p2"),
+            token_count("2025-06-01T12:04:00.000Z", 200),
+            patch("2025-06-01T12:05:00.000Z", "p3", "*** Add File: c.rs\n+z"),
+            token_count("2025-06-01T12:06:00.000Z", 260),
+            failed("2025-06-01T12:07:00.000Z", "p3"),
+        ]
+        .join("\n");
+        fs::write(&path, log + "\n").unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let changes = storage.session_line_changes("sess-a").await.unwrap();
+        assert_eq!((changes.files, changes.added, changes.removed), (1, 2, 1));
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let rows = storage
+            .line_change_costs_between(start, end, LineChangeGroup::Model, 10)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].key, "gpt-5");
+        assert_eq!(rows[0].files_changed, 1);
+        assert_eq!((rows[0].lines_added, rows[0].lines_removed), (2, 1));
+        // The whole spend of the group counts, not only the patching turns.
+        assert_eq!(rows[0].total_tokens, 260);
+    }
+
+    #[tokio::test]
+    async fn subagent_sessions_roll_up_into_their_parent() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let spawn = r#"{"timestamp":"2025-06-01T12:01:00.000Z","type":"event_msg","payload":{"type":"collab_agent_spawn_end","new_thread_id":"sess-spawned"}}"#;
+        fs::write(
+            root.path().join("rollout-parent.jsonl"),
+            format!("{}\n{spawn}\n", session_log("sess-parent", 100)),
+        )
+        .unwrap();
+        let child_meta = r#"{"timestamp":"2025-06-01T12:02:00.000Z","type":"session_meta","payload":{"id":"sess-review","source":{"subagent":{"thread_spawn":{"parent_thread_id":"sess-parent","depth":1}}}}}"#;
+        let child_log = session_log("sess-review", 40);
+        let child_log = child_log.split_once('\n').unwrap().1;
+        fs::write(
+            root.path().join("rollout-review.jsonl"),
+            format!("{child_meta}\n{child_log}\n"),
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("rollout-spawned.jsonl"),
+            session_log("sess-spawned", 10) + "\n",
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("rollout-other.jsonl"),
+            session_log("sess-other", 5) + "\n",
+        )
+        .unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let count = |expanded: Vec<String>| {
+            let storage = storage.clone();
+            async move {
+                storage
+                    .session_tree_rows_count_between(start, end, &expanded)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(count(Vec::new()).await, 2);
+        assert_eq!(count(vec!["sess-parent".to_string()]).await, 4);
+
+        let page = storage
+            .session_tree_page_between(
+                start,
+                end,
+                SessionTreeOrder::Cost,
+                &["sess-parent".to_string()],
+                0,
+                10,
+            )
+            .await
+            .unwrap();
+        let rows: Vec<(&str, u32)> = page
+            .iter()
+            .map(|row| {
+                let node = row.tree.as_ref().unwrap();
+                (row.session_id.as_str(), node.depth)
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("sess-parent", 0),
+                ("sess-spawned", 1),
+                ("sess-review", 1),
+                ("sess-other", 0),
+            ]
+        );
+        let parent = page[0].tree.as_ref().unwrap();
+        assert_eq!(parent.descendants, 2);
+        assert_eq!(parent.tree_total_tokens, 150);
+        assert_eq!(page[0].total_tokens, 100);
+        assert_eq!(page[2].subagent.as_deref(), Some("thread_spawn"));
+    }
+
+    #[tokio::test]
+    async fn turns_keep_the_approval_policy_across_restarts() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        let token_count = |ts: &str, tokens: u64| {
+            format!(
+                "{{\"timestamp\":\"{ts}\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{{\"input_tokens\":{tokens},\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":{tokens}}}}}}}}}"
+            )
+        };
+        let log = session_log("sess-a", 0);
+        let (head, _) = log.rsplit_once('\n').unwrap();
+        let log = format!(
+            "{head}\n{}\n{}\n",
+            r#"{"timestamp":"2025-06-01T12:00:30.000Z","type":"turn_context","payload":{"cwd":"/work/app","approval_policy":"never","sandbox_policy":{"type":"danger-full-access"},"model":"gpt-5"}}"#,
+            token_count("2025-06-01T12:01:00.000Z", 100),
+        );
+        fs::write(&path, log).unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots.clone(), storage.clone())
+            .await
+            .unwrap();
+        ingestor.scan_once().await.unwrap();
+        drop(ingestor);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{}", token_count("2025-06-01T12:02:00.000Z", 160)).unwrap();
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let rows = storage
+            .line_change_costs_between(start, end, LineChangeGroup::Approval, 10)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].key, "never / danger-full-access");
+        assert_eq!(rows[0].total_tokens, 160);
+    }
+
+    #[tokio::test]
+    async fn moved_file_resumes_from_stored_offset() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        // Codex's own layout: live logs under dated directories in
+        // `sessions`, archived ones flat in the sibling `archived_sessions`.
+        let codex_home = tempdir().unwrap();
+        let live_dir = codex_home.path().join("sessions/2025/06/01");
+        let archive_dir = codex_home.path().join("archived_sessions");
+        fs::create_dir_all(&live_dir).unwrap();
+        fs::create_dir_all(&archive_dir).unwrap();
+        let live_path = live_dir.join("rollout-a.jsonl");
+        fs::write(&live_path, session_log("sess-a", 100) + "\n").unwrap();
+
+        let sessions = crate::config::SessionsConfig {
+            root_dir: codex_home.path().join("sessions"),
+            ..Default::default()
+        };
+        let roots = sessions.session_roots();
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let archived_path = archive_dir.join("rollout-a.jsonl");
+        fs::rename(&live_path, &archived_path).unwrap();
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&archived_path)
+            .unwrap();
+        writeln!(
+            file,
+            "{{\"timestamp\":\"2025-06-01T12:05:00.000Z\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{{\"input_tokens\":150,\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":150}}}}}}}}"
+        )
+        .unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let states = storage.load_ingest_state().await.unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].path, archived_path);
+        assert_eq!(
+            states[0].last_offset,
+            fs::metadata(&archived_path).unwrap().len()
+        );
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert_eq!(totals.total_tokens, 150);
+    }
+
+    #[tokio::test]
+    async fn moved_file_without_a_matching_head_is_read_from_the_start() {
+        let env = TestEnv::new().await;
+        let old_path = env.root.path().join("rollout-a.jsonl");
+        fs::write(&old_path, session_log("sess-a", 100) + "\n").unwrap();
+        env.ingestor().await.scan_once().await.unwrap();
+
+        // A state stored before fingerprints were recorded only knows its
+        // session id, which is not enough to trust its offset.
+        let mut stored = env.storage.load_ingest_state().await.unwrap().remove(0);
+        stored.head_fingerprint = None;
+        stored.device = None;
+        stored.inode = None;
+        let mut tx = env.storage.begin_tx().await.unwrap();
+        env.storage
+            .upsert_ingest_state_tx(&mut tx, &stored)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        fs::remove_file(&old_path).unwrap();
+        let new_path = env.root.path().join("rollout-b.jsonl");
+        let log = session_log("sess-a", 150).replacen("\"id\"", "\"cwd\":\"/elsewhere\",\"id\"", 1);
+        fs::write(&new_path, log + "\n").unwrap();
+        env.ingestor().await.scan_once().await.unwrap();
+
+        let states = env.storage.load_ingest_state().await.unwrap();
+        let new_state = states.iter().find(|state| state.path == new_path).unwrap();
+        assert_eq!(
+            new_state.last_offset,
+            fs::metadata(&new_path).unwrap().len()
+        );
+        assert!(states.iter().any(|state| state.path == old_path));
+        assert!(env.storage.list_ingest_errors().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn truncated_file_starts_from_a_fresh_state() {
+        let env = TestEnv::new().await;
+        let path = env.root.path().join("rollout-a.jsonl");
+        // A summary seen before any session_meta waits for the session id.
+        let message = format!(
+            "{{\"timestamp\":\"2025-06-01T12:00:00.000Z\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"agent_message\",\"message\":\"{}\"}}}}",
+            "stale ".repeat(60)
+        );
+        fs::write(&path, message + "\n").unwrap();
+        let mut ingestor = env.ingestor().await;
+        ingestor.scan_once().await.unwrap();
+
+        // The rewritten log is shorter, so it is read again from the start;
+        // the summary belonged to the old content and must not be applied.
+        fs::write(&path, session_log("sess-a", 40) + "\n").unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let start = "2025-06-01T00:00:00Z".parse().unwrap();
+        let end = "2025-06-02T00:00:00Z".parse().unwrap();
+        let sessions = env
+            .storage
+            .sessions_page_by_cost_between(start, end, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "sess-a");
+        assert_eq!(sessions[0].last_summary, None);
+        assert_eq!(sessions[0].total_tokens, 40);
+    }
+
+    #[tokio::test]
+    async fn stream_lines_are_recorded_under_the_overridden_session() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let (tx, rx) = mpsc::channel(CHUNK_MAX_LINES);
+        let mut offset = 0;
+        for line in session_log("sess-orig", 100)
+            .lines()
+            .chain(["{\"truncated"])
+        {
+            tx.send(Ok((offset, line.to_string()))).await.unwrap();
+            offset += line.len() as u64 + 1;
+        }
+        drop(tx);
+
+        let options = StreamOptions {
+            label: Some("ci".to_string()),
+            session_id: Some("run-42".to_string()),
+            model: None,
+        };
+        let summary = ingest_stream_lines(
+            &storage,
+            Path::new("<stdin>"),
+            &options,
+            rx,
+            std::future::pending(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            summary,
+            StreamSummary {
+                lines: 4,
+                session_id: Some("run-42".to_string()),
+            }
+        );
+
+        let totals = storage.session_turn_totals("run-42").await.unwrap();
+        assert_eq!(totals.total_tokens, 100);
+        assert_eq!(storage.session_turns_count("sess-orig").await.unwrap(), 0);
+        assert_eq!(storage.session_root_labels().await.unwrap(), ["ci"]);
+
+        let errors = storage.list_ingest_errors().await.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, Path::new("<stdin>"));
+        assert_eq!(errors[0].kind, "invalid_json");
+    }
+
+    /// Sends each line of `log` with its offset, as `read_stream` would.
+    async fn send_log_lines(tx: &mpsc::Sender<StreamLine>, log: &str) {
+        let mut offset = 0;
+        for line in log.lines() {
+            tx.send(Ok((offset, line.to_string()))).await.unwrap();
+            offset += line.len() as u64 + 1;
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_read_error_commits_the_lines_before_it() {
+        let env = TestEnv::new().await;
+        let (tx, rx) = mpsc::channel(CHUNK_MAX_LINES);
+        send_log_lines(&tx, &session_log("sess-a", 100)).await;
+        tx.send(Err(io::Error::other("pipe broke"))).await.unwrap();
+        drop(tx);
+
+        let result = ingest_stream_lines(
+            &env.storage,
+            Path::new("<stdin>"),
+            &StreamOptions::default(),
+            rx,
+            std::future::pending(),
+        )
+        .await;
+        let err = result.unwrap_err();
+        assert!(format!("{err:#}").contains("pipe broke"), "{err:#}");
+        let totals = env.storage.session_turn_totals("sess-a").await.unwrap();
+        assert_eq!(totals.total_tokens, 100);
+    }
+
+    #[tokio::test]
+    async fn stream_shutdown_commits_lines_already_received() {
+        let env = TestEnv::new().await;
+        let (tx, rx) = mpsc::channel(CHUNK_MAX_LINES);
+        send_log_lines(&tx, &session_log("sess-a", 100)).await;
+
+        // The sender stays open, as a pipe whose writer is still running.
+        let summary = ingest_stream_lines(
+            &env.storage,
+            Path::new("<stdin>"),
+            &StreamOptions::default(),
+            rx,
+            std::future::ready(()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.lines, 3);
+        let totals = env.storage.session_turn_totals("sess-a").await.unwrap();
+        assert_eq!(totals.total_tokens, 100);
+        assert!(tx.is_closed());
+    }
+
+    #[tokio::test]
+    async fn exec_json_stream_is_recorded_at_receive_time() {
+        let env = TestEnv::new().await;
+        let storage = &env.storage;
+
+        // `codex exec --json "is there a README here?"`
+        let exec = [
+            r#"{"type":"thread.started","thread_id":"0199a213-81c0-7800-8aa1-bbab2a035a53"}"#,
+            r#"{"type":"turn.started"}"#,
+            r#"{"type":"item.completed","item":{"id":"item_0","type":"reasoning","text":"**Searching for README files**"}}"#,
+            r#"{"type":"item.started","item":{"id":"item_1","type":"command_execution","command":"bash -lc ls","aggregated_output":"","exit_code":null,"status":"in_progress"}}"#,
+            r#"{"type":"item.completed","item":{"id":"item_1","type":"command_execution","command":"bash -lc ls","aggregated_output":"AGENTS.md\nCHANGELOG.md\ncodex-cli\ncodex-rs\ndocs\nREADME.md\n","exit_code":0,"status":"completed"}}"#,
+            r#"{"type":"item.completed","item":{"id":"item_2","type":"agent_message","text":"Yes, there is a README.md in the repository root."}}"#,
+            r#"{"type":"turn.completed","usage":{"input_tokens":24763,"cached_input_tokens":24448,"output_tokens":122}}"#,
+        ];
+        let (tx, rx) = mpsc::channel(CHUNK_MAX_LINES);
+        let mut offset = 0;
+        for line in exec {
+            tx.send(Ok((offset, line.to_string()))).await.unwrap();
+            offset += line.len() as u64 + 1;
+        }
+        drop(tx);
+
+        let options = StreamOptions {
+            model: Some("gpt-5".to_string()),
+            ..StreamOptions::default()
+        };
+        let before = Utc::now();
+        let summary = ingest_stream_lines(
+            storage,
+            Path::new("<stdin>"),
+            &options,
+            rx,
+            std::future::pending(),
+        )
+        .await
+        .unwrap();
+        let session_id = "0199a213-81c0-7800-8aa1-bbab2a035a53";
+        assert_eq!(summary.session_id.as_deref(), Some(session_id));
+
+        let totals = storage.session_turn_totals(session_id).await.unwrap();
+        assert_eq!(totals.total_tokens, 24885);
+        assert_eq!(totals.cached_prompt_tokens, 24448);
+        assert_eq!(totals.completion_tokens, 122);
+        let window = storage
+            .totals_between_timestamps(before, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(window.total_tokens, 24885);
+
+        let tools = storage.session_tool_counts(session_id).await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!((tools[0].count, tools[0].failed), (1, 0));
+        assert!(storage.list_ingest_errors().await.unwrap().is_empty());
+    }
+
+    /// Makes every chunk commit for paths matching `pattern` (SQL `LIKE`) fail
+    /// until the trigger is dropped again.
+    async fn fail_ingest_state_writes(db_path: &Path, pattern: &str) {
+        let options = sqlx::sqlite::SqliteConnectOptions::new().filename(db_path);
+        let pool = sqlx::SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query(&format!(
+            "CREATE TRIGGER fail_ingest_state BEFORE INSERT ON ingest_state \
+             WHEN NEW.path LIKE '{pattern}' \
+             BEGIN SELECT RAISE(ABORT, 'forced failure'); END"
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+    }
+
+    async fn allow_ingest_state_writes(db_path: &Path) {
+        let options = sqlx::sqlite::SqliteConnectOptions::new().filename(db_path);
+        let pool = sqlx::SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query("DROP TRIGGER fail_ingest_state")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn watched_changes_continue_past_a_failing_file() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let bad = root.path().join("rollout-bad.jsonl");
+        let good = root.path().join("rollout-good.jsonl");
+        fs::write(&bad, format!("{}\n", session_log("sess-bad", 100))).unwrap();
+        fs::write(&good, format!("{}\n", session_log("sess-good", 40))).unwrap();
+        fail_ingest_state_writes(db_file.path(), "%rollout-bad%").await;
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor
+            .process_changed(HashSet::from([bad, good]))
+            .await
+            .unwrap();
+
+        let totals = storage.session_turn_totals("sess-good").await.unwrap();
+        assert_eq!(totals.total_tokens, 40);
+        assert_eq!(storage.session_turns_count("sess-bad").await.unwrap(), 0);
     }
-}
 
-impl Eq for TokenTotals {}
+    #[tokio::test]
+    async fn failed_chunk_is_read_again_on_the_next_scan() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("rollout-a.jsonl");
+        fs::write(&path, format!("{}\n", session_log("sess-a", 100))).unwrap();
+
+        let roots = vec![SessionRootConfig {
+            path: root.path().to_path_buf(),
+            label: "default".to_string(),
+            source: LogSourceKind::Codex,
+        }];
+        let mut ingestor = SessionIngestor::new(roots, storage.clone()).await.unwrap();
+        ingestor.scan_once().await.unwrap();
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(
+            file,
+            "{{\"timestamp\":\"2025-06-01T12:05:00.000Z\",\"type\":\"event_msg\",\"payload\":{{\"type\":\"token_count\",\"info\":{{\"total_token_usage\":{{\"input_tokens\":250,\"cached_input_tokens\":0,\"output_tokens\":0,\"reasoning_output_tokens\":0,\"total_tokens\":250}}}}}}}}"
+        )
+        .unwrap();
+        drop(file);
+        fail_ingest_state_writes(db_file.path(), "%rollout-a%").await;
+        ingestor.scan_once().await.unwrap();
+        let totals = storage.session_turn_totals("sess-a").await.unwrap();
+        assert_eq!(totals.total_tokens, 100);
+
+        allow_ingest_state_writes(db_file.path()).await;
+        ingestor.scan_once().await.unwrap();
+        let totals = storage.session_turn_totals("sess-a").await.unwrap();
+        assert_eq!(totals.total_tokens, 250);
+        assert_eq!(storage.session_turns_count("sess-a").await.unwrap(), 2);
+    }
+}
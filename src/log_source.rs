@@ -0,0 +1,276 @@
+//! Agent-specific transcript parsing. A `LogSource` turns one line of a
+//! transcript into normalized events; the ingestor applies those events to its
+//! per-file state and storage the same way for every agent.
+
+mod codex;
+mod codex_exec;
+
+use crate::{
+    config::LogSourceKind,
+    storage::{FileLineChange, RateLimitWindow},
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+pub use codex::CodexSource;
+
+static CODEX: CodexSource = CodexSource;
+
+/// Parser for one agent's transcript format.
+pub trait LogSource: Send + Sync {
+    /// Whether a file with this name is a transcript of this agent. The
+    /// ingestor strips `.gz`/`.zst` suffixes first and decompresses those.
+    fn is_log_file(&self, file_name: &str) -> bool {
+        file_name.ends_with(".jsonl")
+    }
+
+    /// Transcript files under `root`. The default walks the whole tree and
+    /// keeps the files [`LogSource::is_log_file`] accepts, compressed or not;
+    /// sources with a fixed layout can list their files directly.
+    fn log_files(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        let mut stack = vec![root.to_path_buf()];
+        while let Some(path) = stack.pop() {
+            let entries = match path.read_dir() {
+                Ok(entries) => entries,
+                Err(err) => {
+                    tracing::warn!(error = %err, path = %path.display(), "failed to read session directory");
+                    continue;
+                }
+            };
+            for entry in entries {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        tracing::warn!(error = %err, "failed to read directory entry");
+                        continue;
+                    }
+                };
+                let path = entry.path();
+                if path.is_dir() {
+                    stack.push(path);
+                    continue;
+                }
+                let accepted = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| self.is_log_file(strip_compression_suffix(name)));
+                if accepted {
+                    paths.push(path);
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Parses one transcript line. Lines that are not valid for the format
+    /// return an error; valid lines without anything to record return no
+    /// events.
+    fn parse_line(&self, line: &str) -> Result<ParsedLine, ParseError>;
+}
+
+/// Why a transcript line could not be parsed. Recorded in `ingest_errors`
+/// under [`ParseError::kind`].
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error("invalid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    /// Valid JSON for a known record type whose payload no longer has the
+    /// expected shape, e.g. after a format change in a new agent release.
+    #[error("unrecognized {0} payload")]
+    Unrecognized(&'static str),
+}
+
+impl ParseError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::InvalidJson(_) => "invalid_json",
+            ParseError::Unrecognized(_) => "unrecognized",
+        }
+    }
+}
+
+/// `file_name` without a `.gz` or `.zst` suffix.
+pub fn strip_compression_suffix(file_name: &str) -> &str {
+    file_name
+        .strip_suffix(".gz")
+        .or_else(|| file_name.strip_suffix(".zst"))
+        .unwrap_or(file_name)
+}
+
+pub fn source_for(kind: LogSourceKind) -> &'static dyn LogSource {
+    match kind {
+        LogSourceKind::Codex => &CODEX,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedLine {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub events: Vec<LogEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    /// Session identity and metadata, normally the first line of a transcript.
+    Session(SessionInfo),
+    /// Settings for the following turns. `model`, `service_tier` and the
+    /// policy fields are kept when absent; `effort` is replaced.
+    TurnContext {
+        model: Option<String>,
+        effort: Option<String>,
+        service_tier: Option<String>,
+        approval_policy: Option<String>,
+        sandbox_policy: Option<String>,
+        cwd: Option<String>,
+    },
+    /// Cumulative token usage for the session so far, and the usage of the
+    /// latest request when the source reports it.
+    TokenCount {
+        totals: TokenTotals,
+        last: Option<TokenTotals>,
+        context_window: Option<u64>,
+    },
+    RateLimits {
+        primary: Option<RateLimitWindow>,
+        secondary: Option<RateLimitWindow>,
+        plan_type: Option<String>,
+    },
+    UserMessage(String),
+    AgentMessage(String),
+    Reasoning(String),
+    /// A tool invocation; `detail` is the command, query or path it acted on.
+    /// `call_id` links it to its [`LogEvent::ToolOutput`].
+    ToolCall {
+        name: String,
+        detail: Option<String>,
+        call_id: Option<String>,
+    },
+    /// Outcome of an earlier tool call. A call may get several of these (e.g.
+    /// an exec end event and the output item); missing fields keep what the
+    /// others reported.
+    ToolOutput(ToolOutput),
+    /// Files an `apply_patch` call changes, emitted after its
+    /// [`LogEvent::ToolCall`]. The patch may still fail to apply.
+    Patch {
+        call_id: Option<String>,
+        files: Vec<FileLineChange>,
+    },
+    WebSearch {
+        query: Option<String>,
+    },
+    /// The current session started a subagent session.
+    SubagentSpawned {
+        session_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub cwd: Option<String>,
+    pub repo_url: Option<String>,
+    pub repo_branch: Option<String>,
+    pub repo_commit: Option<String>,
+    pub model_provider: Option<String>,
+    pub subagent: Option<String>,
+    /// The session that spawned this one, for subagent sessions.
+    pub parent_session_id: Option<String>,
+    pub service_tier: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolOutput {
+    pub call_id: String,
+    pub exit_code: Option<i64>,
+    /// Explicit success flag; without one, success is a zero exit code.
+    pub success: Option<bool>,
+    pub duration_ms: Option<u64>,
+    pub output_bytes: Option<u64>,
+}
+
+impl ToolOutput {
+    pub fn succeeded(&self) -> Option<bool> {
+        self.success.or(self.exit_code.map(|code| code == 0))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenTotals {
+    pub input_tokens: u64,
+    pub cached_input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_output_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl TokenTotals {
+    pub fn is_zero(self) -> bool {
+        self.input_tokens == 0
+            && self.cached_input_tokens == 0
+            && self.output_tokens == 0
+            && self.reasoning_output_tokens == 0
+            && self.total_tokens == 0
+    }
+
+    pub fn any_decreased(self, previous: Self) -> bool {
+        self.input_tokens < previous.input_tokens
+            || self.cached_input_tokens < previous.cached_input_tokens
+            || self.output_tokens < previous.output_tokens
+            || self.reasoning_output_tokens < previous.reasoning_output_tokens
+            || self.total_tokens < previous.total_tokens
+    }
+
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.saturating_add(other.input_tokens),
+            cached_input_tokens: self
+                .cached_input_tokens
+                .saturating_add(other.cached_input_tokens),
+            output_tokens: self.output_tokens.saturating_add(other.output_tokens),
+            reasoning_output_tokens: self
+                .reasoning_output_tokens
+                .saturating_add(other.reasoning_output_tokens),
+            total_tokens: self.total_tokens.saturating_add(other.total_tokens),
+        }
+    }
+
+    /// Names of the counters that differ between `self` and `other`.
+    pub fn differing_fields(self, other: Self) -> Vec<&'static str> {
+        [
+            ("input", self.input_tokens, other.input_tokens),
+            (
+                "cached_input",
+                self.cached_input_tokens,
+                other.cached_input_tokens,
+            ),
+            ("output", self.output_tokens, other.output_tokens),
+            (
+                "reasoning_output",
+                self.reasoning_output_tokens,
+                other.reasoning_output_tokens,
+            ),
+            ("total", self.total_tokens, other.total_tokens),
+        ]
+        .into_iter()
+        .filter(|(_, a, b)| a != b)
+        .map(|(name, _, _)| name)
+        .collect()
+    }
+
+    pub fn saturating_sub(self, previous: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.saturating_sub(previous.input_tokens),
+            cached_input_tokens: self
+                .cached_input_tokens
+                .saturating_sub(previous.cached_input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(previous.output_tokens),
+            reasoning_output_tokens: self
+                .reasoning_output_tokens
+                .saturating_sub(previous.reasoning_output_tokens),
+            total_tokens: self.total_tokens.saturating_sub(previous.total_tokens),
+        }
+    }
+}
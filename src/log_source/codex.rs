@@ -0,0 +1,780 @@
+use super::codex_exec::{is_exec_event, parse_exec_event};
+use super::{LogEvent, LogSource, ParseError, ParsedLine, SessionInfo, TokenTotals, ToolOutput};
+use crate::storage::{FileLineChange, RateLimitWindow};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Rollout record types, all of which carry a `payload`.
+const ROLLOUT_RECORD_TYPES: [&str; 4] =
+    ["session_meta", "turn_context", "event_msg", "response_item"];
+
+/// Codex CLI rollout files (`~/.codex/sessions/**/rollout-*.jsonl`). Lines
+/// without a rollout `payload` whose type is a `codex exec --json` event
+/// (`thread.*`, `turn.*`, `item.*`) are read as such, so the same source
+/// handles both kinds of piped stream.
+pub struct CodexSource;
+
+impl LogSource for CodexSource {
+    fn parse_line(&self, line: &str) -> Result<ParsedLine, ParseError> {
+        let value: Value = serde_json::from_str(line)?;
+        if value.get("payload").is_none() {
+            let kind = value.get("type").and_then(|v| v.as_str());
+            if kind.is_some_and(is_exec_event) {
+                return Ok(ParsedLine {
+                    timestamp: None,
+                    events: parse_exec_event(&value)?,
+                });
+            }
+            // A rollout record that lost its payload. Unknown types are
+            // skipped below, with or without one.
+            let record = match kind {
+                Some(kind) => ROLLOUT_RECORD_TYPES.into_iter().find(|name| *name == kind),
+                None => Some("record"),
+            };
+            if let Some(record) = record {
+                return Err(ParseError::Unrecognized(record));
+            }
+        }
+        let timestamp = value
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .and_then(parse_timestamp);
+        let mut events = Vec::new();
+        if let Some(kind) = value.get("type").and_then(|v| v.as_str()) {
+            parse_event(kind, &value, timestamp, &mut events)?;
+        }
+        Ok(ParsedLine { timestamp, events })
+    }
+}
+
+fn parse_event(
+    kind: &str,
+    value: &Value,
+    timestamp: Option<DateTime<Utc>>,
+    events: &mut Vec<LogEvent>,
+) -> Result<(), ParseError> {
+    let Some(payload) = value.get("payload") else {
+        return Ok(());
+    };
+    match kind {
+        "session_meta" => {
+            let info =
+                parse_session_meta(payload).ok_or(ParseError::Unrecognized("session_meta"))?;
+            events.push(LogEvent::Session(info));
+        }
+        "turn_context" => {
+            events.push(LogEvent::TurnContext {
+                model: string_field(payload, "model"),
+                effort: payload
+                    .get("effort")
+                    .and_then(|v| v.as_str())
+                    .map(|value| value.trim())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.to_string()),
+                service_tier: string_field(payload, "service_tier"),
+                approval_policy: string_field(payload, "approval_policy"),
+                sandbox_policy: payload.get("sandbox_policy").and_then(parse_sandbox_policy),
+                cwd: string_field(payload, "cwd"),
+            });
+        }
+        "event_msg" => match payload.get("type").and_then(|v| v.as_str()) {
+            Some("token_count") => {
+                if let (Some(rate_limits), Some(ts)) = (payload.get("rate_limits"), timestamp) {
+                    let primary = parse_rate_limit_window(rate_limits, "primary", ts);
+                    let secondary = parse_rate_limit_window(rate_limits, "secondary", ts);
+                    if primary.is_some() || secondary.is_some() {
+                        events.push(LogEvent::RateLimits {
+                            primary,
+                            secondary,
+                            plan_type: string_field(rate_limits, "plan_type"),
+                        });
+                    }
+                }
+                // `info` is null until the first model response of a session.
+                if let Some(info) = payload.get("info").filter(|info| !info.is_null()) {
+                    let totals = info
+                        .get("total_token_usage")
+                        .and_then(parse_totals)
+                        .ok_or(ParseError::Unrecognized("token_count"))?;
+                    let last = info.get("last_token_usage").and_then(parse_totals);
+                    let context_window = info
+                        .get("model_context_window")
+                        .and_then(|v| v.as_u64().or_else(|| v.as_i64().map(|v| v as u64)));
+                    events.push(LogEvent::TokenCount {
+                        totals,
+                        last,
+                        context_window,
+                    });
+                }
+            }
+            Some("user_message") => {
+                if let Some(message) = string_field(payload, "message") {
+                    events.push(LogEvent::UserMessage(message));
+                }
+            }
+            Some("agent_message") => {
+                if let Some(message) = string_field(payload, "message") {
+                    events.push(LogEvent::AgentMessage(message));
+                }
+            }
+            Some("agent_reasoning") => {
+                if let Some(text) = string_field(payload, "text") {
+                    events.push(LogEvent::Reasoning(text));
+                }
+            }
+            Some("exec_command_end") => {
+                if let Some(call_id) = string_field(payload, "call_id") {
+                    let output = payload
+                        .get("aggregated_output")
+                        .or_else(|| payload.get("formatted_output"))
+                        .and_then(|v| v.as_str());
+                    events.push(LogEvent::ToolOutput(ToolOutput {
+                        call_id,
+                        exit_code: payload.get("exit_code").and_then(|v| v.as_i64()),
+                        success: None,
+                        duration_ms: payload.get("duration").and_then(parse_duration_ms),
+                        output_bytes: output.map(|text| text.len() as u64),
+                    }));
+                }
+            }
+            Some("collab_agent_spawn_end") => {
+                if let Some(session_id) = string_field(payload, "new_thread_id") {
+                    events.push(LogEvent::SubagentSpawned { session_id });
+                }
+            }
+            Some("patch_apply_end") => {
+                if let Some(call_id) = string_field(payload, "call_id") {
+                    events.push(LogEvent::ToolOutput(ToolOutput {
+                        call_id,
+                        success: payload.get("success").and_then(|v| v.as_bool()),
+                        ..ToolOutput::default()
+                    }));
+                }
+            }
+            Some("mcp_tool_call_end") => {
+                if let Some(call_id) = string_field(payload, "call_id") {
+                    let result = payload.get("result");
+                    let success = result.map(|result| {
+                        result.get("Ok").is_some_and(|ok| {
+                            !ok.get("isError")
+                                .or_else(|| ok.get("is_error"))
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false)
+                        })
+                    });
+                    events.push(LogEvent::ToolOutput(ToolOutput {
+                        call_id,
+                        success,
+                        duration_ms: payload.get("duration").and_then(parse_duration_ms),
+                        ..ToolOutput::default()
+                    }));
+                }
+            }
+            _ => {}
+        },
+        "response_item" => match payload.get("type").and_then(|v| v.as_str()) {
+            Some("message") => {
+                if let Some(role) = payload.get("role").and_then(|v| v.as_str())
+                    && let Some(text) = extract_message_text(payload)
+                {
+                    if role.eq_ignore_ascii_case("user") {
+                        events.push(LogEvent::UserMessage(text));
+                    } else if role.eq_ignore_ascii_case("assistant") {
+                        events.push(LogEvent::AgentMessage(text));
+                    }
+                }
+            }
+            Some("function_call") | Some("custom_tool_call") => {
+                if let Some(name) = string_field(payload, "name") {
+                    let call_id = string_field(payload, "call_id");
+                    let patch = apply_patch_text(payload).map(|text| parse_apply_patch(&text));
+                    events.push(LogEvent::ToolCall {
+                        name,
+                        detail: tool_call_detail(payload),
+                        call_id: call_id.clone(),
+                    });
+                    if let Some(files) = patch.filter(|files| !files.is_empty()) {
+                        events.push(LogEvent::Patch { call_id, files });
+                    }
+                }
+            }
+            Some("function_call_output") | Some("custom_tool_call_output") => {
+                if let Some(call_id) = string_field(payload, "call_id") {
+                    events.push(LogEvent::ToolOutput(parse_call_output(call_id, payload)));
+                }
+            }
+            Some("web_search_call") => {
+                events.push(LogEvent::WebSearch {
+                    query: payload
+                        .get("action")
+                        .and_then(|v| v.get("query"))
+                        .and_then(|v| v.as_str())
+                        .map(|v| v.to_string()),
+                });
+            }
+            Some("local_shell_call") => {
+                let name = payload
+                    .get("action")
+                    .and_then(|v| v.get("type"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("local_shell");
+                events.push(LogEvent::ToolCall {
+                    name: name.to_string(),
+                    detail: None,
+                    call_id: string_field(payload, "call_id"),
+                });
+            }
+            Some("reasoning") => {
+                if let Some(text) = reasoning_summary_text(payload) {
+                    events.push(LogEvent::Reasoning(text));
+                }
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Reads a `function_call_output` item. Its `output` is either JSON with the
+/// text and an `exit_code`/`duration_seconds` metadata object, or plain text
+/// that may start with `Exit code:` and `Wall time:` header lines.
+fn parse_call_output(call_id: String, payload: &Value) -> ToolOutput {
+    let mut result = ToolOutput {
+        call_id,
+        ..ToolOutput::default()
+    };
+    let raw = match payload.get("output") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Object(map)) => {
+            result.success = map.get("success").and_then(|v| v.as_bool());
+            map.get("content")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        }
+        _ => return result,
+    };
+    if let Ok(parsed) = serde_json::from_str::<Value>(&raw)
+        && parsed.is_object()
+    {
+        let text = parsed.get("output").and_then(|v| v.as_str()).unwrap_or("");
+        result.output_bytes = Some(text.len() as u64);
+        if let Some(metadata) = parsed.get("metadata") {
+            result.exit_code = metadata.get("exit_code").and_then(|v| v.as_i64());
+            result.duration_ms = metadata
+                .get("duration_seconds")
+                .and_then(|v| v.as_f64())
+                .map(seconds_to_ms);
+        }
+        return result;
+    }
+
+    let mut body = raw.as_str();
+    while let Some((line, rest)) = body.split_once('\n') {
+        if let Some(code) = line.strip_prefix("Exit code:") {
+            result.exit_code = code.trim().parse().ok();
+        } else if let Some(wall) = line.strip_prefix("Wall time:") {
+            result.duration_ms = wall
+                .trim()
+                .trim_end_matches("seconds")
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(seconds_to_ms);
+        } else if line.starts_with("Total output lines:") {
+        } else if line == "Output:" {
+            body = rest;
+            break;
+        } else {
+            break;
+        }
+        body = rest;
+    }
+    result.output_bytes = Some(body.len() as u64);
+    result
+}
+
+/// Durations appear as `{"secs": .., "nanos": ..}`, as seconds, or as a
+/// string like `"1.5s"`/`"250ms"`.
+fn parse_duration_ms(value: &Value) -> Option<u64> {
+    match value {
+        Value::Object(map) => {
+            let secs = map.get("secs")?.as_u64()?;
+            let nanos = map.get("nanos").and_then(|v| v.as_u64()).unwrap_or(0);
+            Some(secs * 1000 + nanos / 1_000_000)
+        }
+        Value::Number(secs) => secs.as_f64().map(seconds_to_ms),
+        Value::String(text) => {
+            let text = text.trim();
+            if let Some(ms) = text.strip_suffix("ms") {
+                ms.trim().parse::<f64>().ok().map(|ms| ms.round() as u64)
+            } else {
+                text.trim_end_matches('s')
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .map(seconds_to_ms)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn seconds_to_ms(secs: f64) -> u64 {
+    (secs.max(0.0) * 1000.0).round() as u64
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+}
+
+/// Older releases write the policy as a bare string, newer ones as an object
+/// tagged with `type` (or `mode`) plus its options.
+fn parse_sandbox_policy(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .or_else(|| value.get("type").and_then(|v| v.as_str()))
+        .or_else(|| value.get("mode").and_then(|v| v.as_str()))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+fn parse_totals(value: &Value) -> Option<TokenTotals> {
+    Some(TokenTotals {
+        input_tokens: value.get("input_tokens")?.as_u64()?,
+        cached_input_tokens: value
+            .get("cached_input_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        output_tokens: value.get("output_tokens")?.as_u64()?,
+        reasoning_output_tokens: value
+            .get("reasoning_output_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        total_tokens: value.get("total_tokens")?.as_u64()?,
+    })
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Reads a rate limit window from either the nested (`primary.used_percent`)
+/// or the older flat (`primary_used_percent`) layout. Reset times may be given
+/// as an absolute `resets_at` (unix seconds or RFC 3339) or relative
+/// `resets_in_seconds`.
+fn parse_rate_limit_window(
+    value: &Value,
+    name: &str,
+    timestamp: DateTime<Utc>,
+) -> Option<RateLimitWindow> {
+    let field = |key: &str| match value.get(name) {
+        Some(window) if window.is_object() => window.get(key),
+        _ => value.get(format!("{name}_{key}")),
+    };
+    let used_percent = field("used_percent")?.as_f64()?;
+    let window_minutes = field("window_minutes").and_then(|v| v.as_u64());
+    let resets_at = match field("resets_at") {
+        Some(Value::Number(secs)) => secs
+            .as_i64()
+            .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0)),
+        Some(Value::String(text)) => parse_timestamp(text),
+        _ => None,
+    }
+    .or_else(|| {
+        field("resets_in_seconds")
+            .and_then(|v| v.as_i64())
+            .map(|secs| timestamp + chrono::Duration::seconds(secs))
+    });
+    Some(RateLimitWindow {
+        used_percent,
+        window_minutes,
+        resets_at,
+    })
+}
+
+/// Reads `source.subagent`, which is either a plain kind (`"review"`) or an
+/// object keyed by kind, e.g. `{"thread_spawn": {"parent_thread_id": ...}}`.
+fn parse_subagent(payload: &Value) -> (Option<String>, Option<String>) {
+    match payload.get("source").and_then(|v| v.get("subagent")) {
+        Some(Value::String(kind)) => (Some(kind.clone()), None),
+        Some(Value::Object(map)) => {
+            let Some((kind, detail)) = map.iter().next() else {
+                return (None, None);
+            };
+            let parent = string_field(detail, "parent_thread_id");
+            let kind = detail.as_str().unwrap_or(kind).to_string();
+            (Some(kind), parent)
+        }
+        _ => (None, None),
+    }
+}
+
+fn parse_session_meta(payload: &Value) -> Option<SessionInfo> {
+    let git = payload.get("git");
+    let git_field = |key: &str| git.and_then(|git| string_field(git, key));
+    let (subagent, parent_session_id) = parse_subagent(payload);
+    Some(SessionInfo {
+        session_id: payload.get("id")?.as_str()?.to_string(),
+        started_at: payload
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .and_then(parse_timestamp),
+        cwd: string_field(payload, "cwd"),
+        repo_url: git_field("repository_url"),
+        repo_branch: git_field("branch"),
+        repo_commit: git_field("commit_hash"),
+        model_provider: string_field(payload, "model_provider"),
+        subagent,
+        parent_session_id,
+        service_tier: string_field(payload, "service_tier"),
+    })
+}
+
+fn extract_message_text(payload: &Value) -> Option<String> {
+    let content = payload.get("content")?;
+    if let Some(arr) = content.as_array() {
+        let mut acc = String::new();
+        for entry in arr {
+            if let Some(text) = entry.get("text").and_then(|v| v.as_str())
+                && !text.trim().is_empty()
+            {
+                if !acc.is_empty() {
+                    acc.push(' ');
+                }
+                acc.push_str(text.trim());
+            }
+        }
+        if acc.is_empty() { None } else { Some(acc) }
+    } else {
+        content.as_str().map(|text| text.to_string())
+    }
+}
+
+/// The most descriptive argument of a tool call: its command, query, path or
+/// raw input.
+fn tool_call_detail(payload: &Value) -> Option<String> {
+    if let Some(args) = payload.get("arguments").and_then(|v| v.as_str()) {
+        let Ok(parsed) = serde_json::from_str::<Value>(args) else {
+            return Some(args.to_string());
+        };
+        ["command", "query", "path", "input", "code"]
+            .iter()
+            .find_map(|key| string_field(&parsed, key))
+    } else {
+        string_field(payload, "input")
+    }
+}
+
+/// The patch body of an `apply_patch` call: the raw `input` of the custom
+/// tool, or the `input`/`command` argument of the function and shell forms.
+fn apply_patch_text(payload: &Value) -> Option<String> {
+    let is_patch = |text: &str| text.trim_start().starts_with(PATCH_BEGIN);
+    if let Some(input) = payload.get("input").and_then(|v| v.as_str()) {
+        return is_patch(input).then(|| input.to_string());
+    }
+    let args = payload.get("arguments").and_then(|v| v.as_str())?;
+    let parsed = serde_json::from_str::<Value>(args).ok()?;
+    match parsed.get("input").or_else(|| parsed.get("command"))? {
+        Value::String(text) => is_patch(text).then(|| text.clone()),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.as_str())
+            .find(|part| is_patch(part))
+            .map(|part| part.to_string()),
+        _ => None,
+    }
+}
+
+const PATCH_BEGIN: &str = "*** Begin Patch";
+
+/// Counts the lines each file section of an `apply_patch` body adds and
+/// removes. Deleted files carry no line content, so they count as touched
+/// with nothing removed.
+fn parse_apply_patch(text: &str) -> Vec<FileLineChange> {
+    let mut files: Vec<FileLineChange> = Vec::new();
+    let mut in_file = false;
+    for line in text.lines() {
+        let header = ["*** Add File: ", "*** Update File: ", "*** Delete File: "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix));
+        if let Some(path) = header {
+            files.push(FileLineChange {
+                path: path.trim().to_string(),
+                ..FileLineChange::default()
+            });
+            in_file = !line.starts_with("*** Delete File: ");
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("*** Move to: ")
+            && let Some(file) = files.last_mut()
+        {
+            file.path = path.trim().to_string();
+            continue;
+        }
+        if line.starts_with("***") {
+            in_file = in_file && line.starts_with("*** End of File");
+            continue;
+        }
+        let Some(file) = files.last_mut().filter(|_| in_file) else {
+            continue;
+        };
+        if line.starts_with('+') {
+            file.added += 1;
+        } else if line.starts_with('-') {
+            file.removed += 1;
+        }
+    }
+    files
+}
+
+fn reasoning_summary_text(payload: &Value) -> Option<String> {
+    let non_empty = |text: &str| (!text.trim().is_empty()).then(|| text.to_string());
+    if let Some(summary) = payload.get("summary").and_then(|v| v.as_array()) {
+        for entry in summary {
+            let text = entry
+                .get("text")
+                .and_then(|v| v.as_str())
+                .or_else(|| entry.as_str());
+            if let Some(text) = text.and_then(non_empty) {
+                return Some(text);
+            }
+        }
+    }
+    payload
+        .get("content")
+        .and_then(|v| v.as_array())?
+        .iter()
+        .filter_map(|entry| entry.get("text").and_then(|v| v.as_str()))
+        .find_map(non_empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_count_line_yields_rate_limits_then_totals() {
+        let line = r#"{"timestamp":"2025-06-01T12:00:00Z","type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"input_tokens":10,"output_tokens":2,"total_tokens":12},"last_token_usage":{"input_tokens":4,"output_tokens":1,"total_tokens":5},"model_context_window":272000},"rate_limits":{"primary_used_percent":5.0,"primary_window_minutes":300}}}"#;
+        let parsed = CodexSource.parse_line(line).unwrap();
+        assert!(parsed.timestamp.is_some());
+        let [
+            LogEvent::RateLimits { primary, .. },
+            LogEvent::TokenCount {
+                totals,
+                last,
+                context_window,
+            },
+        ] = parsed.events.as_slice()
+        else {
+            panic!("unexpected events: {:?}", parsed.events);
+        };
+        assert_eq!(primary.as_ref().map(|w| w.window_minutes), Some(Some(300)));
+        assert_eq!(totals.total_tokens, 12);
+        assert_eq!(totals.cached_input_tokens, 0);
+        assert_eq!(last.map(|last| last.total_tokens), Some(5));
+        assert_eq!(*context_window, Some(272000));
+
+        let err = CodexSource.parse_line("not json").unwrap_err();
+        assert_eq!(err.kind(), "invalid_json");
+        let drifted =
+            r#"{"type":"event_msg","payload":{"type":"token_count","info":{"total_usage":{}}}}"#;
+        let err = CodexSource.parse_line(drifted).unwrap_err();
+        assert_eq!(err.kind(), "unrecognized");
+        let parsed = CodexSource.parse_line(r#"{"type":"unknown"}"#).unwrap();
+        assert!(parsed.events.is_empty());
+    }
+
+    #[test]
+    fn apply_patch_calls_yield_line_counts_per_file() {
+        let patch = "*** Begin Patch\n*** Add File: src/new.rs\n+fn a() {}\n+\n*** Update File: src/lib.rs\n*** Move to: src/core.rs\n@@ fn b()\n context\n-old\n+new\n+more\n*** End of File\n*** Delete File: src/gone.rs\n*** End Patch";
+        let custom = serde_json::json!({
+            "type": "response_item",
+            "payload": {"type": "custom_tool_call", "name": "apply_patch", "call_id": "c1", "input": patch},
+        });
+        let parsed = CodexSource.parse_line(&custom.to_string()).unwrap();
+        let [
+            LogEvent::ToolCall { name, .. },
+            LogEvent::Patch { call_id, files },
+        ] = parsed.events.as_slice()
+        else {
+            panic!("unexpected events: {:?}", parsed.events);
+        };
+        assert_eq!(name, "apply_patch");
+        assert_eq!(call_id.as_deref(), Some("c1"));
+        let counts: Vec<_> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.added, f.removed))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("src/new.rs", 2, 0),
+                ("src/core.rs", 2, 1),
+                ("src/gone.rs", 0, 0)
+            ]
+        );
+
+        let args = serde_json::json!({"command": ["apply_patch", patch]}).to_string();
+        let shell = serde_json::json!({
+            "type": "response_item",
+            "payload": {"type": "function_call", "name": "shell", "call_id": "c2", "arguments": args},
+        });
+        let parsed = CodexSource.parse_line(&shell.to_string()).unwrap();
+        assert!(matches!(
+            parsed.events.as_slice(),
+            [LogEvent::ToolCall { .. }, LogEvent::Patch { files, .. }] if files.len() == 3
+        ));
+    }
+
+    #[test]
+    fn only_exec_events_are_read_without_a_payload() {
+        let err = CodexSource
+            .parse_line(r#"{"type":"event_msg"}"#)
+            .unwrap_err();
+        assert_eq!(err.kind(), "unrecognized");
+        assert_eq!(err.to_string(), "unrecognized event_msg payload");
+        let err = CodexSource.parse_line(r#"{"status":"ok"}"#).unwrap_err();
+        assert_eq!(err.to_string(), "unrecognized record payload");
+
+        let parsed = CodexSource
+            .parse_line(r#"{"type":"turn.started"}"#)
+            .unwrap();
+        assert_eq!(parsed.timestamp, None);
+        assert!(parsed.events.is_empty());
+    }
+
+    #[test]
+    fn subagent_sessions_name_their_parent() {
+        let session = |line: &str| match CodexSource.parse_line(line).unwrap().events.as_slice() {
+            [LogEvent::Session(info)] => info.clone(),
+            events => panic!("unexpected events: {events:?}"),
+        };
+        let spawned = session(
+            r#"{"type":"session_meta","payload":{"id":"child","source":{"subagent":{"thread_spawn":{"parent_thread_id":"parent","depth":1}}}}}"#,
+        );
+        assert_eq!(spawned.subagent.as_deref(), Some("thread_spawn"));
+        assert_eq!(spawned.parent_session_id.as_deref(), Some("parent"));
+        let review = session(
+            r#"{"type":"session_meta","payload":{"id":"review","source":{"subagent":"review"}}}"#,
+        );
+        assert_eq!(review.subagent.as_deref(), Some("review"));
+        assert_eq!(review.parent_session_id, None);
+
+        let parsed = CodexSource
+            .parse_line(r#"{"type":"event_msg","payload":{"type":"collab_agent_spawn_end","call_id":"c1","sender_thread_id":"parent","new_thread_id":"child"}}"#)
+            .unwrap();
+        assert!(matches!(
+            parsed.events.as_slice(),
+            [LogEvent::SubagentSpawned { session_id }] if session_id == "child"
+        ));
+    }
+
+    #[test]
+    fn turn_context_carries_approval_and_sandbox_policies() {
+        let context = |line: &str| match CodexSource.parse_line(line).unwrap().events.as_slice() {
+            [
+                LogEvent::TurnContext {
+                    approval_policy,
+                    sandbox_policy,
+                    cwd,
+                    ..
+                },
+            ] => (approval_policy.clone(), sandbox_policy.clone(), cwd.clone()),
+            events => panic!("unexpected events: {events:?}"),
+        };
+        assert_eq!(
+            context(
+                r#"{"type":"turn_context","payload":{"cwd":"/work/app","approval_policy":"on-request","sandbox_policy":{"type":"workspace-write","network_access":false},"model":"gpt-5"}}"#,
+            ),
+            (
+                Some("on-request".to_string()),
+                Some("workspace-write".to_string()),
+                Some("/work/app".to_string()),
+            )
+        );
+        assert_eq!(
+            context(
+                r#"{"type":"turn_context","payload":{"approval_policy":"never","sandbox_policy":{"mode":"danger-full-access"}}}"#,
+            )
+            .1
+            .as_deref(),
+            Some("danger-full-access")
+        );
+        assert_eq!(
+            context(r#"{"type":"turn_context","payload":{"sandbox_policy":"read-only"}}"#).1,
+            Some("read-only".to_string())
+        );
+    }
+
+    #[test]
+    fn tool_outputs_carry_exit_code_duration_and_size() {
+        let output = |line: &str| match CodexSource.parse_line(line).unwrap().events.as_slice() {
+            [LogEvent::ToolOutput(output)] => output.clone(),
+            events => panic!("unexpected events: {events:?}"),
+        };
+
+        let json = output(
+            r#"{"type":"response_item","payload":{"type":"function_call_output","call_id":"c1","output":"{\"output\":\"boom\\n\",\"metadata\":{\"exit_code\":2,\"duration_seconds\":1.25}}"}}"#,
+        );
+        assert_eq!(json.call_id, "c1");
+        assert_eq!(json.exit_code, Some(2));
+        assert_eq!(json.succeeded(), Some(false));
+        assert_eq!(json.duration_ms, Some(1250));
+        assert_eq!(json.output_bytes, Some(5));
+
+        let text = output(
+            r#"{"type":"response_item","payload":{"type":"function_call_output","call_id":"c2","output":"Exit code: 0\nWall time: 0.5 seconds\nOutput:\nok"}}"#,
+        );
+        assert_eq!(text.exit_code, Some(0));
+        assert_eq!(text.duration_ms, Some(500));
+        assert_eq!(text.output_bytes, Some(2));
+
+        let exec = output(
+            r#"{"type":"event_msg","payload":{"type":"exec_command_end","call_id":"c3","exit_code":1,"duration":{"secs":3,"nanos":200000000},"aggregated_output":"err"}}"#,
+        );
+        assert_eq!(exec.duration_ms, Some(3200));
+        assert_eq!(exec.succeeded(), Some(false));
+
+        let patch = output(
+            r#"{"type":"event_msg","payload":{"type":"patch_apply_end","call_id":"c4","success":true}}"#,
+        );
+        assert_eq!(patch.succeeded(), Some(true));
+        assert_eq!(patch.exit_code, None);
+    }
+
+    #[test]
+    fn default_discovery_walks_the_tree_for_transcripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = dir.path().join("2025/06/01");
+        std::fs::create_dir_all(&day).unwrap();
+        for name in [
+            "rollout-a.jsonl",
+            "rollout-b.jsonl.gz",
+            "rollout-c.jsonl.zst",
+            "notes.txt",
+        ] {
+            std::fs::write(day.join(name), "").unwrap();
+        }
+        std::fs::write(dir.path().join("rollout-d.jsonl"), "").unwrap();
+
+        let mut files = CodexSource.log_files(dir.path()).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                day.join("rollout-a.jsonl"),
+                day.join("rollout-b.jsonl.gz"),
+                day.join("rollout-c.jsonl.zst"),
+                dir.path().join("rollout-d.jsonl"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Events printed by `codex exec --json`. Unlike rollout records they have no
+//! `timestamp`/`payload` envelope: each line is `{"type": ..}` with the event's
+//! fields inline, and the model is not named anywhere in the stream.
+
+use super::{LogEvent, ParseError, SessionInfo, TokenTotals, ToolOutput};
+use serde_json::Value;
+
+/// Whether `kind` is the type of an exec stream event rather than a rollout
+/// record.
+pub(super) fn is_exec_event(kind: &str) -> bool {
+    ["thread.", "turn.", "item."]
+        .iter()
+        .any(|prefix| kind.starts_with(prefix))
+}
+
+/// Events of one exec stream line. Unknown event and item types yield none.
+pub(super) fn parse_exec_event(value: &Value) -> Result<Vec<LogEvent>, ParseError> {
+    let mut events = Vec::new();
+    match value.get("type").and_then(|v| v.as_str()) {
+        Some("thread.started") => {
+            let session_id = value
+                .get("thread_id")
+                .and_then(|v| v.as_str())
+                .ok_or(ParseError::Unrecognized("thread.started"))?;
+            events.push(LogEvent::Session(SessionInfo {
+                session_id: session_id.to_string(),
+                ..SessionInfo::default()
+            }));
+        }
+        // `usage` is the thread's cumulative usage so far, like a rollout
+        // `token_count`, so a resumed thread continues from its earlier turns.
+        Some("turn.completed") => {
+            let totals = value
+                .get("usage")
+                .and_then(parse_usage)
+                .ok_or(ParseError::Unrecognized("turn.completed"))?;
+            events.push(LogEvent::TokenCount {
+                totals,
+                last: None,
+                context_window: None,
+            });
+        }
+        Some("item.completed") => {
+            if let Some(item) = value.get("item") {
+                parse_item(item, &mut events);
+            }
+        }
+        _ => {}
+    }
+    Ok(events)
+}
+
+fn parse_item(item: &Value, events: &mut Vec<LogEvent>) {
+    let text = || {
+        item.get("text")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    let call_id = item.get("id").and_then(|v| v.as_str()).map(str::to_string);
+    match item.get("type").and_then(|v| v.as_str()) {
+        Some("agent_message") => events.extend(text().map(LogEvent::AgentMessage)),
+        Some("reasoning") => events.extend(text().map(LogEvent::Reasoning)),
+        Some("command_execution") => {
+            events.push(LogEvent::ToolCall {
+                name: "shell".to_string(),
+                detail: item
+                    .get("command")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string()),
+                call_id: call_id.clone(),
+            });
+            if let Some(call_id) = call_id {
+                events.push(LogEvent::ToolOutput(ToolOutput {
+                    call_id,
+                    exit_code: item.get("exit_code").and_then(|v| v.as_i64()),
+                    output_bytes: item
+                        .get("aggregated_output")
+                        .and_then(|v| v.as_str())
+                        .map(|text| text.len() as u64),
+                    ..ToolOutput::default()
+                }));
+            }
+        }
+        Some("file_change") => {
+            events.push(LogEvent::ToolCall {
+                name: "apply_patch".to_string(),
+                detail: None,
+                call_id: call_id.clone(),
+            });
+            if let Some(call_id) = call_id {
+                events.push(LogEvent::ToolOutput(ToolOutput {
+                    call_id,
+                    success: item_succeeded(item),
+                    ..ToolOutput::default()
+                }));
+            }
+        }
+        Some("mcp_tool_call") => {
+            let Some(tool) = item.get("tool").and_then(|v| v.as_str()) else {
+                return;
+            };
+            // Rollouts name MCP calls `<server>__<tool>`.
+            let name = match item.get("server").and_then(|v| v.as_str()) {
+                Some(server) => format!("{server}__{tool}"),
+                None => tool.to_string(),
+            };
+            events.push(LogEvent::ToolCall {
+                name,
+                detail: None,
+                call_id: call_id.clone(),
+            });
+            if let Some(call_id) = call_id {
+                events.push(LogEvent::ToolOutput(ToolOutput {
+                    call_id,
+                    success: item_succeeded(item),
+                    ..ToolOutput::default()
+                }));
+            }
+        }
+        Some("web_search") => events.push(LogEvent::WebSearch {
+            query: item
+                .get("query")
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string()),
+        }),
+        _ => {}
+    }
+}
+
+fn item_succeeded(item: &Value) -> Option<bool> {
+    match item.get("status").and_then(|v| v.as_str())? {
+        "completed" => Some(true),
+        "failed" => Some(false),
+        _ => None,
+    }
+}
+
+/// Exec usage has no reasoning or total counts; reasoning is part of
+/// `output_tokens` and the total is input plus output, as in rollouts.
+fn parse_usage(value: &Value) -> Option<TokenTotals> {
+    let input_tokens = value.get("input_tokens")?.as_u64()?;
+    let output_tokens = value.get("output_tokens")?.as_u64()?;
+    Some(TokenTotals {
+        input_tokens,
+        cached_input_tokens: value
+            .get("cached_input_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        output_tokens,
+        reasoning_output_tokens: 0,
+        total_tokens: input_tokens + output_tokens,
+    })
+}
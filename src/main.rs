@@ -1,24 +1,36 @@
 mod app;
 mod cli;
 mod config;
+mod doctor;
+mod fx;
+mod git;
 mod ingest;
+mod log_source;
 mod pricing_remote;
+mod report;
 mod storage;
 mod tokens;
 mod tui;
 
 use anyhow::Result;
-use clap::Parser;
 use std::fs::OpenOptions;
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     init_tracing();
-    let cli = cli::Cli::parse();
+    let cli = cli::Cli::parse_args();
     let config = config::AppConfig::load(cli.config_path.as_deref())?;
     let app = app::App::new(config).await?;
-    app.run(cli.rebuild).await
+    match cli.command {
+        Some(cli::Command::Daemon(args)) => app.run_daemon(args.rebuild).await,
+        Some(cli::Command::Report(args)) => app.run_report(&args).await,
+        Some(cli::Command::Doctor) => app.run_doctor().await,
+        Some(cli::Command::AttributeCommits) => app.run_attribute_commits().await,
+        Some(cli::Command::Ingest(args)) => app.run_ingest_stream(&args).await,
+        None if cli.headless => app.run_daemon(cli.rebuild).await,
+        None => app.run(cli.rebuild, !cli.no_ingest).await,
+    }
 }
 
 fn init_tracing() {
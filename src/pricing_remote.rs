@@ -1,22 +1,39 @@
 use crate::{
     config::PricingConfig,
-    storage::{NewPrice, Storage},
+    storage::{DEFAULT_SERVICE_TIER, LongContextPrice, NewPrice, PriceOverride, Storage},
 };
 use anyhow::{Context, Result};
-use chrono::{Duration, NaiveDate, Utc};
+use chrono::{Duration, Local, NaiveDate, Utc};
 use reqwest::Client;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration as StdDuration;
 use tokio::task;
 
 const MILLION: f64 = 1_000_000.0;
+/// Service tiers priced separately in the dataset, keyed by rate suffix.
+const SERVICE_TIER_SUFFIXES: [(&str, &str); 3] = [
+    ("flex", "flex"),
+    ("priority", "priority"),
+    ("batch", "batches"),
+];
+/// LiteLLM-format pricing compiled into the binary, used only when nothing is
+/// cached and the configured source cannot be read.
+const BUNDLED_PRICES: &str = include_str!("../assets/pricing_snapshot.json");
+const BUNDLED_SOURCE: &str = "bundled snapshot";
+/// Day the bundled snapshot was taken; recorded as its fetch time so the
+/// configured source is retried on the next check.
+const BUNDLED_SNAPSHOT_DATE: (i32, u32, u32) = (2025, 11, 20);
 
 #[derive(Debug, Deserialize, Clone)]
 struct RemotePricing {
     input_cost_per_token: Option<f64>,
     output_cost_per_token: Option<f64>,
     cache_read_input_token_cost: Option<f64>,
+    /// Remaining fields, scanned for `*_above_<N>k_tokens` tiered rates.
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
 }
 
 pub async fn sync_if_needed(config: &PricingConfig, storage: &Storage) -> Result<bool> {
@@ -36,10 +53,10 @@ pub async fn sync_if_needed(config: &PricingConfig, storage: &Storage) -> Result
 
     match fetch_prices(config).await {
         Ok(prices) => {
-            let count = storage
-                .replace_prices(&prices, &config.remote.url, now)
+            let changed = storage
+                .sync_prices(&prices, &config.remote.url, now)
                 .await?;
-            tracing::info!(count, "refreshed remote pricing");
+            tracing::info!(count = prices.len(), changed, "refreshed remote pricing");
             Ok(true)
         }
         Err(err) => {
@@ -47,28 +64,126 @@ pub async fn sync_if_needed(config: &PricingConfig, storage: &Storage) -> Result
                 tracing::warn!(error = %err, "failed to refresh pricing; using cached prices");
                 Ok(false)
             } else {
-                Err(err)
+                tracing::warn!(error = %err, "failed to load pricing; using bundled snapshot");
+                load_bundled(config, storage)
+                    .await
+                    .with_context(|| format!("no cached prices and fetch failed: {err:#}"))?;
+                Ok(true)
             }
         }
     }
 }
 
+async fn load_bundled(config: &PricingConfig, storage: &Storage) -> Result<()> {
+    let (year, month, day) = BUNDLED_SNAPSHOT_DATE;
+    let snapshot_date = NaiveDate::from_ymd_opt(year, month, day)
+        .with_context(|| "invalid bundled snapshot date")?;
+    let prices = parse_prices(BUNDLED_PRICES.as_bytes(), &config.currency, snapshot_date)?;
+    let fetched_at = snapshot_date
+        .and_hms_opt(0, 0, 0)
+        .with_context(|| "invalid bundled snapshot date")?
+        .and_utc();
+    storage
+        .sync_prices(&prices, BUNDLED_SOURCE, fetched_at)
+        .await?;
+    tracing::info!(count = prices.len(), "loaded bundled pricing snapshot");
+    Ok(())
+}
+
 pub async fn force_sync(config: &PricingConfig, storage: &Storage) -> Result<usize> {
     let now = Utc::now();
     let prices = fetch_prices(config).await?;
-    storage
-        .replace_prices(&prices, &config.remote.url, now)
-        .await
+    storage.sync_prices(&prices, &config.remote.url, now).await
+}
+
+/// Stores the configured `[pricing.overrides]`, replacing any previous set.
+pub async fn sync_overrides(config: &PricingConfig, storage: &Storage) -> Result<()> {
+    let overrides: Vec<PriceOverride> = config
+        .overrides
+        .iter()
+        .map(|(key, price)| {
+            let (model, service_tier) = split_override_key(key);
+            PriceOverride {
+                model,
+                service_tier,
+                effective_from: price.effective_from,
+                currency: config.currency.clone(),
+                prompt_per_1m: price.prompt_per_1m,
+                cached_prompt_per_1m: price.cached_prompt_per_1m,
+                completion_per_1m: price.completion_per_1m,
+                long_context: match (price.long_context_threshold, price.long_prompt_per_1m) {
+                    (Some(threshold_tokens), Some(prompt_per_1m)) => Some(LongContextPrice {
+                        threshold_tokens,
+                        prompt_per_1m,
+                        cached_prompt_per_1m: price.long_cached_prompt_per_1m,
+                        completion_per_1m: price
+                            .long_completion_per_1m
+                            .unwrap_or(price.completion_per_1m),
+                    }),
+                    _ => None,
+                },
+            }
+        })
+        .collect();
+    storage.replace_price_overrides(&overrides).await?;
+    if !overrides.is_empty() {
+        tracing::info!(count = overrides.len(), "applied price overrides");
+    }
+    Ok(())
+}
+
+/// Splits an override key such as `gpt-5@flex` into model and service tier;
+/// keys without a tier apply to the default tier.
+fn split_override_key(key: &str) -> (String, String) {
+    match key.rsplit_once('@') {
+        Some((model, tier)) if !tier.trim().is_empty() => {
+            (model.trim().to_string(), tier.trim().to_ascii_lowercase())
+        }
+        _ => (
+            key.trim().trim_end_matches('@').to_string(),
+            DEFAULT_SERVICE_TIER.to_string(),
+        ),
+    }
 }
 
 async fn fetch_prices(config: &PricingConfig) -> Result<Vec<NewPrice>> {
+    let url = config.remote.url.trim();
+    let bytes = match local_source_path(url) {
+        Some(path) => tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("failed to read pricing dataset {}", path.display()))?,
+        None => download_dataset(url, config.remote.timeout_secs).await?,
+    };
+    let currency = config.currency.clone();
+    let effective_from = Local::now().date_naive();
+
+    let prices = task::spawn_blocking(move || parse_prices(&bytes, &currency, effective_from))
+        .await
+        .with_context(|| "pricing parse task failed")??;
+
+    Ok(prices)
+}
+
+/// Resolves `file://` URLs and plain filesystem paths; anything with another
+/// scheme is fetched over HTTP.
+fn local_source_path(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+    if url.contains("://") {
+        return None;
+    }
+    Some(PathBuf::from(url))
+}
+
+async fn download_dataset(url: &str, timeout_secs: u64) -> Result<Vec<u8>> {
     let client = Client::builder()
-        .timeout(StdDuration::from_secs(config.remote.timeout_secs.max(1)))
+        .timeout(StdDuration::from_secs(timeout_secs.max(1)))
         .build()
         .with_context(|| "failed to build pricing http client")?;
 
     let response = client
-        .get(&config.remote.url)
+        .get(url)
         .send()
         .await
         .with_context(|| "failed to fetch pricing dataset")?;
@@ -84,20 +199,20 @@ async fn fetch_prices(config: &PricingConfig) -> Result<Vec<NewPrice>> {
         .bytes()
         .await
         .with_context(|| "failed to read pricing dataset")?;
-    let currency = config.currency.clone();
-
-    let prices = task::spawn_blocking(move || -> Result<Vec<NewPrice>> {
-        let raw: HashMap<String, RemotePricing> =
-            serde_json::from_slice(&bytes).with_context(|| "failed to parse pricing dataset")?;
-        Ok(build_prices(raw, &currency))
-    })
-    .await
-    .with_context(|| "pricing parse task failed")??;
+    Ok(bytes.to_vec())
+}
 
-    Ok(prices)
+fn parse_prices(bytes: &[u8], currency: &str, effective_from: NaiveDate) -> Result<Vec<NewPrice>> {
+    let raw: HashMap<String, RemotePricing> =
+        serde_json::from_slice(bytes).with_context(|| "failed to parse pricing dataset")?;
+    Ok(build_prices(raw, currency, effective_from))
 }
 
-fn build_prices(raw: HashMap<String, RemotePricing>, currency: &str) -> Vec<NewPrice> {
+fn build_prices(
+    raw: HashMap<String, RemotePricing>,
+    currency: &str,
+    effective_from: NaiveDate,
+) -> Vec<NewPrice> {
     let mut resolved: HashMap<String, RemotePricing> = HashMap::new();
 
     for (key, record) in raw {
@@ -107,16 +222,25 @@ fn build_prices(raw: HashMap<String, RemotePricing>, currency: &str) -> Vec<NewP
         resolved.entry(model).or_insert(record);
     }
 
-    let effective_from = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
     let mut prices = Vec::with_capacity(resolved.len());
     for (model, record) in resolved {
         let Some(price) = normalize_pricing(&model, &record, currency, effective_from) else {
             continue;
         };
         prices.push(price);
+        prices.extend(service_tier_pricing(
+            &model,
+            &record,
+            currency,
+            effective_from,
+        ));
     }
 
-    prices.sort_by(|a, b| a.model.cmp(&b.model));
+    prices.sort_by(|a, b| {
+        a.model
+            .cmp(&b.model)
+            .then_with(|| a.service_tier.cmp(&b.service_tier))
+    });
     prices
 }
 
@@ -172,11 +296,76 @@ fn normalize_pricing(
 
     Some(NewPrice {
         model: model.to_string(),
+        service_tier: DEFAULT_SERVICE_TIER.to_string(),
         effective_from,
         currency: currency.to_string(),
         prompt_per_1m,
         cached_prompt_per_1m,
         completion_per_1m,
+        long_context: long_context_pricing(record),
+    })
+}
+
+/// Reads the `_flex`, `_priority` and `_batches` rate variants as separate
+/// service tier prices. Tiers without both input and output rates are skipped.
+fn service_tier_pricing(
+    model: &str,
+    record: &RemotePricing,
+    currency: &str,
+    effective_from: NaiveDate,
+) -> Vec<NewPrice> {
+    SERVICE_TIER_SUFFIXES
+        .iter()
+        .filter_map(|(tier, suffix)| {
+            let rate = |prefix: &str| {
+                record
+                    .extra
+                    .get(&format!("{prefix}_{suffix}"))
+                    .and_then(serde_json::Value::as_f64)
+            };
+            let input = rate("input_cost_per_token")?;
+            let output = rate("output_cost_per_token")?;
+            Some(NewPrice {
+                model: model.to_string(),
+                service_tier: (*tier).to_string(),
+                effective_from,
+                currency: currency.to_string(),
+                prompt_per_1m: input * MILLION,
+                cached_prompt_per_1m: rate("cache_read_input_token_cost")
+                    .map(|value| value * MILLION),
+                completion_per_1m: output * MILLION,
+                long_context: None,
+            })
+        })
+        .collect()
+}
+
+/// Reads the lowest `input_cost_per_token_above_<N>k_tokens` tier and its
+/// matching cached/output rates; a missing output rate keeps the base rate.
+fn long_context_pricing(record: &RemotePricing) -> Option<LongContextPrice> {
+    let (size_k, input) = record
+        .extra
+        .iter()
+        .filter_map(|(key, value)| {
+            let size = key
+                .strip_prefix("input_cost_per_token_above_")?
+                .strip_suffix("k_tokens")?;
+            Some((size.parse::<u64>().ok()?, value.as_f64()?))
+        })
+        .min_by_key(|(size_k, _)| *size_k)?;
+    let tier_rate = |prefix: &str| {
+        record
+            .extra
+            .get(&format!("{prefix}_above_{size_k}k_tokens"))
+            .and_then(serde_json::Value::as_f64)
+    };
+    let output = tier_rate("output_cost_per_token").or(record.output_cost_per_token)?;
+
+    Some(LongContextPrice {
+        threshold_tokens: size_k * 1000,
+        prompt_per_1m: input * MILLION,
+        cached_prompt_per_1m: tier_rate("cache_read_input_token_cost").map(|value| value * MILLION),
+        completion_per_1m: output * MILLION,
     })
 }
 
@@ -213,6 +402,7 @@ mod tests {
                 input_cost_per_token: Some(1.0),
                 output_cost_per_token: Some(2.0),
                 cache_read_input_token_cost: None,
+                extra: HashMap::new(),
             },
         );
         raw.insert(
@@ -221,14 +411,100 @@ mod tests {
                 input_cost_per_token: Some(3.0),
                 output_cost_per_token: Some(4.0),
                 cache_read_input_token_cost: Some(0.5),
+                extra: HashMap::new(),
             },
         );
 
-        let prices = build_prices(raw, "USD");
+        let effective_from = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let prices = build_prices(raw, "USD", effective_from);
         assert_eq!(prices.len(), 1);
         assert_eq!(prices[0].model, "gpt-test");
+        assert_eq!(prices[0].effective_from, effective_from);
         assert!((prices[0].prompt_per_1m - 3_000_000.0).abs() < f64::EPSILON);
         assert!((prices[0].completion_per_1m - 4_000_000.0).abs() < f64::EPSILON);
         assert_eq!(prices[0].cached_prompt_per_1m, Some(500_000.0));
     }
+
+    #[test]
+    fn long_context_tier_uses_lowest_threshold() {
+        let record: RemotePricing = serde_json::from_str(
+            r#"{
+                "input_cost_per_token": 1e-6,
+                "output_cost_per_token": 8e-6,
+                "input_cost_per_token_above_400k_tokens": 4e-6,
+                "input_cost_per_token_above_200k_tokens": 2e-6,
+                "cache_read_input_token_cost_above_200k_tokens": 2e-7,
+                "max_input_tokens": 1000000
+            }"#,
+        )
+        .unwrap();
+
+        let tier = long_context_pricing(&record).unwrap();
+        assert_eq!(tier.threshold_tokens, 200_000);
+        assert!((tier.prompt_per_1m - 2.0).abs() < 1e-9);
+        assert!((tier.cached_prompt_per_1m.unwrap() - 0.2).abs() < 1e-9);
+        assert!((tier.completion_per_1m - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn service_tier_rates_become_separate_prices() {
+        let record: RemotePricing = serde_json::from_str(
+            r#"{
+                "input_cost_per_token": 1.25e-6,
+                "output_cost_per_token": 1e-5,
+                "input_cost_per_token_flex": 6.25e-7,
+                "output_cost_per_token_flex": 5e-6,
+                "cache_read_input_token_cost_flex": 6.25e-8,
+                "input_cost_per_token_priority": 2.5e-6,
+                "input_cost_per_token_batches": 6.25e-7,
+                "output_cost_per_token_batches": 5e-6
+            }"#,
+        )
+        .unwrap();
+        let effective_from = NaiveDate::from_ymd_opt(2025, 11, 20).unwrap();
+
+        let tiers = service_tier_pricing("gpt-5", &record, "USD", effective_from);
+        let names: Vec<&str> = tiers
+            .iter()
+            .map(|price| price.service_tier.as_str())
+            .collect();
+        assert_eq!(names, ["flex", "batch"]);
+        assert!((tiers[0].prompt_per_1m - 0.625).abs() < 1e-9);
+        assert!((tiers[0].cached_prompt_per_1m.unwrap() - 0.0625).abs() < 1e-9);
+        assert!(tiers[1].cached_prompt_per_1m.is_none());
+
+        assert_eq!(
+            split_override_key("gpt-5@Flex"),
+            ("gpt-5".to_string(), "flex".to_string())
+        );
+        assert_eq!(
+            split_override_key("gpt-5"),
+            ("gpt-5".to_string(), DEFAULT_SERVICE_TIER.to_string())
+        );
+    }
+
+    #[test]
+    fn local_source_path_accepts_file_urls_and_paths() {
+        assert_eq!(
+            local_source_path("file:///srv/pricing.json"),
+            Some(PathBuf::from("/srv/pricing.json"))
+        );
+        assert_eq!(
+            local_source_path("pricing/prices.json"),
+            Some(PathBuf::from("pricing/prices.json"))
+        );
+        assert_eq!(local_source_path("https://example.com/prices.json"), None);
+    }
+
+    #[test]
+    fn bundled_snapshot_parses() {
+        let effective_from = NaiveDate::from_ymd_opt(2025, 11, 20).unwrap();
+        let prices = parse_prices(BUNDLED_PRICES.as_bytes(), "USD", effective_from).unwrap();
+        let gpt5 = prices
+            .iter()
+            .find(|price| price.model == "gpt-5" && price.service_tier == DEFAULT_SERVICE_TIER)
+            .unwrap();
+        assert!((gpt5.prompt_per_1m - 1.25).abs() < 1e-9);
+        assert!((gpt5.completion_per_1m - 10.0).abs() < 1e-9);
+    }
 }
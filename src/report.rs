@@ -0,0 +1,901 @@
+use crate::{
+    cli::{ReportArgs, ReportFormat, ReportGroupBy, ReportRange},
+    fx,
+    storage::{AggregateTotals, LineChangeCostRow, LineChangeGroup, Storage},
+    tui::{self, Period, TimeRange},
+};
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::Serialize;
+use std::{collections::HashMap, io::Write};
+
+const DAY_BUCKET_EXPR: &str = "strftime('%Y-%m-%d', timestamp, 'localtime')";
+const TABLE_TITLE_MAX_CHARS: usize = 60;
+const TABLE_SHA_CHARS: usize = 12;
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct ReportRow {
+    key: String,
+    /// Repo of a commit or branch row.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sessions: Option<u64>,
+    /// Commits the branch's spend is attributed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commits: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cached_prompt_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completion_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_tokens: Option<u64>,
+    total_tokens: u64,
+    /// Cost in the display currency.
+    cost: Option<f64>,
+    /// Lines changed by applied patches; only set for model, repo, effort and
+    /// approval groupings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines_added: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines_removed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost_per_100_lines: Option<f64>,
+}
+
+impl ReportRow {
+    fn from_totals(key: String, totals: &AggregateTotals) -> Self {
+        Self {
+            key,
+            prompt_tokens: Some(totals.prompt_tokens),
+            cached_prompt_tokens: Some(totals.cached_prompt_tokens),
+            completion_tokens: Some(totals.completion_tokens),
+            reasoning_tokens: Some(totals.reasoning_tokens),
+            total_tokens: totals.total_tokens,
+            cost: totals.cost,
+            ..Self::default()
+        }
+    }
+
+    fn set_line_changes(&mut self, added: u64, removed: u64) {
+        let lines = added.saturating_add(removed);
+        self.lines_added = Some(added);
+        self.lines_removed = Some(removed);
+        self.cost_per_100_lines = self
+            .cost
+            .filter(|_| lines > 0)
+            .map(|cost| cost * 100.0 / lines as f64);
+    }
+
+    fn has_line_changes(&self) -> bool {
+        self.lines_added.unwrap_or(0) + self.lines_removed.unwrap_or(0) > 0
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    range: &'static str,
+    label: String,
+    start: String,
+    end: String,
+    group_by: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root_label: Option<String>,
+    currency: String,
+    #[serde(skip)]
+    group: ReportGroupBy,
+    totals: ReportRow,
+    rows: Vec<ReportRow>,
+}
+
+pub async fn run(storage: &Storage, args: &ReportArgs, out: &mut impl Write) -> Result<()> {
+    let now_local = Local::now();
+    let period = tui::period_for_range(time_range(args.range), now_local.date_naive(), now_local);
+    let storage = storage.with_root_label(args.label.as_deref());
+    let report = build_report(&storage, args, &period).await?;
+    match args.format {
+        ReportFormat::Table => write_table(out, &report)?,
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &report)
+                .with_context(|| "failed to write JSON report")?;
+            writeln!(out)?;
+        }
+        ReportFormat::Csv => write_csv(out, &report)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+async fn build_report(storage: &Storage, args: &ReportArgs, period: &Period) -> Result<Report> {
+    let limit = args.limit.unwrap_or(usize::MAX);
+    let line_group = match args.group_by {
+        ReportGroupBy::Model => Some(LineChangeGroup::Model),
+        ReportGroupBy::Repo => Some(LineChangeGroup::Repo),
+        ReportGroupBy::Effort => Some(LineChangeGroup::Effort),
+        ReportGroupBy::Approval => Some(LineChangeGroup::Approval),
+        ReportGroupBy::Session
+        | ReportGroupBy::Day
+        | ReportGroupBy::Label
+        | ReportGroupBy::Commit
+        | ReportGroupBy::Branch => None,
+    };
+    let line_rows: Vec<LineChangeCostRow> = match line_group {
+        Some(group) => {
+            storage
+                .line_change_costs_between(period.start, period.end, group, usize::MAX)
+                .await?
+        }
+        None => Vec::new(),
+    };
+    let mut rows: Vec<ReportRow> = match args.group_by {
+        ReportGroupBy::Model => storage
+            .model_usage_by_cost_between(period.start, period.end, limit)
+            .await?
+            .into_iter()
+            .map(|entry| ReportRow {
+                key: entry.model,
+                total_tokens: entry.total_tokens,
+                cost: Some(entry.cost),
+                ..ReportRow::default()
+            })
+            .collect(),
+        ReportGroupBy::Repo => storage
+            .repo_usage_by_cost_between(period.start, period.end, limit)
+            .await?
+            .into_iter()
+            .map(|entry| ReportRow {
+                key: entry.repo,
+                total_tokens: entry.total_tokens,
+                cost: Some(entry.cost),
+                ..ReportRow::default()
+            })
+            .collect(),
+        ReportGroupBy::Effort | ReportGroupBy::Approval => line_rows
+            .iter()
+            .take(limit)
+            .map(|entry| ReportRow {
+                key: entry.key.clone(),
+                total_tokens: entry.total_tokens,
+                cost: entry.cost,
+                ..ReportRow::default()
+            })
+            .collect(),
+        ReportGroupBy::Label => storage
+            .root_usage_by_cost_between(period.start, period.end, limit)
+            .await?
+            .into_iter()
+            .map(|entry| ReportRow {
+                key: entry.label,
+                total_tokens: entry.total_tokens,
+                cost: Some(entry.cost),
+                ..ReportRow::default()
+            })
+            .collect(),
+        ReportGroupBy::Commit => storage
+            .commit_costs_between(period.start, period.end, limit)
+            .await?
+            .into_iter()
+            .map(|entry| ReportRow {
+                key: entry.sha,
+                repo: Some(entry.repo.unwrap_or_default()),
+                title: Some(entry.subject),
+                sessions: Some(entry.sessions),
+                total_tokens: entry.total_tokens,
+                cost: entry.cost,
+                ..ReportRow::default()
+            })
+            .collect(),
+        ReportGroupBy::Branch => storage
+            .branch_costs_between(period.start, period.end, limit)
+            .await?
+            .into_iter()
+            .map(|entry| ReportRow {
+                key: entry.branch,
+                repo: Some(entry.repo.unwrap_or_default()),
+                sessions: Some(entry.sessions),
+                commits: Some(entry.commits),
+                total_tokens: entry.total_tokens,
+                cost: entry.cost,
+                ..ReportRow::default()
+            })
+            .collect(),
+        ReportGroupBy::Session => {
+            let limit = match args.limit {
+                Some(limit) => limit,
+                None => {
+                    storage
+                        .sessions_count_between(period.start, period.end)
+                        .await?
+                }
+            };
+            storage
+                .sessions_page_by_cost_between(period.start, period.end, 0, limit)
+                .await?
+                .into_iter()
+                .map(|session| ReportRow {
+                    key: session.session_id,
+                    title: session.title,
+                    prompt_tokens: Some(session.prompt_tokens),
+                    cached_prompt_tokens: Some(session.cached_prompt_tokens),
+                    completion_tokens: Some(session.completion_tokens),
+                    reasoning_tokens: Some(session.reasoning_tokens),
+                    total_tokens: session.total_tokens,
+                    cost: session.cost,
+                    ..ReportRow::default()
+                })
+                .collect()
+        }
+        ReportGroupBy::Day => {
+            let mut rows: Vec<ReportRow> = storage
+                .aggregates_by_bucket(period.start, period.end, DAY_BUCKET_EXPR)
+                .await?
+                .into_iter()
+                .map(|bucket| {
+                    let mut row = ReportRow::from_totals(bucket.bucket, &bucket.totals);
+                    row.sessions = Some(bucket.session_count);
+                    row
+                })
+                .collect();
+            rows.sort_by(|a, b| a.key.cmp(&b.key));
+            rows.truncate(limit);
+            rows
+        }
+    };
+
+    let totals = storage
+        .totals_between_timestamps(period.start, period.end)
+        .await?;
+    let counts = storage
+        .counts_between_timestamps(period.start, period.end)
+        .await?;
+    let mut totals = ReportRow::from_totals("total".to_string(), &totals);
+    totals.sessions = Some(counts.session_count);
+
+    if line_group.is_some() {
+        let by_key: HashMap<&str, &LineChangeCostRow> = line_rows
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry))
+            .collect();
+        for row in rows.iter_mut() {
+            let (added, removed) = by_key
+                .get(row.key.as_str())
+                .map(|entry| (entry.lines_added, entry.lines_removed))
+                .unwrap_or_default();
+            row.set_line_changes(added, removed);
+        }
+        let added = line_rows.iter().map(|entry| entry.lines_added).sum();
+        let removed = line_rows.iter().map(|entry| entry.lines_removed).sum();
+        totals.set_line_changes(added, removed);
+    }
+
+    let currency = fx::display();
+    Ok(Report {
+        range: range_name(args.range),
+        label: period.label.clone(),
+        start: period.start.to_rfc3339(),
+        end: period.end.to_rfc3339(),
+        group_by: group_by_name(args.group_by),
+        root_label: args.label.clone(),
+        currency: currency.code().to_string(),
+        group: args.group_by,
+        totals,
+        rows,
+    })
+}
+
+fn write_table(out: &mut impl Write, report: &Report) -> Result<()> {
+    let columns = report_columns(report);
+    let mut cells: Vec<Vec<String>> = Vec::with_capacity(report.rows.len() + 2);
+    cells.push(columns.iter().map(|column| column.header()).collect());
+    for row in &report.rows {
+        cells.push(
+            columns
+                .iter()
+                .map(|column| column.table_value(row))
+                .collect(),
+        );
+    }
+    let mut total_row: Vec<String> = columns
+        .iter()
+        .map(|column| column.table_value(&report.totals))
+        .collect();
+    total_row[0] = "TOTAL".to_string();
+    for (idx, column) in columns.iter().enumerate().skip(1) {
+        if *column == Column::Title {
+            total_row[idx].clear();
+        }
+    }
+
+    let mut widths = vec![0usize; columns.len()];
+    for row in cells.iter().chain(std::iter::once(&total_row)) {
+        for (idx, cell) in row.iter().enumerate() {
+            widths[idx] = widths[idx].max(cell.chars().count());
+        }
+    }
+
+    write!(
+        out,
+        "Codex usage for {} ({}) by {}",
+        report.label, report.range, report.group_by
+    )?;
+    if let Some(label) = &report.root_label {
+        write!(out, " in root {label}")?;
+    }
+    writeln!(out)?;
+    writeln!(out)?;
+    for (row_idx, row) in cells.iter().enumerate() {
+        write_table_row(out, &columns, &widths, row)?;
+        if row_idx == 0 {
+            write_separator(out, &widths)?;
+        }
+    }
+    if report.rows.is_empty() {
+        writeln!(out, "(no usage)")?;
+    }
+    write_separator(out, &widths)?;
+    write_table_row(out, &columns, &widths, &total_row)?;
+    Ok(())
+}
+
+fn write_table_row(
+    out: &mut impl Write,
+    columns: &[Column],
+    widths: &[usize],
+    row: &[String],
+) -> Result<()> {
+    let mut line = String::new();
+    for (idx, cell) in row.iter().enumerate() {
+        if idx > 0 {
+            line.push_str("  ");
+        }
+        if columns[idx].is_numeric() {
+            line.push_str(&format!("{:>width$}", cell, width = widths[idx]));
+        } else {
+            line.push_str(&format!("{:<width$}", cell, width = widths[idx]));
+        }
+    }
+    writeln!(out, "{}", line.trim_end())?;
+    Ok(())
+}
+
+fn write_separator(out: &mut impl Write, widths: &[usize]) -> Result<()> {
+    let total = widths.iter().sum::<usize>() + widths.len().saturating_sub(1) * 2;
+    writeln!(out, "{}", "-".repeat(total))?;
+    Ok(())
+}
+
+fn write_csv(out: &mut impl Write, report: &Report) -> Result<()> {
+    let columns = report_columns(report);
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(columns.iter().map(|column| column.csv_header()))?;
+    for row in &report.rows {
+        writer.write_record(columns.iter().map(|column| column.csv_value(row)))?;
+    }
+    writer
+        .flush()
+        .with_context(|| "failed to write CSV report")?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Key(ReportGroupBy),
+    Repo,
+    Title,
+    Sessions,
+    Commits,
+    Prompt,
+    Cached,
+    Completion,
+    Reasoning,
+    Total,
+    Cost,
+    LinesAdded,
+    LinesRemoved,
+    CostPer100Lines,
+}
+
+impl Column {
+    fn header(self) -> String {
+        match self {
+            Column::Key(group_by) => group_by_name(group_by).to_ascii_uppercase(),
+            Column::Repo => "REPO".to_string(),
+            Column::Title => "TITLE".to_string(),
+            Column::Sessions => "SESSIONS".to_string(),
+            Column::Commits => "COMMITS".to_string(),
+            Column::Prompt => "PROMPT".to_string(),
+            Column::Cached => "CACHED".to_string(),
+            Column::Completion => "COMPLETION".to_string(),
+            Column::Reasoning => "REASONING".to_string(),
+            Column::Total => "TOTAL".to_string(),
+            Column::Cost => "COST".to_string(),
+            Column::LinesAdded => "+LINES".to_string(),
+            Column::LinesRemoved => "-LINES".to_string(),
+            Column::CostPer100Lines => "COST/100 LINES".to_string(),
+        }
+    }
+
+    fn csv_header(self) -> String {
+        let name = match self {
+            Column::Key(group_by) => group_by_name(group_by),
+            Column::Repo => "repo",
+            Column::Title => "title",
+            Column::Sessions => "sessions",
+            Column::Commits => "commits",
+            Column::Prompt => "prompt_tokens",
+            Column::Cached => "cached_prompt_tokens",
+            Column::Completion => "completion_tokens",
+            Column::Reasoning => "reasoning_tokens",
+            Column::Total => "total_tokens",
+            Column::LinesAdded => "lines_added",
+            Column::LinesRemoved => "lines_removed",
+            Column::Cost => {
+                return format!("cost_{}", fx::display().code().to_ascii_lowercase());
+            }
+            Column::CostPer100Lines => {
+                return format!(
+                    "cost_per_100_lines_{}",
+                    fx::display().code().to_ascii_lowercase()
+                );
+            }
+        };
+        name.to_string()
+    }
+
+    fn is_numeric(self) -> bool {
+        !matches!(self, Column::Key(_) | Column::Repo | Column::Title)
+    }
+
+    fn count(self, row: &ReportRow) -> Option<u64> {
+        match self {
+            Column::Sessions => row.sessions,
+            Column::Commits => row.commits,
+            Column::Prompt => row.prompt_tokens,
+            Column::Cached => row.cached_prompt_tokens,
+            Column::Completion => row.completion_tokens,
+            Column::Reasoning => row.reasoning_tokens,
+            Column::Total => Some(row.total_tokens),
+            Column::LinesAdded => row.lines_added,
+            Column::LinesRemoved => row.lines_removed,
+            _ => None,
+        }
+    }
+
+    fn table_value(self, row: &ReportRow) -> String {
+        match self {
+            Column::Key(ReportGroupBy::Commit) => row.key.chars().take(TABLE_SHA_CHARS).collect(),
+            Column::Key(_) => row.key.clone(),
+            Column::Repo => row.repo.clone().unwrap_or_default(),
+            Column::Title => {
+                let title = row.title.as_deref().unwrap_or_default();
+                if title.chars().count() > TABLE_TITLE_MAX_CHARS {
+                    let truncated: String = title.chars().take(TABLE_TITLE_MAX_CHARS - 1).collect();
+                    format!("{truncated}…")
+                } else {
+                    title.to_string()
+                }
+            }
+            Column::Cost => tui::format_cost(row.cost),
+            Column::CostPer100Lines => row
+                .cost_per_100_lines
+                .map(|cost| tui::format_cost(Some(cost)))
+                .unwrap_or_default(),
+            _ => self.count(row).map(|v| v.to_string()).unwrap_or_default(),
+        }
+    }
+
+    fn csv_value(self, row: &ReportRow) -> String {
+        match self {
+            Column::Key(_) => row.key.clone(),
+            Column::Title => row.title.clone().unwrap_or_default(),
+            Column::Cost => row.cost.map(|v| format!("{v:.6}")).unwrap_or_default(),
+            Column::CostPer100Lines => row
+                .cost_per_100_lines
+                .map(|v| format!("{v:.6}"))
+                .unwrap_or_default(),
+            _ => self.table_value(row),
+        }
+    }
+}
+
+fn report_columns(report: &Report) -> Vec<Column> {
+    let mut columns = vec![Column::Key(report.group)];
+    let has_breakdown = report.rows.iter().any(|row| row.prompt_tokens.is_some());
+    if report.rows.iter().any(|row| row.repo.is_some()) {
+        columns.push(Column::Repo);
+    }
+    if report.rows.iter().any(|row| row.title.is_some()) {
+        columns.push(Column::Title);
+    }
+    if report.rows.iter().any(|row| row.sessions.is_some()) {
+        columns.push(Column::Sessions);
+    }
+    if report.rows.iter().any(|row| row.commits.is_some()) {
+        columns.push(Column::Commits);
+    }
+    if has_breakdown {
+        columns.extend([
+            Column::Prompt,
+            Column::Cached,
+            Column::Completion,
+            Column::Reasoning,
+        ]);
+    }
+    columns.extend([Column::Total, Column::Cost]);
+    if report.rows.iter().any(ReportRow::has_line_changes) {
+        columns.extend([
+            Column::LinesAdded,
+            Column::LinesRemoved,
+            Column::CostPer100Lines,
+        ]);
+    }
+    columns
+}
+
+fn time_range(range: ReportRange) -> TimeRange {
+    match range {
+        ReportRange::Day => TimeRange::Day,
+        ReportRange::Week => TimeRange::Week,
+        ReportRange::Month => TimeRange::Month,
+        ReportRange::Year => TimeRange::Year,
+        ReportRange::All => TimeRange::All,
+    }
+}
+
+fn range_name(range: ReportRange) -> &'static str {
+    match range {
+        ReportRange::Day => "day",
+        ReportRange::Week => "week",
+        ReportRange::Month => "month",
+        ReportRange::Year => "year",
+        ReportRange::All => "all",
+    }
+}
+
+fn group_by_name(group_by: ReportGroupBy) -> &'static str {
+    match group_by {
+        ReportGroupBy::Model => "model",
+        ReportGroupBy::Repo => "repo",
+        ReportGroupBy::Session => "session",
+        ReportGroupBy::Day => "day",
+        ReportGroupBy::Label => "label",
+        ReportGroupBy::Effort => "effort",
+        ReportGroupBy::Approval => "approval",
+        ReportGroupBy::Commit => "commit",
+        ReportGroupBy::Branch => "branch",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{
+        DEFAULT_SERVICE_TIER, FileLineChange, GitScanTarget, NewPrice, NewTurn, SessionCommit,
+        TurnPolicy,
+    };
+    use chrono::{Duration, NaiveDate, Utc};
+    use std::path::PathBuf;
+    use tempfile::NamedTempFile;
+
+    async fn seeded_storage(db_file: &NamedTempFile) -> Storage {
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+        let prices = ["gpt-a", "gpt-b"].map(|model| NewPrice {
+            model: model.to_string(),
+            service_tier: DEFAULT_SERVICE_TIER.to_string(),
+            effective_from: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            currency: "USD".to_string(),
+            prompt_per_1m: 1.0,
+            cached_prompt_per_1m: None,
+            completion_per_1m: 2.0,
+            long_context: None,
+        });
+        storage
+            .sync_prices(&prices, "test", Utc::now())
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        for (session_id, model, prompt) in [
+            ("sess-1", "gpt-a", 1_000_000),
+            ("sess-2", "gpt-b", 2_000_000),
+            ("sess-3", "gpt-b", 1_000_000),
+        ] {
+            let mut tx = storage.begin_tx().await.unwrap();
+            storage
+                .record_turn_tx(
+                    &mut tx,
+                    &NewTurn {
+                        session_id,
+                        timestamp: now,
+                        model,
+                        prompt_tokens: prompt,
+                        total_tokens: prompt,
+                        ..NewTurn::default()
+                    },
+                )
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+        }
+        storage
+    }
+
+    fn args(group_by: ReportGroupBy, format: ReportFormat) -> ReportArgs {
+        ReportArgs {
+            range: ReportRange::Day,
+            group_by,
+            format,
+            limit: None,
+            label: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn csv_report_groups_by_model_in_cost_order() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = seeded_storage(&db_file).await;
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Model, ReportFormat::Csv),
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "model,total_tokens,cost_usd",
+                "gpt-b,3000000,3.000000",
+                "gpt-a,1000000,1.000000",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn json_report_totals_match_rows() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = seeded_storage(&db_file).await;
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Session, ReportFormat::Json),
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let rows = value["rows"].as_array().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["key"], "sess-2");
+        assert_eq!(value["totals"]["sessions"], 3);
+        let total_cost = value["totals"]["cost"].as_f64().unwrap();
+        assert!((total_cost - 4.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn label_filter_and_grouping_use_session_roots() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = seeded_storage(&db_file).await;
+        storage
+            .apply_session_root_labels(&[
+                ("sess-1".to_string(), "work".to_string()),
+                ("sess-2".to_string(), "home".to_string()),
+                ("sess-3".to_string(), "work".to_string()),
+            ])
+            .await
+            .unwrap();
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Label, ReportFormat::Csv),
+            &mut out,
+        )
+        .await
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec![
+                "label,total_tokens,cost_usd",
+                "home,2000000,2.000000",
+                "work,2000000,2.000000",
+            ]
+        );
+
+        let mut filtered = args(ReportGroupBy::Model, ReportFormat::Json);
+        filtered.label = Some("work".to_string());
+        let mut out = Vec::new();
+        run(&storage, &filtered, &mut out).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["root_label"], "work");
+        assert_eq!(value["totals"]["sessions"], 2);
+        let total_cost = value["totals"]["cost"].as_f64().unwrap();
+        assert!((total_cost - 2.0).abs() < 1e-9);
+        let mut models: Vec<&str> = value["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["key"].as_str().unwrap())
+            .collect();
+        models.sort_unstable();
+        assert_eq!(models, ["gpt-a", "gpt-b"]);
+    }
+
+    #[tokio::test]
+    async fn effort_report_shows_cost_per_100_lines_changed() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = seeded_storage(&db_file).await;
+        let now = Utc::now();
+        let mut tx = storage.begin_tx().await.unwrap();
+        storage
+            .record_turn_tx(
+                &mut tx,
+                &NewTurn {
+                    session_id: "sess-4",
+                    timestamp: now,
+                    model: "gpt-a",
+                    reasoning_effort: Some("high"),
+                    prompt_tokens: 1_000_000,
+                    total_tokens: 1_000_000,
+                    ..NewTurn::default()
+                },
+            )
+            .await
+            .unwrap();
+        let files = [FileLineChange {
+            path: "src/lib.rs".to_string(),
+            added: 150,
+            removed: 50,
+        }];
+        storage
+            .record_patch_files_tx(&mut tx, "sess-4", now, Some("p1"), &files)
+            .await
+            .unwrap();
+        storage
+            .attribute_patches_to_turn_tx(&mut tx, "sess-4", now, "gpt-a")
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Effort, ReportFormat::Csv),
+            &mut out,
+        )
+        .await
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec![
+                "effort,total_tokens,cost_usd,lines_added,lines_removed,cost_per_100_lines_usd",
+                "default,4000000,4.000000,0,0,",
+                "high,1000000,1.000000,150,50,0.500000",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn approval_report_breaks_down_spend_by_policy() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = seeded_storage(&db_file).await;
+        let now = Utc::now();
+        let mut tx = storage.begin_tx().await.unwrap();
+        for (session_id, approval_policy, sandbox_policy) in [
+            ("sess-auto", "on-request", "workspace-write"),
+            ("sess-yolo", "never", "danger-full-access"),
+        ] {
+            let policy = TurnPolicy {
+                approval_policy: Some(approval_policy),
+                sandbox_policy: Some(sandbox_policy),
+                cwd: Some("/work/app"),
+            };
+            storage
+                .record_turn_tx(
+                    &mut tx,
+                    &NewTurn {
+                        session_id,
+                        timestamp: now,
+                        model: "gpt-a",
+                        policy,
+                        prompt_tokens: 1_000_000,
+                        total_tokens: 1_000_000,
+                        ..NewTurn::default()
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        tx.commit().await.unwrap();
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Approval, ReportFormat::Csv),
+            &mut out,
+        )
+        .await
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec![
+                "approval,total_tokens,cost_usd",
+                "unknown,4000000,4.000000",
+                "never / danger-full-access,1000000,1.000000",
+                "on-request / workspace-write,1000000,1.000000",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn commit_and_branch_reports_attribute_turns_to_the_next_commit() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = seeded_storage(&db_file).await;
+        let now = Utc::now();
+        let target = GitScanTarget {
+            session_id: "sess-2".to_string(),
+            cwd: PathBuf::from("/work/app"),
+            started_at: now - Duration::minutes(5),
+            last_event_at: now + Duration::minutes(5),
+        };
+        let commit = SessionCommit {
+            sha: "0123456789abcdef0123".to_string(),
+            authored_at: now + Duration::seconds(1),
+            branch: Some("feat".to_string()),
+            subject: "Add feature".to_string(),
+        };
+        storage
+            .record_git_scan(&target, now, "ok", None, Some(&[commit]))
+            .await
+            .unwrap();
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Commit, ReportFormat::Csv),
+            &mut out,
+        )
+        .await
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec![
+                "commit,repo,title,sessions,total_tokens,cost_usd",
+                "0123456789abcdef0123,,Add feature,1,2000000,2.000000",
+            ]
+        );
+
+        let mut out = Vec::new();
+        run(
+            &storage,
+            &args(ReportGroupBy::Branch, ReportFormat::Csv),
+            &mut out,
+        )
+        .await
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec![
+                "branch,repo,sessions,commits,total_tokens,cost_usd",
+                "feat,,1,1,2000000,2.000000",
+            ]
+        );
+    }
+}
@@ -1,9 +1,12 @@
-use crate::tokens::blended_total;
+use crate::{fx::DisplayCurrency, tokens::blended_total};
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, LocalResult, NaiveDate, Utc, Duration as ChronoDuration, TimeZone};
+use chrono::{DateTime, Duration as ChronoDuration, Local, LocalResult, NaiveDate, TimeZone, Utc};
 use sqlx::{
-    Row, SqlitePool,
-    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+    Row, Sqlite, SqlitePool,
+    query::Query,
+    sqlite::{
+        SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow,
+    },
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -21,6 +24,15 @@ SELECT
     p.completion_per_1m,
     CASE
         WHEN p.prompt_per_1m IS NULL OR p.completion_per_1m IS NULL THEN NULL
+        WHEN p.long_context_threshold IS NOT NULL
+             AND p.long_prompt_per_1m IS NOT NULL
+             AND t.prompt_tokens > p.long_context_threshold
+        THEN (
+            (t.prompt_tokens - MIN(t.cached_prompt_tokens, t.prompt_tokens)) * p.long_prompt_per_1m
+            + MIN(t.cached_prompt_tokens, t.prompt_tokens)
+                * COALESCE(p.long_cached_prompt_per_1m, p.cached_prompt_per_1m, p.long_prompt_per_1m)
+            + t.completion_tokens * COALESCE(p.long_completion_per_1m, p.completion_per_1m)
+        ) / 1000000.0
         ELSE (
             (t.prompt_tokens - CASE
                 WHEN t.cached_prompt_tokens > t.prompt_tokens THEN t.prompt_tokens
@@ -41,7 +53,15 @@ SELECT
     END AS missing_price
 FROM session_turns t
 LEFT JOIN model_prices p
-  ON p.model = t.model;
+  ON p.model = t.model
+ AND p.service_tier = t.service_tier
+ AND p.effective_from = (
+     SELECT MAX(p2.effective_from)
+     FROM model_prices p2
+     WHERE p2.model = t.model
+       AND p2.service_tier = t.service_tier
+       AND p2.effective_from <= DATE(t.timestamp, 'localtime')
+ );
 "#;
 
 const SESSION_DAILY_COSTS_VIEW_SQL: &str = r#"
@@ -54,15 +74,19 @@ SELECT
     CASE
         WHEN p.prompt_per_1m IS NULL OR p.completion_per_1m IS NULL THEN NULL
         ELSE (
-            (d.prompt_tokens - CASE
-                WHEN d.cached_prompt_tokens > d.prompt_tokens THEN d.prompt_tokens
-                ELSE d.cached_prompt_tokens
-            END) * p.prompt_per_1m
-            + (CASE
-                WHEN d.cached_prompt_tokens > d.prompt_tokens THEN d.prompt_tokens
-                ELSE d.cached_prompt_tokens
-            END) * COALESCE(p.cached_prompt_per_1m, p.prompt_per_1m)
-            + d.completion_tokens * p.completion_per_1m
+            ((d.prompt_tokens - d.long_prompt_tokens)
+                - MIN(d.cached_prompt_tokens - d.long_cached_prompt_tokens,
+                      d.prompt_tokens - d.long_prompt_tokens)) * p.prompt_per_1m
+            + MIN(d.cached_prompt_tokens - d.long_cached_prompt_tokens,
+                  d.prompt_tokens - d.long_prompt_tokens)
+                * COALESCE(p.cached_prompt_per_1m, p.prompt_per_1m)
+            + (d.completion_tokens - d.long_completion_tokens) * p.completion_per_1m
+            + (d.long_prompt_tokens - MIN(d.long_cached_prompt_tokens, d.long_prompt_tokens))
+                * COALESCE(p.long_prompt_per_1m, p.prompt_per_1m)
+            + MIN(d.long_cached_prompt_tokens, d.long_prompt_tokens)
+                * COALESCE(p.long_cached_prompt_per_1m, p.cached_prompt_per_1m,
+                           p.long_prompt_per_1m, p.prompt_per_1m)
+            + d.long_completion_tokens * COALESCE(p.long_completion_per_1m, p.completion_per_1m)
         ) / 1000000.0
     END AS cost_usd,
     CASE
@@ -73,7 +97,15 @@ SELECT
     END AS missing_price
 FROM session_daily_stats d
 LEFT JOIN model_prices p
-  ON p.model = d.model;
+  ON p.model = d.model
+ AND p.service_tier = d.service_tier
+ AND p.effective_from = (
+     SELECT MAX(p2.effective_from)
+     FROM model_prices p2
+     WHERE p2.model = d.model
+       AND p2.service_tier = d.service_tier
+       AND p2.effective_from <= d.date
+ );
 "#;
 
 const DAILY_STATS_COSTS_VIEW_SQL: &str = r#"
@@ -86,15 +118,19 @@ SELECT
     CASE
         WHEN p.prompt_per_1m IS NULL OR p.completion_per_1m IS NULL THEN NULL
         ELSE (
-            (d.prompt_tokens - CASE
-                WHEN d.cached_prompt_tokens > d.prompt_tokens THEN d.prompt_tokens
-                ELSE d.cached_prompt_tokens
-            END) * p.prompt_per_1m
-            + (CASE
-                WHEN d.cached_prompt_tokens > d.prompt_tokens THEN d.prompt_tokens
-                ELSE d.cached_prompt_tokens
-            END) * COALESCE(p.cached_prompt_per_1m, p.prompt_per_1m)
-            + d.completion_tokens * p.completion_per_1m
+            ((d.prompt_tokens - d.long_prompt_tokens)
+                - MIN(d.cached_prompt_tokens - d.long_cached_prompt_tokens,
+                      d.prompt_tokens - d.long_prompt_tokens)) * p.prompt_per_1m
+            + MIN(d.cached_prompt_tokens - d.long_cached_prompt_tokens,
+                  d.prompt_tokens - d.long_prompt_tokens)
+                * COALESCE(p.cached_prompt_per_1m, p.prompt_per_1m)
+            + (d.completion_tokens - d.long_completion_tokens) * p.completion_per_1m
+            + (d.long_prompt_tokens - MIN(d.long_cached_prompt_tokens, d.long_prompt_tokens))
+                * COALESCE(p.long_prompt_per_1m, p.prompt_per_1m)
+            + MIN(d.long_cached_prompt_tokens, d.long_prompt_tokens)
+                * COALESCE(p.long_cached_prompt_per_1m, p.cached_prompt_per_1m,
+                           p.long_prompt_per_1m, p.prompt_per_1m)
+            + d.long_completion_tokens * COALESCE(p.long_completion_per_1m, p.completion_per_1m)
         ) / 1000000.0
     END AS cost_usd,
     CASE
@@ -105,14 +141,150 @@ SELECT
     END AS missing_price
 FROM daily_stats d
 LEFT JOIN model_prices p
-  ON p.model = d.model;
+  ON p.model = d.model
+ AND p.service_tier = d.service_tier
+ AND p.effective_from = (
+     SELECT MAX(p2.effective_from)
+     FROM model_prices p2
+     WHERE p2.model = d.model
+       AND p2.service_tier = d.service_tier
+       AND p2.effective_from <= d.date
+ );
+"#;
+
+/// Each session with the root of its subagent tree and its depth below it.
+/// Links to sessions that were never ingested are ignored, and the walk stops
+/// after a fixed depth so a cyclic link cannot recurse forever.
+const SESSION_TREE_VIEW_SQL: &str = r#"
+CREATE VIEW IF NOT EXISTS session_tree AS
+WITH RECURSIVE ancestry(session_id, ancestor_id, depth) AS (
+    SELECT session_id, session_id, 0 FROM sessions
+    UNION ALL
+    SELECT a.session_id, l.parent_session_id, a.depth + 1
+    FROM ancestry a
+    JOIN session_links l ON l.session_id = a.ancestor_id
+    JOIN sessions p ON p.session_id = l.parent_session_id
+    WHERE a.depth < 16
+)
+SELECT session_id, ancestor_id AS root_session_id, MAX(depth) AS depth
+FROM ancestry
+GROUP BY session_id;
+"#;
+
+/// Tokens from turns whose prompt exceeded the long-context threshold of the
+/// price in force, kept alongside the daily totals so tiered rates can be
+/// applied without re-reading every turn.
+const LONG_CONTEXT_TOKEN_COLUMNS: [&str; 3] = [
+    "long_prompt_tokens",
+    "long_cached_prompt_tokens",
+    "long_completion_tokens",
+];
+
+const PRICES_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS prices (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    model TEXT NOT NULL,
+    service_tier TEXT NOT NULL DEFAULT 'default',
+    effective_from TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    prompt_per_1m REAL NOT NULL,
+    cached_prompt_per_1m REAL,
+    completion_per_1m REAL NOT NULL,
+    long_context_threshold INTEGER,
+    long_prompt_per_1m REAL,
+    long_cached_prompt_per_1m REAL,
+    long_completion_per_1m REAL,
+    UNIQUE(model, service_tier, effective_from)
+);
+"#;
+
+/// Service tier recorded for turns that do not name one and used for prices
+/// that apply to standard processing.
+pub const DEFAULT_SERVICE_TIER: &str = "default";
+
+/// Per-day, per-session sums of the turns priced at the long-context tier.
+const LONG_CONTEXT_TURNS_SQL: &str = r#"
+SELECT
+    DATE(t.timestamp, 'localtime') AS date,
+    t.session_id,
+    t.model,
+    t.service_tier,
+    SUM(t.prompt_tokens) AS prompt_tokens,
+    SUM(t.cached_prompt_tokens) AS cached_prompt_tokens,
+    SUM(t.completion_tokens) AS completion_tokens
+FROM session_turns t
+JOIN model_prices p
+  ON p.model = t.model
+ AND p.service_tier = t.service_tier
+ AND p.effective_from = (
+     SELECT MAX(p2.effective_from)
+     FROM model_prices p2
+     WHERE p2.model = t.model
+       AND p2.service_tier = t.service_tier
+       AND p2.effective_from <= DATE(t.timestamp, 'localtime')
+ )
+WHERE p.long_context_threshold IS NOT NULL
+  AND t.prompt_tokens > p.long_context_threshold
+GROUP BY date, t.session_id, t.model, t.service_tier
 "#;
 
+/// Long-context tier columns shared by `prices`, `price_overrides` and
+/// `model_prices`.
+const LONG_CONTEXT_PRICE_COLUMNS: [(&str, &str); 4] = [
+    ("long_context_threshold", "INTEGER"),
+    ("long_prompt_per_1m", "REAL"),
+    ("long_cached_prompt_per_1m", "REAL"),
+    ("long_completion_per_1m", "REAL"),
+];
+
+/// Outcome of a tool call, filled in from its output events by `call_id`.
+const TOOL_OUTCOME_COLUMNS: [(&str, &str); 6] = [
+    ("call_id", "TEXT"),
+    ("detail", "TEXT"),
+    ("exit_code", "INTEGER"),
+    ("success", "INTEGER"),
+    ("duration_ms", "INTEGER"),
+    ("output_bytes", "INTEGER"),
+];
+
+/// Effective date given to the first known price of a model, so usage recorded
+/// before the first sync is still priced.
+const PRICE_BASELINE_DATE: &str = "1970-01-01";
+
+/// Column of the first commit of a turn's session authored at or after the
+/// turn; `{column}` is replaced before use.
+const NEXT_COMMIT_SQL: &str = r#"
+SELECT c.{column}
+FROM session_commits c
+WHERE c.session_id = t.session_id AND c.authored_at >= t.timestamp
+ORDER BY c.authored_at ASC, c.sha ASC
+LIMIT 1
+"#;
+
+/// Longest line excerpt kept per ingest error.
+const INGEST_ERROR_SAMPLE_CHARS: usize = 240;
+
+/// Columns read into an [`IngestStateRow`] by `ingest_state_from_row`.
+const INGEST_STATE_COLUMNS: &str = "path, session_id, last_offset, \
+    last_seen_input_tokens, last_seen_cached_input_tokens, \
+    last_seen_output_tokens, last_seen_reasoning_output_tokens, \
+    last_seen_total_tokens, last_committed_input_tokens, \
+    last_committed_cached_input_tokens, last_committed_output_tokens, \
+    last_committed_reasoning_output_tokens, last_committed_total_tokens, \
+    current_message_id, current_message_seq, \
+    current_model, current_effort, current_service_tier, \
+    current_approval_policy, current_sandbox_policy, current_cwd, completed, \
+    device, inode, head_fingerprint";
+
 #[derive(Clone)]
 pub struct Storage {
     pool: Arc<SqlitePool>,
     #[allow(dead_code)]
     path: PathBuf,
+    /// Session root label range queries are limited to; `None` covers all roots.
+    root_label: Option<Arc<str>>,
+    /// Currency costs are returned in; the cost views themselves stay in USD.
+    currency: Arc<DisplayCurrency>,
 }
 
 impl Storage {
@@ -132,9 +304,94 @@ impl Storage {
         Ok(Self {
             pool: Arc::new(pool),
             path: path_buf,
+            root_label: None,
+            currency: Arc::new(DisplayCurrency::usd()),
         })
     }
 
+    /// Returns this handle with costs converted to `currency`, each at the
+    /// rate in force on the turn's or day's date.
+    pub fn with_display_currency(mut self, currency: DisplayCurrency) -> Self {
+        self.currency = Arc::new(currency);
+        self
+    }
+
+    /// Returns a handle sharing this pool whose range queries only count
+    /// sessions stored under `label`.
+    pub fn with_root_label(&self, label: Option<&str>) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            path: self.path.clone(),
+            root_label: label.map(Arc::from),
+            currency: self.currency.clone(),
+        }
+    }
+
+    pub fn root_label(&self) -> Option<&str> {
+        self.root_label.as_deref()
+    }
+
+    /// Per-day aggregates have no session column, so scoped queries read the
+    /// per-session table instead.
+    fn daily_source(&self, all_roots: &'static str, per_session: &'static str) -> &'static str {
+        if self.root_label.is_some() {
+            per_session
+        } else {
+            all_roots
+        }
+    }
+
+    /// Condition limiting `column` to sessions under the scoped label, using
+    /// `param` as the placeholder; empty when unscoped.
+    fn root_scope(&self, column: &str, param: &str) -> String {
+        if self.root_label.is_some() {
+            format!("AND {column} IN (SELECT session_id FROM sessions WHERE root_label = {param})")
+        } else {
+            String::new()
+        }
+    }
+
+    /// `session_turn_costs` with a `cost` column in the display currency,
+    /// converted by each turn's local date.
+    fn turn_costs(&self) -> String {
+        self.display_costs("session_turn_costs", "DATE(timestamp, 'localtime')")
+    }
+
+    /// `view`, one of the per-day cost views, with a `cost` column in the
+    /// display currency.
+    fn daily_costs(&self, view: &str) -> String {
+        self.display_costs(view, "date")
+    }
+
+    /// Subquery over `view` adding `cost`: `cost_usd` times the rate in
+    /// force on `date`. The rates are inlined, so the stored views stay in
+    /// USD whatever currency each process shows.
+    fn display_costs(&self, view: &str, date: &str) -> String {
+        let (base_rate, dated_rates) = self.currency.rates();
+        let rate = if dated_rates.is_empty() {
+            format!("{base_rate:?}")
+        } else {
+            let mut rate = String::from("CASE");
+            for (effective_from, value) in dated_rates.iter().rev() {
+                rate.push_str(&format!(
+                    " WHEN {date} >= '{effective_from}' THEN {value:?}"
+                ));
+            }
+            format!("{rate} ELSE {base_rate:?} END")
+        };
+        format!("(SELECT *, cost_usd * {rate} AS cost FROM {view})")
+    }
+
+    fn bind_root_scope<'q>(
+        &'q self,
+        query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        match self.root_label.as_deref() {
+            Some(label) => query.bind(label),
+            None => query,
+        }
+    }
+
     pub async fn begin_tx(&self) -> Result<sqlx::Transaction<'_, sqlx::Sqlite>> {
         self.pool
             .begin()
@@ -155,6 +412,26 @@ impl Storage {
             .execute(&mut *tx)
             .await
             .with_context(|| "failed to clear session_tool_calls")?;
+        sqlx::query("DELETE FROM session_events;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear session_events")?;
+        sqlx::query("DELETE FROM session_patch_files;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear session_patch_files")?;
+        sqlx::query("DELETE FROM session_commits;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear session_commits")?;
+        sqlx::query("DELETE FROM session_git_scans;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear session_git_scans")?;
+        sqlx::query("DELETE FROM session_links;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear session_links")?;
         sqlx::query("DELETE FROM session_turns;")
             .execute(&mut *tx)
             .await
@@ -179,8 +456,24 @@ impl Storage {
             .execute(&mut *tx)
             .await
             .with_context(|| "failed to clear ingest_state")?;
+        sqlx::query("DELETE FROM ingest_errors;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear ingest_errors")?;
+        sqlx::query("DELETE FROM usage_mismatches;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear usage_mismatches")?;
+        sqlx::query("DELETE FROM ingest_backfill;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear ingest_backfill")?;
+        sqlx::query("DELETE FROM rate_limit_snapshots;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear rate_limit_snapshots")?;
         let _ = sqlx::query(
-            "DELETE FROM sqlite_sequence WHERE name IN ('session_turns','session_tool_calls','session_messages');",
+            "DELETE FROM sqlite_sequence WHERE name IN ('session_turns','session_tool_calls','session_events','session_messages','rate_limit_snapshots');",
         )
         .execute(&mut *tx)
         .await;
@@ -268,7 +561,8 @@ impl Storage {
                 completion_tokens INTEGER NOT NULL DEFAULT 0,
                 reasoning_tokens INTEGER NOT NULL DEFAULT 0,
                 total_tokens INTEGER NOT NULL DEFAULT 0,
-                user_messages INTEGER NOT NULL DEFAULT 0
+                user_messages INTEGER NOT NULL DEFAULT 0,
+                root_label TEXT
             );
             "#,
         )
@@ -283,6 +577,12 @@ impl Storage {
                 .await
                 .with_context(|| "failed to add sessions.subagent column")?;
         }
+        if !self.table_has_column("sessions", "root_label").await? {
+            sqlx::query("ALTER TABLE sessions ADD COLUMN root_label TEXT;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to add sessions.root_label column")?;
+        }
         let has_user_messages = self
             .table_has_column("sessions", "user_messages")
             .await
@@ -306,6 +606,10 @@ impl Storage {
                 note TEXT,
                 context_window INTEGER,
                 reasoning_effort TEXT,
+                service_tier TEXT NOT NULL DEFAULT 'default',
+                approval_policy TEXT,
+                sandbox_policy TEXT,
+                cwd TEXT,
                 message_id INTEGER,
                 prompt_tokens INTEGER NOT NULL,
                 cached_prompt_tokens INTEGER NOT NULL,
@@ -349,6 +653,64 @@ impl Storage {
                 .await
                 .with_context(|| "failed to add session_turns.message_id column")?;
         }
+        let has_service_tier = self
+            .table_has_column("session_turns", "service_tier")
+            .await?;
+        if !has_service_tier {
+            sqlx::query(
+                "ALTER TABLE session_turns ADD COLUMN service_tier TEXT NOT NULL DEFAULT 'default';",
+            )
+            .execute(&*self.pool)
+            .await
+            .with_context(|| "failed to add session_turns.service_tier column")?;
+        }
+        for column in ["approval_policy", "sandbox_policy", "cwd"] {
+            if !self.table_has_column("session_turns", column).await? {
+                sqlx::query(&format!(
+                    "ALTER TABLE session_turns ADD COLUMN {column} TEXT;"
+                ))
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("failed to add session_turns.{column} column"))?;
+            }
+        }
+        for column in ["files_changed", "lines_added", "lines_removed"] {
+            if !self.table_has_column("session_turns", column).await? {
+                sqlx::query(&format!(
+                    "ALTER TABLE session_turns ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0;"
+                ))
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("failed to add session_turns.{column} column"))?;
+            }
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_patch_files (
+                session_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                path TEXT NOT NULL,
+                call_id TEXT,
+                lines_added INTEGER NOT NULL,
+                lines_removed INTEGER NOT NULL,
+                turn_timestamp TEXT,
+                turn_model TEXT,
+                PRIMARY KEY (session_id, timestamp, path),
+                FOREIGN KEY (session_id) REFERENCES sessions(session_id)
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_patch_files schema")?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_session_patch_files_call ON session_patch_files(session_id, call_id);",
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_patch_files call index")?;
 
         sqlx::query(
             r#"
@@ -367,18 +729,41 @@ impl Storage {
         .await
         .with_context(|| "failed to ensure session_messages schema")?;
 
+        // Daily tables are rebuilt from session_turns, so a key change just drops
+        // them and lets ensure_daily_stats_basis repopulate them.
+        let daily_has_service_tier = self.table_has_column("daily_stats", "service_tier").await?
+            && self
+                .table_has_column("session_daily_stats", "service_tier")
+                .await?;
+        if !daily_has_service_tier {
+            for table in ["session_daily_stats", "daily_stats"] {
+                sqlx::query(&format!("DROP TABLE IF EXISTS {table};"))
+                    .execute(&*self.pool)
+                    .await
+                    .with_context(|| format!("failed to drop {table} for service tier rebuild"))?;
+            }
+            sqlx::query("DROP TABLE IF EXISTS stats_meta;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to reset stats_meta")?;
+        }
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS session_daily_stats (
                 date TEXT NOT NULL,
                 session_id TEXT NOT NULL,
                 model TEXT NOT NULL,
+                service_tier TEXT NOT NULL DEFAULT 'default',
                 prompt_tokens INTEGER NOT NULL DEFAULT 0,
                 cached_prompt_tokens INTEGER NOT NULL DEFAULT 0,
                 completion_tokens INTEGER NOT NULL DEFAULT 0,
                 reasoning_tokens INTEGER NOT NULL DEFAULT 0,
                 total_tokens INTEGER NOT NULL DEFAULT 0,
-                PRIMARY KEY (date, session_id, model)
+                long_prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                long_cached_prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                long_completion_tokens INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (date, session_id, model, service_tier)
             );
             "#,
         )
@@ -391,12 +776,16 @@ impl Storage {
             CREATE TABLE IF NOT EXISTS daily_stats (
                 date TEXT NOT NULL,
                 model TEXT NOT NULL,
+                service_tier TEXT NOT NULL DEFAULT 'default',
                 prompt_tokens INTEGER NOT NULL DEFAULT 0,
                 cached_prompt_tokens INTEGER NOT NULL DEFAULT 0,
                 completion_tokens INTEGER NOT NULL DEFAULT 0,
                 reasoning_tokens INTEGER NOT NULL DEFAULT 0,
                 total_tokens INTEGER NOT NULL DEFAULT 0,
-                PRIMARY KEY (date, model)
+                long_prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                long_cached_prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                long_completion_tokens INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (date, model, service_tier)
             );
             "#,
         )
@@ -404,6 +793,19 @@ impl Storage {
         .await
         .with_context(|| "failed to ensure daily_stats schema")?;
 
+        for table in ["session_daily_stats", "daily_stats"] {
+            for column in LONG_CONTEXT_TOKEN_COLUMNS {
+                if !self.table_has_column(table, column).await? {
+                    sqlx::query(&format!(
+                        "ALTER TABLE {table} ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0;"
+                    ))
+                    .execute(&*self.pool)
+                    .await
+                    .with_context(|| format!("failed to add {table}.{column} column"))?;
+                }
+            }
+        }
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS stats_meta (
@@ -435,7 +837,15 @@ impl Storage {
                 current_message_id INTEGER,
                 current_message_seq INTEGER NOT NULL DEFAULT 0,
                 current_model TEXT,
-                current_effort TEXT
+                current_effort TEXT,
+                current_service_tier TEXT,
+                current_approval_policy TEXT,
+                current_sandbox_policy TEXT,
+                current_cwd TEXT,
+                completed INTEGER NOT NULL DEFAULT 0,
+                device INTEGER,
+                inode INTEGER,
+                head_fingerprint TEXT
             );
             "#,
         )
@@ -455,6 +865,59 @@ impl Storage {
         .await
         .with_context(|| "failed to ensure ingest_meta schema")?;
 
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ingest_errors (
+                path TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                offset INTEGER NOT NULL,
+                message TEXT NOT NULL,
+                sample TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                count INTEGER NOT NULL DEFAULT 1,
+                PRIMARY KEY (path, kind)
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure ingest_errors schema")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS usage_mismatches (
+                session_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                model TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                reported_tokens INTEGER NOT NULL,
+                cumulative_tokens INTEGER NOT NULL,
+                PRIMARY KEY (session_id, timestamp)
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure usage_mismatches schema")?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ingest_backfill (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                started_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                files_total INTEGER NOT NULL,
+                files_done INTEGER NOT NULL,
+                bytes_total INTEGER NOT NULL,
+                bytes_done INTEGER NOT NULL
+            );
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure ingest_backfill schema")?;
+
         let has_current_effort = self
             .table_has_column("ingest_state", "current_effort")
             .await?;
@@ -464,6 +927,29 @@ impl Storage {
                 .await
                 .with_context(|| "failed to add ingest_state.current_effort column")?;
         }
+        let has_current_service_tier = self
+            .table_has_column("ingest_state", "current_service_tier")
+            .await?;
+        if !has_current_service_tier {
+            sqlx::query("ALTER TABLE ingest_state ADD COLUMN current_service_tier TEXT;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to add ingest_state.current_service_tier column")?;
+        }
+        for column in [
+            "current_approval_policy",
+            "current_sandbox_policy",
+            "current_cwd",
+        ] {
+            if !self.table_has_column("ingest_state", column).await? {
+                sqlx::query(&format!(
+                    "ALTER TABLE ingest_state ADD COLUMN {column} TEXT;"
+                ))
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("failed to add ingest_state.{column} column"))?;
+            }
+        }
         let has_current_message_id = self
             .table_has_column("ingest_state", "current_message_id")
             .await
@@ -486,6 +972,55 @@ impl Storage {
             .await
             .with_context(|| "failed to add ingest_state.current_message_seq column")?;
         }
+        let has_completed = self.table_has_column("ingest_state", "completed").await?;
+        if !has_completed {
+            sqlx::query(
+                "ALTER TABLE ingest_state ADD COLUMN completed INTEGER NOT NULL DEFAULT 0;",
+            )
+            .execute(&*self.pool)
+            .await
+            .with_context(|| "failed to add ingest_state.completed column")?;
+        }
+        let has_inode = self.table_has_column("ingest_state", "inode").await?;
+        if !has_inode {
+            sqlx::query("ALTER TABLE ingest_state ADD COLUMN device INTEGER;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to add ingest_state.device column")?;
+            sqlx::query("ALTER TABLE ingest_state ADD COLUMN inode INTEGER;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to add ingest_state.inode column")?;
+        }
+        let has_head_fingerprint = self
+            .table_has_column("ingest_state", "head_fingerprint")
+            .await?;
+        if !has_head_fingerprint {
+            sqlx::query("ALTER TABLE ingest_state ADD COLUMN head_fingerprint TEXT;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to add ingest_state.head_fingerprint column")?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_ingest_state_fingerprint
+            ON ingest_state(head_fingerprint);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure ingest_state fingerprint index")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_ingest_state_session
+            ON ingest_state(session_id);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure ingest_state session index")?;
 
         sqlx::query(
             r#"
@@ -512,117 +1047,249 @@ impl Storage {
         .await
         .with_context(|| "failed to ensure session_tool_calls session index")?;
 
+        for (column, column_type) in TOOL_OUTCOME_COLUMNS {
+            if !self.table_has_column("session_tool_calls", column).await? {
+                sqlx::query(&format!(
+                    "ALTER TABLE session_tool_calls ADD COLUMN {column} {column_type};"
+                ))
+                .execute(&*self.pool)
+                .await
+                .with_context(|| format!("failed to add session_tool_calls.{column} column"))?;
+            }
+        }
+
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_sessions_last_event
-            ON sessions(last_event_at);
+            CREATE INDEX IF NOT EXISTS idx_session_tool_calls_call
+            ON session_tool_calls(session_id, call_id);
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure sessions last_event_at index")?;
+        .with_context(|| "failed to ensure session_tool_calls call index")?;
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_session_turns_session_time
-            ON session_turns(session_id, timestamp);
+            CREATE TABLE IF NOT EXISTS session_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                detail TEXT,
+                UNIQUE(session_id, timestamp, kind),
+                FOREIGN KEY (session_id) REFERENCES sessions(session_id)
+            );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure session_turns session index")?;
+        .with_context(|| "failed to ensure session_events schema")?;
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_session_turns_timestamp
-            ON session_turns(timestamp);
+            CREATE TABLE IF NOT EXISTS session_links (
+                session_id TEXT PRIMARY KEY,
+                parent_session_id TEXT NOT NULL,
+                source TEXT NOT NULL
+            );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure session_turns timestamp index")?;
+        .with_context(|| "failed to ensure session_links schema")?;
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_session_turns_message
-            ON session_turns(message_id);
+            CREATE TABLE IF NOT EXISTS session_commits (
+                session_id TEXT NOT NULL,
+                sha TEXT NOT NULL,
+                authored_at TEXT NOT NULL,
+                branch TEXT,
+                subject TEXT,
+                PRIMARY KEY (session_id, sha)
+            );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure session_turns message index")?;
+        .with_context(|| "failed to ensure session_commits schema")?;
 
         sqlx::query(
             r#"
-            CREATE UNIQUE INDEX IF NOT EXISTS idx_session_messages_seq
-            ON session_messages(session_id, message_seq);
+            CREATE TABLE IF NOT EXISTS session_git_scans (
+                session_id TEXT PRIMARY KEY,
+                scanned_at TEXT NOT NULL,
+                last_event_at TEXT NOT NULL,
+                status TEXT NOT NULL,
+                detail TEXT
+            );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure session_messages seq index")?;
+        .with_context(|| "failed to ensure session_git_scans schema")?;
+
+        sqlx::query("DROP VIEW IF EXISTS session_tree;")
+            .execute(&*self.pool)
+            .await
+            .with_context(|| "failed to drop session_tree view")?;
+        sqlx::query(SESSION_TREE_VIEW_SQL)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| "failed to create session_tree view")?;
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_session_messages_time
-            ON session_messages(session_id, timestamp);
+            CREATE TABLE IF NOT EXISTS rate_limit_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                primary_used_percent REAL,
+                primary_window_minutes INTEGER,
+                primary_resets_at TEXT,
+                secondary_used_percent REAL,
+                secondary_window_minutes INTEGER,
+                secondary_resets_at TEXT,
+                plan_type TEXT,
+                UNIQUE(session_id, timestamp)
+            );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure session_messages time index")?;
+        .with_context(|| "failed to ensure rate_limit_snapshots schema")?;
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_session_daily_date
-            ON session_daily_stats(date);
+            CREATE INDEX IF NOT EXISTS idx_rate_limit_snapshots_timestamp
+            ON rate_limit_snapshots(timestamp);
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure session_daily_stats date index")?;
+        .with_context(|| "failed to ensure rate_limit_snapshots timestamp index")?;
 
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS idx_daily_stats_date
-            ON daily_stats(date);
+            CREATE INDEX IF NOT EXISTS idx_sessions_last_event
+            ON sessions(last_event_at);
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure daily_stats date index")?;
-
-        self.ensure_turn_dedupe_index().await?;
-        self.ensure_daily_stats_basis().await?;
-
-        Ok(())
-    }
+        .with_context(|| "failed to ensure sessions last_event_at index")?;
 
-    async fn index_exists(&self, name: &str) -> Result<bool> {
-        let row: Option<i64> = sqlx::query_scalar(
+        sqlx::query(
             r#"
-            SELECT 1
-            FROM sqlite_master
-            WHERE type = 'index' AND name = ?
+            CREATE INDEX IF NOT EXISTS idx_sessions_root_label
+            ON sessions(root_label);
             "#,
         )
-        .bind(name)
-        .fetch_optional(&*self.pool)
+        .execute(&*self.pool)
         .await
-        .with_context(|| format!("failed to check index {name}"))?;
-        Ok(row.is_some())
-    }
+        .with_context(|| "failed to ensure sessions root_label index")?;
 
-    async fn ensure_turn_dedupe_index(&self) -> Result<()> {
-        if self.index_exists("idx_session_turns_dedupe").await? {
-            return Ok(());
-        }
-        let result = sqlx::query(
+        sqlx::query(
             r#"
-            CREATE UNIQUE INDEX idx_session_turns_dedupe
-            ON session_turns(
-                session_id,
-                timestamp,
+            CREATE INDEX IF NOT EXISTS idx_session_turns_session_time
+            ON session_turns(session_id, timestamp);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_turns session index")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_session_turns_timestamp
+            ON session_turns(timestamp);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_turns timestamp index")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_session_turns_message
+            ON session_turns(message_id);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_turns message index")?;
+
+        sqlx::query(
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_session_messages_seq
+            ON session_messages(session_id, message_seq);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_messages seq index")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_session_messages_time
+            ON session_messages(session_id, timestamp);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_messages time index")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_session_daily_date
+            ON session_daily_stats(date);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure session_daily_stats date index")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_daily_stats_date
+            ON daily_stats(date);
+            "#,
+        )
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to ensure daily_stats date index")?;
+
+        self.ensure_turn_dedupe_index().await?;
+        self.ensure_daily_stats_basis().await?;
+
+        Ok(())
+    }
+
+    async fn index_exists(&self, name: &str) -> Result<bool> {
+        let row: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT 1
+            FROM sqlite_master
+            WHERE type = 'index' AND name = ?
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&*self.pool)
+        .await
+        .with_context(|| format!("failed to check index {name}"))?;
+        Ok(row.is_some())
+    }
+
+    async fn ensure_turn_dedupe_index(&self) -> Result<()> {
+        if self.index_exists("idx_session_turns_dedupe").await? {
+            return Ok(());
+        }
+        let result = sqlx::query(
+            r#"
+            CREATE UNIQUE INDEX idx_session_turns_dedupe
+            ON session_turns(
+                session_id,
+                timestamp,
                 model,
                 prompt_tokens,
                 cached_prompt_tokens,
@@ -674,20 +1341,21 @@ impl Storage {
         sqlx::query(
             r#"
             INSERT INTO session_daily_stats (
-                date, session_id, model, prompt_tokens, cached_prompt_tokens,
+                date, session_id, model, service_tier, prompt_tokens, cached_prompt_tokens,
                 completion_tokens, reasoning_tokens, total_tokens
             )
             SELECT
                 DATE(timestamp, 'localtime') AS date,
                 session_id,
                 model,
+                service_tier,
                 COALESCE(SUM(prompt_tokens), 0),
                 COALESCE(SUM(cached_prompt_tokens), 0),
                 COALESCE(SUM(completion_tokens), 0),
                 COALESCE(SUM(reasoning_tokens), 0),
                 COALESCE(SUM(total_tokens), 0)
             FROM session_turns
-            GROUP BY date, session_id, model
+            GROUP BY date, session_id, model, service_tier
             "#,
         )
         .execute(&mut *tx)
@@ -697,19 +1365,20 @@ impl Storage {
         sqlx::query(
             r#"
             INSERT INTO daily_stats (
-                date, model, prompt_tokens, cached_prompt_tokens,
+                date, model, service_tier, prompt_tokens, cached_prompt_tokens,
                 completion_tokens, reasoning_tokens, total_tokens
             )
             SELECT
                 DATE(timestamp, 'localtime') AS date,
                 model,
+                service_tier,
                 COALESCE(SUM(prompt_tokens), 0),
                 COALESCE(SUM(cached_prompt_tokens), 0),
                 COALESCE(SUM(completion_tokens), 0),
                 COALESCE(SUM(reasoning_tokens), 0),
                 COALESCE(SUM(total_tokens), 0)
             FROM session_turns
-            GROUP BY date, model
+            GROUP BY date, model, service_tier
             "#,
         )
         .execute(&mut *tx)
@@ -747,47 +1416,101 @@ impl Storage {
                 .await;
         }
 
+        sqlx::query(PRICES_TABLE_SQL)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| "failed to ensure prices schema")?;
+
+        // model_prices is derived and price_overrides is reloaded from the
+        // config on startup, so both are simply recreated on schema changes.
+        let model_prices_current = self
+            .table_has_column("model_prices", "effective_from")
+            .await
+            .unwrap_or(false)
+            && self
+                .table_has_column("model_prices", "service_tier")
+                .await
+                .unwrap_or(false);
+        if !model_prices_current {
+            sqlx::query("DROP TABLE IF EXISTS model_prices;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to drop outdated model_prices")?;
+        }
+        if !self
+            .table_has_column("price_overrides", "service_tier")
+            .await
+            .unwrap_or(false)
+        {
+            sqlx::query("DROP TABLE IF EXISTS price_overrides;")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to drop outdated price_overrides")?;
+        }
+
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS prices (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
+            CREATE TABLE IF NOT EXISTS model_prices (
                 model TEXT NOT NULL,
+                service_tier TEXT NOT NULL DEFAULT 'default',
                 effective_from TEXT NOT NULL,
-                currency TEXT NOT NULL,
-                prompt_per_1m REAL NOT NULL,
+                prompt_per_1m REAL,
                 cached_prompt_per_1m REAL,
-                completion_per_1m REAL NOT NULL,
-                UNIQUE(model, effective_from)
+                completion_per_1m REAL,
+                long_context_threshold INTEGER,
+                long_prompt_per_1m REAL,
+                long_cached_prompt_per_1m REAL,
+                long_completion_per_1m REAL,
+                PRIMARY KEY (model, service_tier, effective_from)
             );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure prices schema")?;
+        .with_context(|| "failed to ensure model_prices schema")?;
 
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS model_prices (
-                model TEXT PRIMARY KEY,
-                prompt_per_1m REAL,
+            CREATE TABLE IF NOT EXISTS price_overrides (
+                model TEXT NOT NULL,
+                service_tier TEXT NOT NULL DEFAULT 'default',
+                effective_from TEXT NOT NULL,
+                currency TEXT NOT NULL,
+                prompt_per_1m REAL NOT NULL,
                 cached_prompt_per_1m REAL,
-                completion_per_1m REAL
+                completion_per_1m REAL NOT NULL,
+                long_context_threshold INTEGER,
+                long_prompt_per_1m REAL,
+                long_cached_prompt_per_1m REAL,
+                long_completion_per_1m REAL,
+                PRIMARY KEY (model, service_tier, effective_from)
             );
             "#,
         )
         .execute(&*self.pool)
         .await
-        .with_context(|| "failed to ensure model_prices schema")?;
+        .with_context(|| "failed to ensure price_overrides schema")?;
+
+        let mut model_prices_long_context = true;
+        for table in ["prices", "price_overrides", "model_prices"] {
+            for (column, column_type) in LONG_CONTEXT_PRICE_COLUMNS {
+                if !self.table_has_column(table, column).await? {
+                    if table == "model_prices" {
+                        model_prices_long_context = false;
+                    }
+                    sqlx::query(&format!(
+                        "ALTER TABLE {table} ADD COLUMN {column} {column_type};"
+                    ))
+                    .execute(&*self.pool)
+                    .await
+                    .with_context(|| format!("failed to add {table}.{column} column"))?;
+                }
+            }
+        }
 
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_model_prices_model
-            ON model_prices(model);
-            "#,
-        )
-        .execute(&*self.pool)
-        .await
-        .with_context(|| "failed to ensure model_prices index")?;
+        if !self.table_has_column("prices", "service_tier").await? {
+            self.migrate_prices_to_service_tiers().await?;
+        }
 
         sqlx::query(
             r#"
@@ -822,6 +1545,50 @@ impl Storage {
         .await
         .with_context(|| "failed to ensure prices effective_from index")?;
 
+        if !model_prices_current || !model_prices_long_context {
+            let mut tx = self.pool.begin().await?;
+            self.refresh_model_prices_tx(&mut tx).await?;
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds `prices` with `service_tier` in its unique key, keeping the
+    /// existing price history as standard-tier versions.
+    async fn migrate_prices_to_service_tiers(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("ALTER TABLE prices RENAME TO prices_untiered;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to rename prices for service tier migration")?;
+        sqlx::query(PRICES_TABLE_SQL)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to create tiered prices table")?;
+        sqlx::query(
+            r#"
+            INSERT INTO prices (
+                model, service_tier, effective_from, currency, prompt_per_1m,
+                cached_prompt_per_1m, completion_per_1m, long_context_threshold,
+                long_prompt_per_1m, long_cached_prompt_per_1m, long_completion_per_1m
+            )
+            SELECT
+                model, ?, effective_from, currency, prompt_per_1m,
+                cached_prompt_per_1m, completion_per_1m, long_context_threshold,
+                long_prompt_per_1m, long_cached_prompt_per_1m, long_completion_per_1m
+            FROM prices_untiered
+            "#,
+        )
+        .bind(DEFAULT_SERVICE_TIER)
+        .execute(&mut *tx)
+        .await
+        .with_context(|| "failed to copy prices into tiered table")?;
+        sqlx::query("DROP TABLE prices_untiered;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to drop untiered prices")?;
+        tx.commit().await?;
         Ok(())
     }
 
@@ -901,39 +1668,101 @@ impl Storage {
         }))
     }
 
-    pub async fn replace_prices(
+    /// Records a pricing sync. Models seen for the first time get a baseline
+    /// version that also covers earlier usage; a model whose rates changed gets
+    /// a new version effective from `price.effective_from`. Existing versions are
+    /// never deleted, so past spend keeps the price that was in force.
+    pub async fn sync_prices(
         &self,
         prices: &[NewPrice],
         source_url: &str,
         fetched_at: DateTime<Utc>,
     ) -> Result<usize> {
         let mut tx = self.pool.begin().await?;
-        sqlx::query("DELETE FROM prices;")
-            .execute(&mut *tx)
-            .await
-            .with_context(|| "failed to clear prices table")?;
-        sqlx::query("DELETE FROM model_prices;")
-            .execute(&mut *tx)
-            .await
-            .with_context(|| "failed to clear model_prices table")?;
+        let mut changed = 0usize;
 
         for price in prices {
+            let latest = sqlx::query(
+                r#"
+                SELECT prompt_per_1m, cached_prompt_per_1m, completion_per_1m,
+                       long_context_threshold, long_prompt_per_1m, long_cached_prompt_per_1m,
+                       long_completion_per_1m
+                FROM prices
+                WHERE model = ?
+                  AND service_tier = ?
+                ORDER BY effective_from DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(&price.model)
+            .bind(&price.service_tier)
+            .fetch_optional(&mut *tx)
+            .await
+            .with_context(|| "failed to load latest price version")?;
+
+            let effective_from = match latest {
+                None => PRICE_BASELINE_DATE.to_string(),
+                Some(row) => {
+                    let prompt: f64 = row.try_get("prompt_per_1m")?;
+                    let cached: Option<f64> = row.try_get("cached_prompt_per_1m")?;
+                    let completion: f64 = row.try_get("completion_per_1m")?;
+                    let long_context = long_context_price_from_row(&row)?;
+                    if rates_match(prompt, price.prompt_per_1m)
+                        && rates_match(completion, price.completion_per_1m)
+                        && optional_rates_match(cached, price.cached_prompt_per_1m)
+                        && match (long_context, price.long_context) {
+                            (Some(old), Some(new)) => {
+                                old.threshold_tokens == new.threshold_tokens
+                                    && rates_match(old.prompt_per_1m, new.prompt_per_1m)
+                                    && optional_rates_match(
+                                        old.cached_prompt_per_1m,
+                                        new.cached_prompt_per_1m,
+                                    )
+                                    && rates_match(old.completion_per_1m, new.completion_per_1m)
+                            }
+                            (None, None) => true,
+                            _ => false,
+                        }
+                    {
+                        continue;
+                    }
+                    price.effective_from.to_string()
+                }
+            };
+
             sqlx::query(
                 r#"
                 INSERT INTO prices (
-                    model, effective_from, currency, prompt_per_1m, cached_prompt_per_1m, completion_per_1m
-                ) VALUES (?, ?, ?, ?, ?, ?)
+                    model, service_tier, effective_from, currency, prompt_per_1m, cached_prompt_per_1m,
+                    completion_per_1m, long_context_threshold, long_prompt_per_1m,
+                    long_cached_prompt_per_1m, long_completion_per_1m
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(model, service_tier, effective_from) DO UPDATE SET
+                    currency = excluded.currency,
+                    prompt_per_1m = excluded.prompt_per_1m,
+                    cached_prompt_per_1m = excluded.cached_prompt_per_1m,
+                    completion_per_1m = excluded.completion_per_1m,
+                    long_context_threshold = excluded.long_context_threshold,
+                    long_prompt_per_1m = excluded.long_prompt_per_1m,
+                    long_cached_prompt_per_1m = excluded.long_cached_prompt_per_1m,
+                    long_completion_per_1m = excluded.long_completion_per_1m
                 "#,
             )
             .bind(&price.model)
-            .bind(price.effective_from.to_string())
+            .bind(&price.service_tier)
+            .bind(effective_from)
             .bind(&price.currency)
             .bind(price.prompt_per_1m)
             .bind(price.cached_prompt_per_1m)
             .bind(price.completion_per_1m)
+            .bind(price.long_context.map(|tier| tier.threshold_i64()))
+            .bind(price.long_context.map(|tier| tier.prompt_per_1m))
+            .bind(price.long_context.and_then(|tier| tier.cached_prompt_per_1m))
+            .bind(price.long_context.map(|tier| tier.completion_per_1m))
             .execute(&mut *tx)
             .await
             .with_context(|| "failed to insert price row")?;
+            changed += 1;
         }
 
         self.refresh_model_prices_tx(&mut tx)
@@ -956,61 +1785,219 @@ impl Storage {
         .with_context(|| "failed to upsert pricing meta")?;
 
         tx.commit().await?;
-        Ok(prices.len())
+        Ok(changed)
+    }
+
+    /// Replaces the configured price overrides and re-resolves model prices.
+    pub async fn replace_price_overrides(&self, overrides: &[PriceOverride]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM price_overrides;")
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to clear price_overrides")?;
+
+        for price in overrides {
+            let effective_from = price
+                .effective_from
+                .map(|date| date.to_string())
+                .unwrap_or_else(|| PRICE_BASELINE_DATE.to_string());
+            sqlx::query(
+                r#"
+                INSERT INTO price_overrides (
+                    model, service_tier, effective_from, currency, prompt_per_1m, cached_prompt_per_1m,
+                    completion_per_1m, long_context_threshold, long_prompt_per_1m,
+                    long_cached_prompt_per_1m, long_completion_per_1m
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(model, service_tier, effective_from) DO UPDATE SET
+                    currency = excluded.currency,
+                    prompt_per_1m = excluded.prompt_per_1m,
+                    cached_prompt_per_1m = excluded.cached_prompt_per_1m,
+                    completion_per_1m = excluded.completion_per_1m,
+                    long_context_threshold = excluded.long_context_threshold,
+                    long_prompt_per_1m = excluded.long_prompt_per_1m,
+                    long_cached_prompt_per_1m = excluded.long_cached_prompt_per_1m,
+                    long_completion_per_1m = excluded.long_completion_per_1m
+                "#,
+            )
+            .bind(&price.model)
+            .bind(&price.service_tier)
+            .bind(effective_from)
+            .bind(&price.currency)
+            .bind(price.prompt_per_1m)
+            .bind(price.cached_prompt_per_1m)
+            .bind(price.completion_per_1m)
+            .bind(price.long_context.map(|tier| tier.threshold_i64()))
+            .bind(price.long_context.map(|tier| tier.prompt_per_1m))
+            .bind(price.long_context.and_then(|tier| tier.cached_prompt_per_1m))
+            .bind(price.long_context.map(|tier| tier.completion_per_1m))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to insert price override")?;
+        }
+
+        self.refresh_model_prices_tx(&mut tx)
+            .await
+            .with_context(|| "failed to refresh model price mappings")?;
+        tx.commit().await?;
+        Ok(())
     }
 
+    /// Rebuilds the price versions for every observed model and service tier.
+    /// See `resolve_tier_price_versions` for how remote prices and overrides
+    /// merge.
     async fn refresh_model_prices_tx(
         &self,
         tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
     ) -> Result<()> {
-        sqlx::query(
+        sqlx::query("DELETE FROM model_prices;")
+            .execute(&mut **tx)
+            .await
+            .with_context(|| "failed to clear model_prices")?;
+
+        let models: Vec<(String, String)> =
+            sqlx::query_as("SELECT DISTINCT model, service_tier FROM session_turns")
+                .fetch_all(&mut **tx)
+                .await
+                .with_context(|| "failed to load observed models")?;
+        if models.is_empty() {
+            return Ok(());
+        }
+
+        let remote = load_price_versions_tx(tx, "prices").await?;
+        let overrides = load_price_versions_tx(tx, "price_overrides").await?;
+        for (model, service_tier) in models {
+            let versions = resolve_tier_price_versions(&model, &service_tier, &remote, &overrides);
+            insert_model_prices_tx(tx, &model, &service_tier, &versions).await?;
+        }
+
+        self.rebuild_long_context_tokens_tx(tx).await
+    }
+
+    /// Recomputes the long-context token columns of the daily tables against
+    /// the thresholds now in `model_prices`.
+    async fn rebuild_long_context_tokens_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ) -> Result<()> {
+        for table in ["session_daily_stats", "daily_stats"] {
+            sqlx::query(&format!(
+                r#"
+                UPDATE {table}
+                SET long_prompt_tokens = 0,
+                    long_cached_prompt_tokens = 0,
+                    long_completion_tokens = 0
+                WHERE long_prompt_tokens <> 0
+                   OR long_cached_prompt_tokens <> 0
+                   OR long_completion_tokens <> 0
+                "#
+            ))
+            .execute(&mut **tx)
+            .await
+            .with_context(|| format!("failed to reset long-context tokens in {table}"))?;
+        }
+
+        sqlx::query(&format!(
             r#"
-            INSERT OR REPLACE INTO model_prices (
-                model, prompt_per_1m, cached_prompt_per_1m, completion_per_1m
-            )
-            SELECT
-                m.model,
-                p.prompt_per_1m,
-                p.cached_prompt_per_1m,
-                p.completion_per_1m
+            UPDATE session_daily_stats
+            SET long_prompt_tokens = l.prompt_tokens,
+                long_cached_prompt_tokens = l.cached_prompt_tokens,
+                long_completion_tokens = l.completion_tokens
+            FROM ({LONG_CONTEXT_TURNS_SQL}) l
+            WHERE session_daily_stats.date = l.date
+              AND session_daily_stats.session_id = l.session_id
+              AND session_daily_stats.model = l.model
+              AND session_daily_stats.service_tier = l.service_tier
+            "#
+        ))
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to update long-context tokens in session_daily_stats")?;
+        sqlx::query(&format!(
+            r#"
+            UPDATE daily_stats
+            SET long_prompt_tokens = l.prompt_tokens,
+                long_cached_prompt_tokens = l.cached_prompt_tokens,
+                long_completion_tokens = l.completion_tokens
             FROM (
-                SELECT DISTINCT model FROM session_turns
-            ) m
-            LEFT JOIN prices p
-              ON p.rowid = (
-                  SELECT p2.rowid
-                  FROM prices p2
-                  WHERE m.model LIKE p2.model || '%'
-                  ORDER BY LENGTH(p2.model) DESC
-                  LIMIT 1
-              )
-            "#,
-        )
+                SELECT date, model, service_tier,
+                       SUM(prompt_tokens) AS prompt_tokens,
+                       SUM(cached_prompt_tokens) AS cached_prompt_tokens,
+                       SUM(completion_tokens) AS completion_tokens
+                FROM ({LONG_CONTEXT_TURNS_SQL})
+                GROUP BY date, model, service_tier
+            ) l
+            WHERE daily_stats.date = l.date
+              AND daily_stats.model = l.model
+              AND daily_stats.service_tier = l.service_tier
+            "#
+        ))
         .execute(&mut **tx)
         .await
-        .with_context(|| "failed to refresh model_prices")?;
+        .with_context(|| "failed to update long-context tokens in daily_stats")?;
         Ok(())
     }
 
+    /// Price versions currently in force, one per model prefix and service
+    /// tier. An override hides the remote price for the same prefix and tier.
     pub async fn list_prices(&self) -> Result<Vec<PriceRow>> {
         let rows = sqlx::query(
             r#"
-            SELECT model, prompt_per_1m, cached_prompt_per_1m, completion_per_1m
-            FROM prices
-            ORDER BY model ASC
+            SELECT p.model, p.service_tier, p.effective_from, p.prompt_per_1m, p.cached_prompt_per_1m,
+                   p.completion_per_1m, p.long_context_threshold, p.long_prompt_per_1m,
+                   p.long_cached_prompt_per_1m, p.long_completion_per_1m, 'remote' AS source
+            FROM prices p
+            WHERE p.effective_from = (
+                SELECT MAX(p2.effective_from)
+                FROM prices p2
+                WHERE p2.model = p.model
+                  AND p2.service_tier = p.service_tier
+                  AND p2.effective_from <= DATE('now', 'localtime')
+            )
+            UNION ALL
+            SELECT o.model, o.service_tier, o.effective_from, o.prompt_per_1m, o.cached_prompt_per_1m,
+                   o.completion_per_1m, o.long_context_threshold, o.long_prompt_per_1m,
+                   o.long_cached_prompt_per_1m, o.long_completion_per_1m, 'override' AS source
+            FROM price_overrides o
+            WHERE o.effective_from = (
+                SELECT MAX(o2.effective_from)
+                FROM price_overrides o2
+                WHERE o2.model = o.model
+                  AND o2.service_tier = o.service_tier
+                  AND o2.effective_from <= DATE('now', 'localtime')
+            )
+            ORDER BY model ASC, service_tier ASC, source ASC
             "#,
         )
         .fetch_all(&*self.pool)
         .await
         .with_context(|| "failed to list prices")?;
 
-        let mut prices = Vec::with_capacity(rows.len());
+        let mut prices: Vec<PriceRow> = Vec::with_capacity(rows.len());
         for row in rows {
+            let model: String = row.try_get("model")?;
+            let service_tier: String = row.try_get("service_tier")?;
+            if prices
+                .last()
+                .is_some_and(|last| last.model == model && last.service_tier == service_tier)
+            {
+                continue;
+            }
+            let effective_from: String = row.try_get("effective_from")?;
+            let source: String = row.try_get("source")?;
             prices.push(PriceRow {
-                model: row.try_get::<String, _>("model")?,
+                model,
+                service_tier,
+                effective_from: NaiveDate::parse_from_str(&effective_from, "%Y-%m-%d")
+                    .with_context(|| "invalid effective_from in prices")?,
                 prompt_per_1m: row.try_get::<f64, _>("prompt_per_1m").unwrap_or(0.0),
                 cached_prompt_per_1m: row.try_get::<Option<f64>, _>("cached_prompt_per_1m")?,
                 completion_per_1m: row.try_get::<f64, _>("completion_per_1m").unwrap_or(0.0),
+                long_context: long_context_price_from_row(&row)?,
+                source: if source == "override" {
+                    PriceSource::Override
+                } else {
+                    PriceSource::Remote
+                },
             });
         }
 
@@ -1021,11 +2008,12 @@ impl Storage {
         let rows = sqlx::query(
             r#"
             SELECT model,
+                   service_tier,
                    DATE(MIN(timestamp), 'localtime') AS first_seen,
                    DATE(MAX(timestamp), 'localtime') AS last_seen
             FROM session_turn_costs
             WHERE missing_price = 1
-            GROUP BY model
+            GROUP BY model, service_tier
             ORDER BY last_seen DESC
             LIMIT ?
             "#,
@@ -1046,6 +2034,7 @@ impl Storage {
 
             results.push(MissingPriceDetail {
                 model: row.try_get::<String, _>("model")?,
+                service_tier: row.try_get::<String, _>("service_tier")?,
                 first_seen,
                 last_seen,
             });
@@ -1136,7 +2125,7 @@ impl Storage {
         session_id: &str,
         limit: usize,
     ) -> Result<Vec<SessionMessage>> {
-        let rows = sqlx::query(
+        let query = format!(
             r#"
             SELECT
                 m.id,
@@ -1148,22 +2137,24 @@ impl Storage {
                 COALESCE(SUM(t.completion_tokens), 0) AS completion_tokens,
                 COALESCE(SUM(t.total_tokens), 0) AS total_tokens,
                 COALESCE(SUM(t.reasoning_tokens), 0) AS reasoning_tokens,
-                COALESCE(SUM(t.cost_usd), 0.0) AS cost_usd,
+                COALESCE(SUM(t.cost), 0.0) AS cost,
                 COALESCE(SUM(t.missing_price), 0) AS missing_price
             FROM session_messages m
-            LEFT JOIN session_turn_costs t
+            LEFT JOIN {turn_costs} t
               ON t.message_id = m.id
             WHERE m.session_id = ?
             GROUP BY m.id
             ORDER BY m.message_seq DESC
             LIMIT ?
             "#,
-        )
-        .bind(session_id)
-        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
-        .fetch_all(&*self.pool)
-        .await
-        .with_context(|| "failed to load session messages")?;
+            turn_costs = self.turn_costs(),
+        );
+        let rows = sqlx::query(&query)
+            .bind(session_id)
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load session messages")?;
 
         let mut messages = Vec::with_capacity(rows.len());
         for row in rows {
@@ -1182,7 +2173,7 @@ impl Storage {
                 completion_tokens: row.try_get::<i64, _>("completion_tokens").unwrap_or(0) as u64,
                 total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
                 reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0) as u64,
-                cost_usd: cost_from_row(&row),
+                cost: cost_from_row(&row),
             });
         }
 
@@ -1193,7 +2184,7 @@ impl Storage {
         &self,
         session_id: &str,
     ) -> Result<Option<SessionMessage>> {
-        let row = sqlx::query(
+        let query = format!(
             r#"
             SELECT
                 MAX(timestamp) AS timestamp,
@@ -1203,17 +2194,19 @@ impl Storage {
                 COALESCE(SUM(completion_tokens), 0) AS completion_tokens,
                 COALESCE(SUM(total_tokens), 0) AS total_tokens,
                 COALESCE(SUM(reasoning_tokens), 0) AS reasoning_tokens,
-                COALESCE(SUM(cost_usd), 0.0) AS cost_usd,
+                COALESCE(SUM(cost), 0.0) AS cost,
                 COALESCE(SUM(missing_price), 0) AS missing_price
-            FROM session_turn_costs
+            FROM {turn_costs}
             WHERE session_id = ?
               AND message_id IS NULL
             "#,
-        )
-        .bind(session_id)
-        .fetch_one(&*self.pool)
-        .await
-        .with_context(|| "failed to load unattributed message summary")?;
+            turn_costs = self.turn_costs(),
+        );
+        let row = sqlx::query(&query)
+            .bind(session_id)
+            .fetch_one(&*self.pool)
+            .await
+            .with_context(|| "failed to load unattributed message summary")?;
 
         let turn_count = row.try_get::<i64, _>("turn_count").unwrap_or(0);
         if turn_count <= 0 {
@@ -1239,12 +2232,12 @@ impl Storage {
             completion_tokens: row.try_get::<i64, _>("completion_tokens").unwrap_or(0) as u64,
             total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
             reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0) as u64,
-            cost_usd: cost_from_row(&row),
+            cost: cost_from_row(&row),
         }))
     }
 
     pub async fn session_turn_totals(&self, session_id: &str) -> Result<AggregateTotals> {
-        let row = sqlx::query(
+        let query = format!(
             r#"
             SELECT
                 COALESCE(SUM(prompt_tokens), 0) AS prompt_tokens,
@@ -1252,16 +2245,18 @@ impl Storage {
                 COALESCE(SUM(completion_tokens), 0) AS completion_tokens,
                 COALESCE(SUM(total_tokens), 0) AS total_tokens,
                 COALESCE(SUM(reasoning_tokens), 0) AS reasoning_tokens,
-                COALESCE(SUM(cost_usd), 0.0) AS cost_usd,
+                COALESCE(SUM(cost), 0.0) AS cost,
                 COALESCE(SUM(missing_price), 0) AS missing_price
-            FROM session_turn_costs
+            FROM {turn_costs}
             WHERE session_id = ?
             "#,
-        )
-        .bind(session_id)
-        .fetch_one(&*self.pool)
-        .await
-        .with_context(|| "failed to load session turn totals")?;
+            turn_costs = self.turn_costs(),
+        );
+        let row = sqlx::query(&query)
+            .bind(session_id)
+            .fetch_one(&*self.pool)
+            .await
+            .with_context(|| "failed to load session turn totals")?;
 
         Ok(AggregateTotals {
             prompt_tokens: row.try_get::<i64, _>("prompt_tokens").unwrap_or(0) as u64,
@@ -1269,12 +2264,12 @@ impl Storage {
             completion_tokens: row.try_get::<i64, _>("completion_tokens").unwrap_or(0) as u64,
             total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
             reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0) as u64,
-            cost_usd: cost_from_row(&row),
+            cost: cost_from_row(&row),
         })
     }
 
     pub async fn session_turn_daily_totals(&self, session_id: &str) -> Result<Vec<DailyTotals>> {
-        let rows = sqlx::query(
+        let query = format!(
             r#"
             SELECT
                 strftime('%Y-%m-%d', timestamp, 'localtime') AS day,
@@ -1283,18 +2278,20 @@ impl Storage {
                 COALESCE(SUM(completion_tokens), 0) AS completion_tokens,
                 COALESCE(SUM(total_tokens), 0) AS total_tokens,
                 COALESCE(SUM(reasoning_tokens), 0) AS reasoning_tokens,
-                COALESCE(SUM(cost_usd), 0.0) AS cost_usd,
+                COALESCE(SUM(cost), 0.0) AS cost,
                 COALESCE(SUM(missing_price), 0) AS missing_price
-            FROM session_turn_costs
+            FROM {turn_costs}
             WHERE session_id = ?
             GROUP BY day
             ORDER BY day ASC
             "#,
-        )
-        .bind(session_id)
-        .fetch_all(&*self.pool)
-        .await
-        .with_context(|| "failed to load session daily totals")?;
+            turn_costs = self.turn_costs(),
+        );
+        let rows = sqlx::query(&query)
+            .bind(session_id)
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load session daily totals")?;
 
         let mut totals = Vec::with_capacity(rows.len());
         for row in rows {
@@ -1311,7 +2308,7 @@ impl Storage {
                         as u64,
                     total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
                     reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0) as u64,
-                    cost_usd: cost_from_row(&row),
+                    cost: cost_from_row(&row),
                 },
             });
         }
@@ -1332,65 +2329,811 @@ impl Storage {
         .bind(session_id)
         .fetch_all(&*self.pool)
         .await
-        .with_context(|| "failed to load session model mix")?;
-
-        let mut result = Vec::with_capacity(rows.len());
-        for row in rows {
-            result.push(ModelUsageRow {
-                model: row.try_get::<String, _>("model")?,
-                reasoning_effort: row.try_get::<Option<String>, _>("reasoning_effort")?,
-                total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
-            });
-        }
-        Ok(result)
+        .with_context(|| "failed to load session model mix")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(ModelUsageRow {
+                model: row.try_get::<String, _>("model")?,
+                reasoning_effort: row.try_get::<Option<String>, _>("reasoning_effort")?,
+                total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
+            });
+        }
+        Ok(result)
+    }
+
+    pub async fn session_tool_counts(&self, session_id: &str) -> Result<Vec<ToolCountRow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                tool_name,
+                COUNT(*) AS call_count,
+                COUNT(success) AS finished,
+                SUM(CASE WHEN success = 0 THEN 1 ELSE 0 END) AS failed,
+                COALESCE(SUM(duration_ms), 0) AS duration_ms,
+                COALESCE(SUM(output_bytes), 0) AS output_bytes
+            FROM session_tool_calls
+            WHERE session_id = ?
+            GROUP BY tool_name
+            ORDER BY call_count DESC, tool_name ASC
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load session tool counts")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(tool_count_row(&row)?);
+        }
+        Ok(result)
+    }
+
+    /// Per-tool call counts, failures and time spent for calls made in the
+    /// range, most time-consuming first.
+    pub async fn tool_counts_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ToolCountRow>> {
+        let query = format!(
+            r#"
+            SELECT
+                tool_name,
+                COUNT(*) AS call_count,
+                COUNT(success) AS finished,
+                SUM(CASE WHEN success = 0 THEN 1 ELSE 0 END) AS failed,
+                COALESCE(SUM(duration_ms), 0) AS duration_ms,
+                COALESCE(SUM(output_bytes), 0) AS output_bytes
+            FROM session_tool_calls
+            WHERE timestamp >= ? AND timestamp < ? {}
+            GROUP BY tool_name
+            ORDER BY duration_ms DESC, call_count DESC, tool_name ASC
+            "#,
+            self.root_scope("session_id", "?"),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load tool counts")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(tool_count_row(&row)?);
+        }
+        Ok(result)
+    }
+
+    /// Commands that failed in the range, grouped by tool and command, most
+    /// failures first.
+    pub async fn failing_tool_commands_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<FailingCommandRow>> {
+        let query = format!(
+            r#"
+            SELECT
+                tool_name,
+                detail,
+                COUNT(*) AS call_count,
+                SUM(CASE WHEN success = 0 THEN 1 ELSE 0 END) AS failed,
+                COALESCE(SUM(duration_ms), 0) AS duration_ms
+            FROM session_tool_calls
+            WHERE timestamp >= ? AND timestamp < ? AND detail IS NOT NULL {}
+            GROUP BY tool_name, detail
+            HAVING failed > 0
+            ORDER BY failed DESC, duration_ms DESC
+            LIMIT ?
+            "#,
+            self.root_scope("session_id", "?"),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .bind(limit as i64)
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load failing tool commands")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(FailingCommandRow {
+                tool: row.try_get("tool_name")?,
+                detail: row.try_get("detail")?,
+                count: row.try_get::<i64, _>("call_count")?.max(0) as u64,
+                failed: row.try_get::<i64, _>("failed")?.max(0) as u64,
+                duration_ms: row.try_get::<i64, _>("duration_ms")?.max(0) as u64,
+            });
+        }
+        Ok(result)
+    }
+
+    pub async fn session_events(&self, session_id: &str) -> Result<Vec<SessionEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT timestamp, kind, detail
+            FROM session_events
+            WHERE session_id = ?
+            ORDER BY timestamp ASC, id ASC
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load session events")?;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            let timestamp: String = row.try_get("timestamp")?;
+            events.push(SessionEvent {
+                timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .with_context(|| "invalid timestamp in session_events")?,
+                kind: row.try_get("kind")?,
+                detail: row.try_get("detail")?,
+            });
+        }
+        Ok(events)
+    }
+
+    /// Records a notable point in a session. Replaying the same log line does
+    /// not add a second event.
+    pub async fn record_session_event_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        timestamp: DateTime<Utc>,
+        kind: &str,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_session_stub_tx(tx, session_id, timestamp)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO session_events (session_id, timestamp, kind, detail)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(session_id)
+        .bind(timestamp.to_rfc3339())
+        .bind(kind)
+        .bind(detail)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to insert session event")?;
+        Ok(())
+    }
+
+    /// Records the files a patch touches. They count towards the next turn
+    /// recorded for the session; replaying the same patch does not count it
+    /// twice.
+    pub async fn record_patch_files_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        timestamp: DateTime<Utc>,
+        call_id: Option<&str>,
+        files: &[FileLineChange],
+    ) -> Result<()> {
+        for file in files {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO session_patch_files
+                    (session_id, timestamp, path, call_id, lines_added, lines_removed)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(session_id)
+            .bind(timestamp.to_rfc3339())
+            .bind(&file.path)
+            .bind(call_id)
+            .bind(file.added as i64)
+            .bind(file.removed as i64)
+            .execute(&mut **tx)
+            .await
+            .with_context(|| "failed to record patch file")?;
+        }
+        Ok(())
+    }
+
+    /// Attributes patches not yet counted towards a turn to the given turn and
+    /// updates its line counts.
+    pub async fn attribute_patches_to_turn_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        timestamp: DateTime<Utc>,
+        model: &str,
+    ) -> Result<()> {
+        let ts = timestamp.to_rfc3339();
+        sqlx::query(
+            r#"
+            UPDATE session_patch_files
+            SET turn_timestamp = ?, turn_model = ?
+            WHERE session_id = ? AND turn_timestamp IS NULL AND timestamp <= ?
+            "#,
+        )
+        .bind(&ts)
+        .bind(model)
+        .bind(session_id)
+        .bind(&ts)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to attribute patches to turn")?;
+        self.refresh_turn_line_changes_tx(tx, session_id, &ts, model)
+            .await
+    }
+
+    /// Drops the files of a patch that failed to apply, and takes them out of
+    /// the turn they were counted towards.
+    pub async fn discard_patch_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        call_id: &str,
+    ) -> Result<()> {
+        let turns = sqlx::query(
+            r#"
+            DELETE FROM session_patch_files
+            WHERE session_id = ? AND call_id = ?
+            RETURNING turn_timestamp, turn_model
+            "#,
+        )
+        .bind(session_id)
+        .bind(call_id)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| "failed to discard patch files")?;
+        let mut seen = HashSet::new();
+        for row in turns {
+            let turn_ts: Option<String> = row.try_get("turn_timestamp")?;
+            let turn_model: Option<String> = row.try_get("turn_model")?;
+            if let (Some(turn_ts), Some(turn_model)) = (turn_ts, turn_model)
+                && seen.insert((turn_ts.clone(), turn_model.clone()))
+            {
+                self.refresh_turn_line_changes_tx(tx, session_id, &turn_ts, &turn_model)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn refresh_turn_line_changes_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        turn_timestamp: &str,
+        model: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE session_turns
+            SET (files_changed, lines_added, lines_removed) = (
+                SELECT
+                    COUNT(DISTINCT path),
+                    COALESCE(SUM(lines_added), 0),
+                    COALESCE(SUM(lines_removed), 0)
+                FROM session_patch_files
+                WHERE session_id = ? AND turn_timestamp = ? AND turn_model = ?
+            )
+            WHERE session_id = ? AND timestamp = ? AND model = ?
+            "#,
+        )
+        .bind(session_id)
+        .bind(turn_timestamp)
+        .bind(model)
+        .bind(session_id)
+        .bind(turn_timestamp)
+        .bind(model)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to update turn line changes")?;
+        Ok(())
+    }
+
+    /// Distinct files and total lines changed by a session's patches.
+    pub async fn session_line_changes(&self, session_id: &str) -> Result<LineChanges> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COUNT(DISTINCT path) AS files,
+                COALESCE(SUM(lines_added), 0) AS added,
+                COALESCE(SUM(lines_removed), 0) AS removed
+            FROM session_patch_files
+            WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_one(&*self.pool)
+        .await
+        .with_context(|| "failed to load session line changes")?;
+        Ok(LineChanges {
+            files: row.try_get::<i64, _>("files")?.max(0) as u64,
+            added: row.try_get::<i64, _>("added")?.max(0) as u64,
+            removed: row.try_get::<i64, _>("removed")?.max(0) as u64,
+        })
+    }
+
+    /// Usage, cost and lines changed per model, reasoning effort or repo for
+    /// turns in the range, most expensive first.
+    pub async fn line_change_costs_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        group: LineChangeGroup,
+        limit: usize,
+    ) -> Result<Vec<LineChangeCostRow>> {
+        let query = format!(
+            r#"
+            SELECT
+                {key} AS key,
+                COALESCE(SUM(t.total_tokens), 0) AS total_tokens,
+                SUM(t.cost) AS cost,
+                COALESCE(SUM(t.files_changed), 0) AS files_changed,
+                COALESCE(SUM(t.lines_added), 0) AS lines_added,
+                COALESCE(SUM(t.lines_removed), 0) AS lines_removed
+            FROM {turn_costs} t
+            JOIN sessions s ON s.session_id = t.session_id
+            WHERE t.timestamp >= ? AND t.timestamp < ? {scope}
+            GROUP BY key
+            HAVING key IS NOT NULL
+            ORDER BY COALESCE(SUM(t.cost), 0.0) DESC, key ASC
+            LIMIT ?
+            "#,
+            key = group.key_expr(),
+            scope = self.root_scope("t.session_id", "?"),
+            turn_costs = self.turn_costs(),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load line change costs")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(LineChangeCostRow {
+                key: row.try_get("key")?,
+                total_tokens: row.try_get::<i64, _>("total_tokens")?.max(0) as u64,
+                cost: row.try_get("cost")?,
+                files_changed: row.try_get::<i64, _>("files_changed")?.max(0) as u64,
+                lines_added: row.try_get::<i64, _>("lines_added")?.max(0) as u64,
+                lines_removed: row.try_get::<i64, _>("lines_removed")?.max(0) as u64,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Sessions with a working directory whose events are newer than their
+    /// last git scan, oldest first.
+    pub async fn sessions_pending_git_scan(&self) -> Result<Vec<GitScanTarget>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.session_id, s.cwd, s.started_at, s.last_event_at
+            FROM sessions s
+            LEFT JOIN session_git_scans g ON g.session_id = s.session_id
+            WHERE NULLIF(s.cwd, '') IS NOT NULL
+              AND (g.session_id IS NULL OR g.last_event_at < s.last_event_at)
+            ORDER BY s.started_at ASC
+            "#,
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load sessions pending git scan")?;
+
+        let mut targets = Vec::with_capacity(rows.len());
+        for row in rows {
+            let started_at: String = row.try_get("started_at")?;
+            let last_event_at: String = row.try_get("last_event_at")?;
+            targets.push(GitScanTarget {
+                session_id: row.try_get("session_id")?,
+                cwd: PathBuf::from(row.try_get::<String, _>("cwd")?),
+                started_at: parse_rfc3339(&started_at, "sessions.started_at")?,
+                last_event_at: parse_rfc3339(&last_event_at, "sessions.last_event_at")?,
+            });
+        }
+        Ok(targets)
+    }
+
+    /// Records the outcome of a git scan. `commits` replaces the session's
+    /// linked commits; `None` (the repo could not be read) keeps the commits
+    /// found by earlier scans.
+    pub async fn record_git_scan(
+        &self,
+        target: &GitScanTarget,
+        scanned_at: DateTime<Utc>,
+        status: &str,
+        detail: Option<&str>,
+        commits: Option<&[SessionCommit]>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(commits) = commits {
+            sqlx::query("DELETE FROM session_commits WHERE session_id = ?")
+                .bind(&target.session_id)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| "failed to clear session commits")?;
+            for commit in commits {
+                sqlx::query(
+                    r#"
+                    INSERT OR REPLACE INTO session_commits
+                        (session_id, sha, authored_at, branch, subject)
+                    VALUES (?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&target.session_id)
+                .bind(&commit.sha)
+                .bind(commit.authored_at.to_rfc3339())
+                .bind(commit.branch.as_deref())
+                .bind(&commit.subject)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| "failed to insert session commit")?;
+            }
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO session_git_scans (session_id, scanned_at, last_event_at, status, detail)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(session_id) DO UPDATE SET
+                scanned_at = excluded.scanned_at,
+                last_event_at = excluded.last_event_at,
+                status = excluded.status,
+                detail = excluded.detail
+            "#,
+        )
+        .bind(&target.session_id)
+        .bind(scanned_at.to_rfc3339())
+        .bind(target.last_event_at.to_rfc3339())
+        .bind(status)
+        .bind(detail)
+        .execute(&mut *tx)
+        .await
+        .with_context(|| "failed to record git scan")?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Commits linked to a session, oldest first.
+    pub async fn session_commits(&self, session_id: &str) -> Result<Vec<SessionCommit>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT sha, authored_at, branch, subject
+            FROM session_commits
+            WHERE session_id = ?
+            ORDER BY authored_at ASC, sha ASC
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load session commits")?;
+
+        let mut commits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let authored_at: String = row.try_get("authored_at")?;
+            commits.push(SessionCommit {
+                sha: row.try_get("sha")?,
+                authored_at: parse_rfc3339(&authored_at, "session_commits.authored_at")?,
+                branch: row.try_get("branch")?,
+                subject: row
+                    .try_get::<Option<String>, _>("subject")?
+                    .unwrap_or_default(),
+            });
+        }
+        Ok(commits)
+    }
+
+    /// Status of the latest git scan of a session, if it was scanned.
+    pub async fn session_git_scan(&self, session_id: &str) -> Result<Option<GitScanStatus>> {
+        let row = sqlx::query("SELECT status, detail FROM session_git_scans WHERE session_id = ?")
+            .bind(session_id)
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| "failed to load session git scan")?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(GitScanStatus {
+            status: row.try_get("status")?,
+            detail: row.try_get("detail")?,
+        }))
+    }
+
+    /// Usage and cost per commit for turns in the range, most expensive first.
+    /// Each turn counts towards the first commit of its session authored at or
+    /// after it; turns after a session's last commit count towards none.
+    pub async fn commit_costs_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<CommitCostRow>> {
+        let query = format!(
+            r#"
+            WITH turn_commits AS (
+                SELECT
+                    t.session_id,
+                    t.total_tokens,
+                    t.cost,
+                    ({next_commit}) AS sha
+                FROM {turn_costs} t
+                WHERE t.timestamp >= ? AND t.timestamp < ? {scope}
+            )
+            SELECT
+                tc.sha,
+                MIN(c.authored_at) AS authored_at,
+                MAX(c.subject) AS subject,
+                MAX(COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, ''))) AS repo,
+                COUNT(DISTINCT tc.session_id) AS sessions,
+                COALESCE(SUM(tc.total_tokens), 0) AS total_tokens,
+                SUM(tc.cost) AS cost
+            FROM turn_commits tc
+            JOIN session_commits c ON c.session_id = tc.session_id AND c.sha = tc.sha
+            JOIN sessions s ON s.session_id = tc.session_id
+            GROUP BY tc.sha
+            ORDER BY COALESCE(SUM(tc.cost), 0.0) DESC, authored_at DESC
+            LIMIT ?
+            "#,
+            next_commit = NEXT_COMMIT_SQL.replace("{column}", "sha"),
+            scope = self.root_scope("t.session_id", "?"),
+            turn_costs = self.turn_costs(),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load commit costs")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(CommitCostRow {
+                sha: row.try_get("sha")?,
+                subject: row
+                    .try_get::<Option<String>, _>("subject")?
+                    .unwrap_or_default(),
+                repo: row.try_get("repo")?,
+                sessions: row.try_get::<i64, _>("sessions")?.max(0) as u64,
+                total_tokens: row.try_get::<i64, _>("total_tokens")?.max(0) as u64,
+                cost: row.try_get("cost")?,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Usage, cost and commits per repo and branch for turns in the range,
+    /// most expensive first. A turn counts towards the branch of the commit it
+    /// is attributed to, or the branch recorded at session start when it led
+    /// to no commit.
+    pub async fn branch_costs_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<BranchCostRow>> {
+        let query = format!(
+            r#"
+            WITH turn_branches AS (
+                SELECT
+                    t.session_id,
+                    t.total_tokens,
+                    t.cost,
+                    COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS repo,
+                    ({next_sha}) AS sha,
+                    COALESCE(({next_branch}), NULLIF(s.repo_branch, '')) AS branch
+                FROM {turn_costs} t
+                JOIN sessions s ON s.session_id = t.session_id
+                WHERE t.timestamp >= ? AND t.timestamp < ? {scope}
+            )
+            SELECT
+                repo,
+                branch,
+                COUNT(DISTINCT session_id) AS sessions,
+                COUNT(DISTINCT sha) AS commits,
+                COALESCE(SUM(total_tokens), 0) AS total_tokens,
+                SUM(cost) AS cost
+            FROM turn_branches
+            WHERE branch IS NOT NULL
+            GROUP BY repo, branch
+            ORDER BY COALESCE(SUM(cost), 0.0) DESC, repo ASC, branch ASC
+            LIMIT ?
+            "#,
+            next_sha = NEXT_COMMIT_SQL.replace("{column}", "sha"),
+            next_branch = NEXT_COMMIT_SQL.replace("{column}", "branch"),
+            scope = self.root_scope("t.session_id", "?"),
+            turn_costs = self.turn_costs(),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load branch costs")?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in rows {
+            result.push(BranchCostRow {
+                repo: row.try_get("repo")?,
+                branch: row.try_get("branch")?,
+                sessions: row.try_get::<i64, _>("sessions")?.max(0) as u64,
+                commits: row.try_get::<i64, _>("commits")?.max(0) as u64,
+                total_tokens: row.try_get::<i64, _>("total_tokens")?.max(0) as u64,
+                cost: row.try_get("cost")?,
+            });
+        }
+        Ok(result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_tool_call_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        timestamp: DateTime<Utc>,
+        tool_name: &str,
+        call_id: Option<&str>,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_session_stub_tx(tx, session_id, timestamp)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO session_tool_calls (session_id, timestamp, tool_name, call_id, detail)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(session_id)
+        .bind(timestamp.to_rfc3339())
+        .bind(tool_name)
+        .bind(call_id)
+        .bind(detail)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to insert session tool call")?;
+        Ok(())
+    }
+
+    /// Fills in the outcome of an earlier tool call. Fields that are `None`
+    /// keep the value from a previous output event of the same call.
+    pub async fn record_tool_outcome_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        outcome: &ToolOutcome<'_>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE session_tool_calls
+            SET exit_code = COALESCE(?, exit_code),
+                success = COALESCE(?, success),
+                duration_ms = COALESCE(?, duration_ms),
+                output_bytes = COALESCE(?, output_bytes)
+            WHERE session_id = ? AND call_id = ?
+            "#,
+        )
+        .bind(outcome.exit_code)
+        .bind(outcome.success)
+        .bind(outcome.duration_ms.map(|value| value as i64))
+        .bind(outcome.output_bytes.map(|value| value as i64))
+        .bind(session_id)
+        .bind(outcome.call_id)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to record tool call outcome")?;
+        Ok(())
+    }
+
+    pub async fn record_rate_limits_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        snapshot: &RateLimitSnapshot,
+    ) -> Result<()> {
+        let primary = snapshot.primary.as_ref();
+        let secondary = snapshot.secondary.as_ref();
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO rate_limit_snapshots (
+                session_id, timestamp,
+                primary_used_percent, primary_window_minutes, primary_resets_at,
+                secondary_used_percent, secondary_window_minutes, secondary_resets_at,
+                plan_type
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&snapshot.session_id)
+        .bind(snapshot.timestamp.to_rfc3339())
+        .bind(primary.map(|window| window.used_percent))
+        .bind(primary.and_then(|window| window.window_minutes.and_then(|v| i64::try_from(v).ok())))
+        .bind(primary.and_then(|window| window.resets_at.map(|ts| ts.to_rfc3339())))
+        .bind(secondary.map(|window| window.used_percent))
+        .bind(
+            secondary.and_then(|window| window.window_minutes.and_then(|v| i64::try_from(v).ok())),
+        )
+        .bind(secondary.and_then(|window| window.resets_at.map(|ts| ts.to_rfc3339())))
+        .bind(snapshot.plan_type.as_deref())
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to insert rate limit snapshot")?;
+        Ok(())
     }
 
-    pub async fn session_tool_counts(&self, session_id: &str) -> Result<Vec<ToolCountRow>> {
+    /// Rate limit snapshots from any session since `since`, oldest first.
+    pub async fn rate_limit_snapshots_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<RateLimitSnapshot>> {
         let rows = sqlx::query(
             r#"
-            SELECT tool_name, COUNT(*) AS call_count
-            FROM session_tool_calls
-            WHERE session_id = ?
-            GROUP BY tool_name
-            ORDER BY call_count DESC, tool_name ASC
+            SELECT
+                session_id, timestamp,
+                primary_used_percent, primary_window_minutes, primary_resets_at,
+                secondary_used_percent, secondary_window_minutes, secondary_resets_at,
+                plan_type
+            FROM rate_limit_snapshots
+            WHERE timestamp >= ?
+            ORDER BY timestamp ASC, id ASC
             "#,
         )
-        .bind(session_id)
+        .bind(since.to_rfc3339())
         .fetch_all(&*self.pool)
         .await
-        .with_context(|| "failed to load session tool counts")?;
+        .with_context(|| "failed to load rate limit snapshots")?;
 
-        let mut result = Vec::with_capacity(rows.len());
+        let mut snapshots = Vec::with_capacity(rows.len());
         for row in rows {
-            result.push(ToolCountRow {
-                tool: row.try_get::<String, _>("tool_name")?,
-                count: row.try_get::<i64, _>("call_count").unwrap_or(0) as u64,
-            });
+            if let Some(snapshot) = rate_limit_snapshot_from_row(&row)? {
+                snapshots.push(snapshot);
+            }
         }
-        Ok(result)
+        Ok(snapshots)
     }
 
-    pub async fn record_tool_call_tx(
-        &self,
-        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-        session_id: &str,
-        timestamp: DateTime<Utc>,
-        tool_name: &str,
-    ) -> Result<()> {
-        self.ensure_session_stub_tx(tx, session_id, timestamp).await?;
-        sqlx::query(
+    /// Most recent rate limit snapshot across all sessions.
+    pub async fn latest_rate_limits(&self) -> Result<Option<RateLimitSnapshot>> {
+        let row = sqlx::query(
             r#"
-            INSERT INTO session_tool_calls (session_id, timestamp, tool_name)
-            VALUES (?, ?, ?)
+            SELECT
+                session_id, timestamp,
+                primary_used_percent, primary_window_minutes, primary_resets_at,
+                secondary_used_percent, secondary_window_minutes, secondary_resets_at,
+                plan_type
+            FROM rate_limit_snapshots
+            ORDER BY timestamp DESC, id DESC
+            LIMIT 1
             "#,
         )
-        .bind(session_id)
-        .bind(timestamp.to_rfc3339())
-        .bind(tool_name)
-        .execute(&mut **tx)
+        .fetch_optional(&*self.pool)
         .await
-        .with_context(|| "failed to insert session tool call")?;
-        Ok(())
+        .with_context(|| "failed to load latest rate limit snapshot")?;
+        match row {
+            Some(row) => rate_limit_snapshot_from_row(&row),
+            None => Ok(None),
+        }
     }
 
     pub async fn totals_between_timestamps(
@@ -1401,33 +3144,39 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut totals = TotalsAccumulator::default();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let row = sqlx::query(
-                    r#"
-                    SELECT
-                        COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
-                        COALESCE(SUM(cached_prompt_tokens), 0) as cached_prompt_tokens,
-                        COALESCE(SUM(completion_tokens), 0) as completion_tokens,
-                        COALESCE(SUM(total_tokens), 0) as total_tokens,
-                        COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-                        COALESCE(SUM(cost_usd), 0.0) as cost_usd,
-                        COALESCE(SUM(missing_price), 0) as missing_price
-                    FROM daily_stats_costs
-                    WHERE date >= ? AND date < ?
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT
+                    COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
+                    COALESCE(SUM(cached_prompt_tokens), 0) as cached_prompt_tokens,
+                    COALESCE(SUM(completion_tokens), 0) as completion_tokens,
+                    COALESCE(SUM(total_tokens), 0) as total_tokens,
+                    COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
+                    COALESCE(SUM(cost), 0.0) as cost,
+                    COALESCE(SUM(missing_price), 0) as missing_price
+                FROM {}
+                WHERE date >= ? AND date < ? {}
+                "#,
+                self.daily_costs(self.daily_source("daily_stats_costs", "session_daily_costs")),
+                self.root_scope("session_id", "?"),
+            );
+            let row = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_one(&*self.pool)
                 .await
                 .with_context(|| "failed to load daily totals between timestamps")?;
-                totals.add(totals_from_row(&row));
-            }
+            totals.add(totals_from_row(&row));
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let row = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT
                     COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
@@ -1435,17 +3184,23 @@ impl Storage {
                     COALESCE(SUM(completion_tokens), 0) as completion_tokens,
                     COALESCE(SUM(total_tokens), 0) as total_tokens,
                     COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-                    COALESCE(SUM(cost_usd), 0.0) as cost_usd,
+                    COALESCE(SUM(cost), 0.0) as cost,
                     COALESCE(SUM(missing_price), 0) as missing_price
-                FROM session_turn_costs
-                WHERE timestamp >= ? AND timestamp < ?
+                FROM {turn_costs}
+                WHERE timestamp >= ? AND timestamp < ? {}
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_one(&*self.pool)
-            .await
-            .with_context(|| "failed to load partial totals between timestamps")?;
+                self.root_scope("session_id", "?"),
+                turn_costs = self.turn_costs(),
+            );
+            let row = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_one(&*self.pool)
+                .await
+                .with_context(|| "failed to load partial totals between timestamps")?;
             totals.add(totals_from_row(&row));
         }
 
@@ -1455,10 +3210,10 @@ impl Storage {
             completion_tokens: totals.completion_tokens.max(0) as u64,
             total_tokens: totals.total_tokens.max(0) as u64,
             reasoning_tokens: totals.reasoning_tokens.max(0) as u64,
-            cost_usd: if totals.missing_price > 0 {
+            cost: if totals.missing_price > 0 {
                 None
             } else {
-                Some(totals.cost_usd)
+                Some(totals.cost)
             },
         })
     }
@@ -1471,41 +3226,51 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut session_ids: HashSet<String> = HashSet::new();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let rows = sqlx::query(
-                    r#"
-                    SELECT DISTINCT session_id
-                    FROM session_daily_stats
-                    WHERE date >= ? AND date < ?
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT DISTINCT session_id
+                FROM session_daily_stats
+                WHERE date >= ? AND date < ? {}
+                "#,
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to load session ids from daily stats")?;
-                for row in rows {
-                    if let Ok(session_id) = row.try_get::<String, _>("session_id") {
-                        session_ids.insert(session_id);
-                    }
+            for row in rows {
+                if let Ok(session_id) = row.try_get::<String, _>("session_id") {
+                    session_ids.insert(session_id);
                 }
             }
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let rows = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT DISTINCT session_id
                 FROM session_turns
-                WHERE timestamp >= ? AND timestamp < ?
+                WHERE timestamp >= ? AND timestamp < ? {}
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load session ids from partial turns")?;
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load session ids from partial turns")?;
             for row in rows {
                 if let Ok(session_id) = row.try_get::<String, _>("session_id") {
                     session_ids.insert(session_id);
@@ -1513,18 +3278,24 @@ impl Storage {
             }
         }
 
-        let message_count: i64 = sqlx::query_scalar(
+        let query = format!(
             r#"
             SELECT COUNT(*)
             FROM session_messages
-            WHERE timestamp >= ? AND timestamp < ?
+            WHERE timestamp >= ? AND timestamp < ? {}
             "#,
-        )
-        .bind(start.to_rfc3339())
-        .bind(end.to_rfc3339())
-        .fetch_one(&*self.pool)
-        .await
-        .with_context(|| "failed to load message counts between timestamps")?;
+            self.root_scope("session_id", "?"),
+        );
+        let mut message_count = sqlx::query_scalar::<_, i64>(&query)
+            .bind(start.to_rfc3339())
+            .bind(end.to_rfc3339());
+        if let Some(label) = self.root_label() {
+            message_count = message_count.bind(label);
+        }
+        let message_count = message_count
+            .fetch_one(&*self.pool)
+            .await
+            .with_context(|| "failed to load message counts between timestamps")?;
 
         Ok(PeriodCounts {
             session_count: session_ids.len() as u64,
@@ -1540,50 +3311,61 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut totals_map: HashMap<NaiveDate, u64> = HashMap::new();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let rows = sqlx::query(
-                    r#"
-                    SELECT date AS day,
-                           COALESCE(SUM(total_tokens), 0) AS total_tokens
-                    FROM daily_stats
-                    WHERE date >= ? AND date < ?
-                    GROUP BY day
-                    ORDER BY day ASC
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT date AS day,
+                       COALESCE(SUM(total_tokens), 0) AS total_tokens
+                FROM {}
+                WHERE date >= ? AND date < ? {}
+                GROUP BY day
+                ORDER BY day ASC
+                "#,
+                self.daily_source("daily_stats", "session_daily_stats"),
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to load daily token totals")?;
 
-                for row in rows {
-                    let day_str: String = row.try_get("day")?;
-                    let date = NaiveDate::parse_from_str(&day_str, "%Y-%m-%d")
-                        .with_context(|| format!("invalid day in token totals: {day_str}"))?;
-                    let total = row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
-                    totals_map.insert(date, total);
-                }
+            for row in rows {
+                let day_str: String = row.try_get("day")?;
+                let date = NaiveDate::parse_from_str(&day_str, "%Y-%m-%d")
+                    .with_context(|| format!("invalid day in token totals: {day_str}"))?;
+                let total = row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
+                totals_map.insert(date, total);
             }
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let rows = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT
                     strftime('%Y-%m-%d', timestamp, 'localtime') AS day,
                     COALESCE(SUM(total_tokens), 0) AS total_tokens
                 FROM session_turns
-                WHERE timestamp >= ? AND timestamp < ?
+                WHERE timestamp >= ? AND timestamp < ? {}
                 GROUP BY day
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load partial token totals by day")?;
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load partial token totals by day")?;
 
             for row in rows {
                 let day_str: String = row.try_get("day")?;
@@ -1615,72 +3397,84 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut totals_map: HashMap<String, ModelCostTotal> = HashMap::new();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let rows = sqlx::query(
-                    r#"
-                    SELECT
-                        model,
-                        COALESCE(SUM(total_tokens), 0) AS total_tokens,
-                        COALESCE(SUM(cost_usd), 0.0) AS cost_usd
-                    FROM daily_stats_costs
-                    WHERE date >= ? AND date < ?
-                    GROUP BY model
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT
+                    model,
+                    COALESCE(SUM(total_tokens), 0) AS total_tokens,
+                    COALESCE(SUM(cost), 0.0) AS cost
+                FROM {}
+                WHERE date >= ? AND date < ? {}
+                GROUP BY model
+                "#,
+                self.daily_costs(self.daily_source("daily_stats_costs", "session_daily_costs")),
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to load model usage from daily stats")?;
 
-                for row in rows {
-                    let model: String = row.try_get("model")?;
-                    let entry = totals_map.entry(model.clone()).or_insert(ModelCostTotal {
-                        model,
-                        total_tokens: 0,
-                        cost_usd: 0.0,
-                    });
-                    entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
-                    entry.cost_usd += row.try_get::<f64, _>("cost_usd").unwrap_or(0.0);
-                }
+            for row in rows {
+                let model: String = row.try_get("model")?;
+                let entry = totals_map.entry(model.clone()).or_insert(ModelCostTotal {
+                    model,
+                    total_tokens: 0,
+                    cost: 0.0,
+                });
+                entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
+                entry.cost += row.try_get::<f64, _>("cost").unwrap_or(0.0);
             }
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let rows = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT
                     model,
                     COALESCE(SUM(total_tokens), 0) AS total_tokens,
-                    COALESCE(SUM(cost_usd), 0.0) AS cost_usd
-                FROM session_turn_costs
-                WHERE timestamp >= ? AND timestamp < ?
+                    COALESCE(SUM(cost), 0.0) AS cost
+                FROM {turn_costs}
+                WHERE timestamp >= ? AND timestamp < ? {}
                 GROUP BY model
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load model usage from partial turns")?;
+                self.root_scope("session_id", "?"),
+                turn_costs = self.turn_costs(),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load model usage from partial turns")?;
 
             for row in rows {
                 let model: String = row.try_get("model")?;
                 let entry = totals_map.entry(model.clone()).or_insert(ModelCostTotal {
                     model,
                     total_tokens: 0,
-                    cost_usd: 0.0,
+                    cost: 0.0,
                 });
                 entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
-                entry.cost_usd += row.try_get::<f64, _>("cost_usd").unwrap_or(0.0);
+                entry.cost += row.try_get::<f64, _>("cost").unwrap_or(0.0);
             }
         }
 
         let mut totals: Vec<ModelCostTotal> = totals_map.into_values().collect();
         totals.sort_by(|a, b| {
-            b.cost_usd
-                .partial_cmp(&a.cost_usd)
+            b.cost
+                .partial_cmp(&a.cost)
                 .unwrap_or(std::cmp::Ordering::Equal)
                 .then_with(|| b.total_tokens.cmp(&a.total_tokens))
         });
@@ -1700,83 +3494,222 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut totals_map: HashMap<String, RepoCostTotal> = HashMap::new();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let rows = sqlx::query(
-                    r#"
-                    SELECT
-                        COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS repo,
-                        COALESCE(SUM(d.total_tokens), 0) AS total_tokens,
-                        COALESCE(SUM(d.cost_usd), 0.0) AS cost_usd
-                    FROM session_daily_costs d
-                    JOIN sessions s ON s.session_id = d.session_id
-                    WHERE d.date >= ? AND d.date < ?
-                      AND COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) IS NOT NULL
-                    GROUP BY repo
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT
+                    COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS repo,
+                    COALESCE(SUM(d.total_tokens), 0) AS total_tokens,
+                    COALESCE(SUM(d.cost), 0.0) AS cost
+                FROM {daily_costs} d
+                JOIN sessions s ON s.session_id = d.session_id
+                WHERE d.date >= ? AND d.date < ?
+                  AND COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) IS NOT NULL
+                  {}
+                GROUP BY repo
+                "#,
+                self.root_scope("s.session_id", "?"),
+                daily_costs = self.daily_costs("session_daily_costs"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to load repo usage from daily stats")?;
 
-                for row in rows {
-                    let repo: String = row.try_get("repo")?;
-                    let entry = totals_map.entry(repo.clone()).or_insert(RepoCostTotal {
-                        repo,
-                        total_tokens: 0,
-                        cost_usd: 0.0,
-                    });
-                    entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
-                    entry.cost_usd += row.try_get::<f64, _>("cost_usd").unwrap_or(0.0);
-                }
+            for row in rows {
+                let repo: String = row.try_get("repo")?;
+                let entry = totals_map.entry(repo.clone()).or_insert(RepoCostTotal {
+                    repo,
+                    total_tokens: 0,
+                    cost: 0.0,
+                });
+                entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
+                entry.cost += row.try_get::<f64, _>("cost").unwrap_or(0.0);
             }
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let rows = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT
                     COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS repo,
                     COALESCE(SUM(t.total_tokens), 0) AS total_tokens,
-                    COALESCE(SUM(t.cost_usd), 0.0) AS cost_usd
-                FROM session_turn_costs t
+                    COALESCE(SUM(t.cost), 0.0) AS cost
+                FROM {turn_costs} t
                 JOIN sessions s ON s.session_id = t.session_id
                 WHERE t.timestamp >= ? AND t.timestamp < ?
                   AND COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) IS NOT NULL
+                  {}
                 GROUP BY repo
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load repo usage from partial turns")?;
+                self.root_scope("s.session_id", "?"),
+                turn_costs = self.turn_costs(),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load repo usage from partial turns")?;
 
             for row in rows {
                 let repo: String = row.try_get("repo")?;
                 let entry = totals_map.entry(repo.clone()).or_insert(RepoCostTotal {
                     repo,
                     total_tokens: 0,
-                    cost_usd: 0.0,
+                    cost: 0.0,
                 });
                 entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
-                entry.cost_usd += row.try_get::<f64, _>("cost_usd").unwrap_or(0.0);
+                entry.cost += row.try_get::<f64, _>("cost").unwrap_or(0.0);
             }
         }
 
         let mut totals: Vec<RepoCostTotal> = totals_map.into_values().collect();
         totals.sort_by(|a, b| {
-            b.cost_usd
-                .partial_cmp(&a.cost_usd)
+            b.cost
+                .partial_cmp(&a.cost)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.total_tokens.cmp(&a.total_tokens))
+        });
+        totals.truncate(limit);
+        Ok(totals)
+    }
+
+    /// Cost per session root label; sessions without a label are grouped
+    /// under an empty label.
+    pub async fn root_usage_by_cost_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<RootCostTotal>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let parts = split_range_local(start, end);
+        let mut totals_map: HashMap<String, RootCostTotal> = HashMap::new();
+        let scope = self.root_scope("s.session_id", "?");
+        let mut queries = Vec::new();
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            queries.push((
+                format!(
+                    r#"
+                    SELECT
+                        COALESCE(s.root_label, '') AS label,
+                        COALESCE(SUM(d.total_tokens), 0) AS total_tokens,
+                        COALESCE(SUM(d.cost), 0.0) AS cost
+                    FROM {daily_costs} d
+                    JOIN sessions s ON s.session_id = d.session_id
+                    WHERE d.date >= ? AND d.date < ? {scope}
+                    GROUP BY label
+                    "#,
+                    daily_costs = self.daily_costs("session_daily_costs"),
+                ),
+                start_date.to_string(),
+                end_date.to_string(),
+            ));
+        }
+        for (partial_start, partial_end) in parts.partials {
+            queries.push((
+                format!(
+                    r#"
+                    SELECT
+                        COALESCE(s.root_label, '') AS label,
+                        COALESCE(SUM(t.total_tokens), 0) AS total_tokens,
+                        COALESCE(SUM(t.cost), 0.0) AS cost
+                    FROM {turn_costs} t
+                    JOIN sessions s ON s.session_id = t.session_id
+                    WHERE t.timestamp >= ? AND t.timestamp < ? {scope}
+                    GROUP BY label
+                    "#,
+                    turn_costs = self.turn_costs(),
+                ),
+                partial_start.to_rfc3339(),
+                partial_end.to_rfc3339(),
+            ));
+        }
+
+        for (query, from, to) in &queries {
+            let rows = self
+                .bind_root_scope(sqlx::query(query).bind(from).bind(to))
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load usage by session root")?;
+            for row in rows {
+                let label: String = row.try_get("label")?;
+                let entry = totals_map.entry(label.clone()).or_insert(RootCostTotal {
+                    label,
+                    total_tokens: 0,
+                    cost: 0.0,
+                });
+                entry.total_tokens += row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64;
+                entry.cost += row.try_get::<f64, _>("cost").unwrap_or(0.0);
+            }
+        }
+
+        let mut totals: Vec<RootCostTotal> = totals_map.into_values().collect();
+        totals.sort_by(|a, b| {
+            b.cost
+                .partial_cmp(&a.cost)
                 .unwrap_or(std::cmp::Ordering::Equal)
                 .then_with(|| b.total_tokens.cmp(&a.total_tokens))
+                .then_with(|| a.label.cmp(&b.label))
         });
         totals.truncate(limit);
         Ok(totals)
     }
 
+    /// Distinct session root labels, for cycling the TUI filter.
+    pub async fn session_root_labels(&self) -> Result<Vec<String>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT root_label
+            FROM sessions
+            WHERE root_label IS NOT NULL
+            ORDER BY root_label ASC
+            "#,
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load session root labels")
+    }
+
+    /// Sets the root label of already ingested sessions, so renamed labels and
+    /// databases from before labels existed match the configured roots.
+    pub async fn apply_session_root_labels(&self, labels: &[(String, String)]) -> Result<()> {
+        let mut tx = self.begin_tx().await?;
+        for (session_id, label) in labels {
+            sqlx::query(
+                r#"
+                UPDATE sessions
+                SET root_label = ?
+                WHERE session_id = ?
+                  AND root_label IS NOT ?
+                "#,
+            )
+            .bind(label)
+            .bind(session_id)
+            .bind(label)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to update session root label")?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
     pub async fn project_count_between(
         &self,
         start: DateTime<Utc>,
@@ -1785,46 +3718,58 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut projects: HashSet<String> = HashSet::new();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let rows = sqlx::query(
-                    r#"
-                    SELECT DISTINCT COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS project
-                    FROM sessions s
-                    JOIN session_daily_stats d ON d.session_id = s.session_id
-                    WHERE d.date >= ? AND d.date < ?
-                      AND COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) IS NOT NULL
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT DISTINCT COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS project
+                FROM sessions s
+                JOIN session_daily_stats d ON d.session_id = s.session_id
+                WHERE d.date >= ? AND d.date < ?
+                  AND COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) IS NOT NULL
+                  {}
+                "#,
+                self.root_scope("s.session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to load projects from daily stats")?;
 
-                for row in rows {
-                    if let Ok(project) = row.try_get::<String, _>("project") {
-                        projects.insert(project);
-                    }
+            for row in rows {
+                if let Ok(project) = row.try_get::<String, _>("project") {
+                    projects.insert(project);
                 }
             }
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let rows = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT DISTINCT COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) AS project
                 FROM sessions s
                 JOIN session_turns t ON t.session_id = s.session_id
                 WHERE t.timestamp >= ? AND t.timestamp < ?
                   AND COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, '')) IS NOT NULL
+                  {}
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load projects from partial turns")?;
+                self.root_scope("s.session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load projects from partial turns")?;
 
             for row in rows {
                 if let Ok(project) = row.try_get::<String, _>("project") {
@@ -1884,6 +3829,7 @@ impl Storage {
         end: DateTime<Utc>,
         bucket_expr: &str,
     ) -> Result<Vec<BucketTotals>> {
+        let scope = self.root_scope("session_id", "?");
         if bucket_expr.contains("%H") {
             let query = format!(
                 r#"
@@ -1894,18 +3840,22 @@ impl Storage {
                     COALESCE(SUM(completion_tokens), 0) as completion_tokens,
                     COALESCE(SUM(total_tokens), 0) as total_tokens,
                     COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-                    COALESCE(SUM(cost_usd), 0.0) as cost_usd,
+                    COALESCE(SUM(cost), 0.0) as cost,
                     COALESCE(SUM(missing_price), 0) as missing_price,
                     COUNT(DISTINCT session_id) as session_count
-                FROM session_turn_costs
-                WHERE timestamp >= ? AND timestamp < ?
+                FROM {turn_costs}
+                WHERE timestamp >= ? AND timestamp < ? {scope}
                 GROUP BY bucket
                 ORDER BY bucket ASC
-                "#
+                "#,
+                turn_costs = self.turn_costs(),
             );
-            let rows = sqlx::query(&query)
-                .bind(start.to_rfc3339())
-                .bind(end.to_rfc3339())
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start.to_rfc3339())
+                        .bind(end.to_rfc3339()),
+                )
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to aggregate usage by bucket")?;
@@ -1919,14 +3869,12 @@ impl Storage {
                         cached_prompt_tokens: row
                             .try_get::<i64, _>("cached_prompt_tokens")
                             .unwrap_or(0) as u64,
-                        completion_tokens: row
-                            .try_get::<i64, _>("completion_tokens")
-                            .unwrap_or(0) as u64,
+                        completion_tokens: row.try_get::<i64, _>("completion_tokens").unwrap_or(0)
+                            as u64,
                         total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
-                        reasoning_tokens: row
-                            .try_get::<i64, _>("reasoning_tokens")
-                            .unwrap_or(0) as u64,
-                        cost_usd: cost_from_row(&row),
+                        reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0)
+                            as u64,
+                        cost: cost_from_row(&row),
                     },
                     session_count: row.try_get::<i64, _>("session_count").unwrap_or(0) as u64,
                 });
@@ -1951,64 +3899,72 @@ impl Storage {
             "strftime('%Y', date)".to_string()
         };
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let query = format!(
-                    r#"
-                    SELECT
-                        {daily_bucket_expr} AS bucket,
-                        COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
-                        COALESCE(SUM(cached_prompt_tokens), 0) as cached_prompt_tokens,
-                        COALESCE(SUM(completion_tokens), 0) as completion_tokens,
-                        COALESCE(SUM(total_tokens), 0) as total_tokens,
-                        COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-                        COALESCE(SUM(cost_usd), 0.0) as cost_usd,
-                        COALESCE(SUM(missing_price), 0) as missing_price
-                    FROM daily_stats_costs
-                    WHERE date >= ? AND date < ?
-                    GROUP BY bucket
-                    "#
-                );
-                let rows = sqlx::query(&query)
-                    .bind(start_date.to_string())
-                    .bind(end_date.to_string())
-                    .fetch_all(&*self.pool)
-                    .await
-                    .with_context(|| "failed to aggregate daily usage by bucket")?;
-
-                for row in rows {
-                    let bucket: String = row.try_get("bucket")?;
-                    let entry = bucket_map.entry(bucket).or_insert(BucketAgg {
-                        totals: TotalsAccumulator::default(),
-                        sessions: HashSet::new(),
-                    });
-                    entry.totals.add(totals_from_row(&row));
-                }
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let daily_source =
+                self.daily_costs(self.daily_source("daily_stats_costs", "session_daily_costs"));
+            let query = format!(
+                r#"
+                SELECT
+                    {daily_bucket_expr} AS bucket,
+                    COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
+                    COALESCE(SUM(cached_prompt_tokens), 0) as cached_prompt_tokens,
+                    COALESCE(SUM(completion_tokens), 0) as completion_tokens,
+                    COALESCE(SUM(total_tokens), 0) as total_tokens,
+                    COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
+                    COALESCE(SUM(cost), 0.0) as cost,
+                    COALESCE(SUM(missing_price), 0) as missing_price
+                FROM {daily_source}
+                WHERE date >= ? AND date < ? {scope}
+                GROUP BY bucket
+                "#
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to aggregate daily usage by bucket")?;
 
-                let query = format!(
-                    r#"
-                    SELECT {daily_bucket_expr} AS bucket,
-                           session_id
-                    FROM session_daily_stats
-                    WHERE date >= ? AND date < ?
-                    GROUP BY bucket, session_id
-                    "#
-                );
-                let rows = sqlx::query(&query)
-                    .bind(start_date.to_string())
-                    .bind(end_date.to_string())
-                    .fetch_all(&*self.pool)
-                    .await
-                    .with_context(|| "failed to aggregate daily session counts")?;
-                for row in rows {
-                    let bucket: String = row.try_get("bucket")?;
-                    let session_id: String = row.try_get("session_id")?;
-                    let entry = bucket_map.entry(bucket).or_insert(BucketAgg {
-                        totals: TotalsAccumulator::default(),
-                        sessions: HashSet::new(),
-                    });
-                    entry.sessions.insert(session_id);
-                }
+            for row in rows {
+                let bucket: String = row.try_get("bucket")?;
+                let entry = bucket_map.entry(bucket).or_insert(BucketAgg {
+                    totals: TotalsAccumulator::default(),
+                    sessions: HashSet::new(),
+                });
+                entry.totals.add(totals_from_row(&row));
+            }
+
+            let query = format!(
+                r#"
+                SELECT {daily_bucket_expr} AS bucket,
+                       session_id
+                FROM session_daily_stats
+                WHERE date >= ? AND date < ? {scope}
+                GROUP BY bucket, session_id
+                "#
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to aggregate daily session counts")?;
+            for row in rows {
+                let bucket: String = row.try_get("bucket")?;
+                let session_id: String = row.try_get("session_id")?;
+                let entry = bucket_map.entry(bucket).or_insert(BucketAgg {
+                    totals: TotalsAccumulator::default(),
+                    sessions: HashSet::new(),
+                });
+                entry.sessions.insert(session_id);
             }
         }
 
@@ -2022,16 +3978,20 @@ impl Storage {
                     COALESCE(SUM(completion_tokens), 0) as completion_tokens,
                     COALESCE(SUM(total_tokens), 0) as total_tokens,
                     COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-                    COALESCE(SUM(cost_usd), 0.0) as cost_usd,
+                    COALESCE(SUM(cost), 0.0) as cost,
                     COALESCE(SUM(missing_price), 0) as missing_price
-                FROM session_turn_costs
-                WHERE timestamp >= ? AND timestamp < ?
+                FROM {turn_costs}
+                WHERE timestamp >= ? AND timestamp < ? {scope}
                 GROUP BY bucket
-                "#
+                "#,
+                turn_costs = self.turn_costs(),
             );
-            let rows = sqlx::query(&query)
-                .bind(partial_start.to_rfc3339())
-                .bind(partial_end.to_rfc3339())
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to aggregate partial usage by bucket")?;
@@ -2050,13 +4010,16 @@ impl Storage {
                 SELECT {bucket_expr} AS bucket,
                        session_id
                 FROM session_turns
-                WHERE timestamp >= ? AND timestamp < ?
+                WHERE timestamp >= ? AND timestamp < ? {scope}
                 GROUP BY bucket, session_id
                 "#
             );
-            let rows = sqlx::query(&query)
-                .bind(partial_start.to_rfc3339())
-                .bind(partial_end.to_rfc3339())
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to aggregate partial session counts")?;
@@ -2081,10 +4044,10 @@ impl Storage {
                     completion_tokens: agg.totals.completion_tokens.max(0) as u64,
                     total_tokens: agg.totals.total_tokens.max(0) as u64,
                     reasoning_tokens: agg.totals.reasoning_tokens.max(0) as u64,
-                    cost_usd: if agg.totals.missing_price > 0 {
+                    cost: if agg.totals.missing_price > 0 {
                         None
                     } else {
-                        Some(agg.totals.cost_usd)
+                        Some(agg.totals.cost)
                     },
                 },
                 session_count: agg.sessions.len() as u64,
@@ -2099,40 +4062,53 @@ impl Storage {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Option<TopModelShare>> {
-        let total_row = sqlx::query(
+        let scope = self.root_scope("session_id", "?");
+        let query = format!(
             r#"
-            SELECT COALESCE(SUM(cost_usd), 0.0) as total_cost
-            FROM session_turn_costs
+            SELECT COALESCE(SUM(cost), 0.0) as total_cost
+            FROM {turn_costs}
             WHERE timestamp >= ? AND timestamp < ?
-              AND cost_usd IS NOT NULL
+              AND cost IS NOT NULL
+              {scope}
             "#,
-        )
-        .bind(start.to_rfc3339())
-        .bind(end.to_rfc3339())
-        .fetch_one(&*self.pool)
-        .await
-        .with_context(|| "failed to load total cost for top model")?;
+            turn_costs = self.turn_costs(),
+        );
+        let total_row = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .fetch_one(&*self.pool)
+            .await
+            .with_context(|| "failed to load total cost for top model")?;
         let total_cost = total_row.try_get::<f64, _>("total_cost").unwrap_or(0.0);
         if total_cost <= 0.0 {
             return Ok(None);
         }
 
-        let row = sqlx::query(
+        let query = format!(
             r#"
-            SELECT model, reasoning_effort, COALESCE(SUM(cost_usd), 0.0) as cost
-            FROM session_turn_costs
+            SELECT model, reasoning_effort, COALESCE(SUM(cost), 0.0) as cost
+            FROM {turn_costs}
             WHERE timestamp >= ? AND timestamp < ?
-              AND cost_usd IS NOT NULL
+              AND cost IS NOT NULL
+              {scope}
             GROUP BY model, reasoning_effort
             ORDER BY cost DESC
             LIMIT 1
             "#,
-        )
-        .bind(start.to_rfc3339())
-        .bind(end.to_rfc3339())
-        .fetch_optional(&*self.pool)
-        .await
-        .with_context(|| "failed to load top model by cost")?;
+            turn_costs = self.turn_costs(),
+        );
+        let row = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339()),
+            )
+            .fetch_optional(&*self.pool)
+            .await
+            .with_context(|| "failed to load top model by cost")?;
 
         let Some(row) = row else {
             return Ok(None);
@@ -2163,41 +4139,51 @@ impl Storage {
         let parts = split_range_local(start, end);
         let mut session_ids: HashSet<String> = HashSet::new();
 
-        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end) {
-            if start_date < end_date {
-                let rows = sqlx::query(
-                    r#"
-                    SELECT DISTINCT session_id
-                    FROM session_daily_stats
-                    WHERE date >= ? AND date < ?
-                    "#,
+        if let (Some(start_date), Some(end_date)) = (parts.full_start, parts.full_end)
+            && start_date < end_date
+        {
+            let query = format!(
+                r#"
+                SELECT DISTINCT session_id
+                FROM session_daily_stats
+                WHERE date >= ? AND date < ? {}
+                "#,
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(start_date.to_string())
+                        .bind(end_date.to_string()),
                 )
-                .bind(start_date.to_string())
-                .bind(end_date.to_string())
                 .fetch_all(&*self.pool)
                 .await
                 .with_context(|| "failed to count sessions from daily stats")?;
-                for row in rows {
-                    if let Ok(session_id) = row.try_get::<String, _>("session_id") {
-                        session_ids.insert(session_id);
-                    }
+            for row in rows {
+                if let Ok(session_id) = row.try_get::<String, _>("session_id") {
+                    session_ids.insert(session_id);
                 }
             }
         }
 
         for (partial_start, partial_end) in parts.partials {
-            let rows = sqlx::query(
+            let query = format!(
                 r#"
                 SELECT DISTINCT session_id
                 FROM session_turns
-                WHERE timestamp >= ? AND timestamp < ?
+                WHERE timestamp >= ? AND timestamp < ? {}
                 "#,
-            )
-            .bind(partial_start.to_rfc3339())
-            .bind(partial_end.to_rfc3339())
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to count sessions from partial turns")?;
+                self.root_scope("session_id", "?"),
+            );
+            let rows = self
+                .bind_root_scope(
+                    sqlx::query(&query)
+                        .bind(partial_start.to_rfc3339())
+                        .bind(partial_end.to_rfc3339()),
+                )
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to count sessions from partial turns")?;
             for row in rows {
                 if let Ok(session_id) = row.try_get::<String, _>("session_id") {
                     session_ids.insert(session_id);
@@ -2208,7 +4194,7 @@ impl Storage {
         Ok(session_ids.len())
     }
 
-    pub async fn sessions_page_by_recent_between(
+    pub async fn sessions_page_by_cost_between(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
@@ -2219,20 +4205,24 @@ impl Storage {
             return Ok(Vec::new());
         }
 
-        let rows = sqlx::query(
+        let query = format!(
             r#"
-            WITH period_sessions AS (
-                SELECT session_id, MAX(timestamp) AS last_in_range
-                FROM session_turn_costs
-                WHERE timestamp BETWEEN ?1 AND ?2
+            WITH period_stats AS (
+                SELECT
+                    session_id,
+                    COALESCE(SUM(cost), 0.0) AS period_cost,
+                    SUM(prompt_tokens) AS period_prompt,
+                    COALESCE(SUM(missing_price), 0) AS missing_price
+                FROM {turn_costs}
+                WHERE timestamp BETWEEN ?1 AND ?2 {}
                 GROUP BY session_id
             ),
             session_costs AS (
                 SELECT
                     session_id,
-                    COALESCE(SUM(cost_usd), 0.0) AS cost_usd,
+                    COALESCE(SUM(cost), 0.0) AS cost,
                     COALESCE(SUM(missing_price), 0) AS missing_price
-                FROM session_daily_costs
+                FROM {daily_costs}
                 GROUP BY session_id
             )
             SELECT
@@ -2251,30 +4241,82 @@ impl Storage {
                 s.user_messages,
                 s.title,
                 s.last_summary,
-                session_costs.cost_usd,
-                session_costs.missing_price
-            FROM period_sessions p
-            JOIN sessions s ON s.session_id = p.session_id
+                s.root_label,
+                session_costs.cost,
+                session_costs.missing_price,
+                period_stats.period_cost,
+                period_stats.period_prompt
+            FROM period_stats
+            JOIN sessions s ON s.session_id = period_stats.session_id
             LEFT JOIN session_costs ON session_costs.session_id = s.session_id
-            ORDER BY p.last_in_range DESC
+            ORDER BY COALESCE(period_stats.period_cost, 0) DESC,
+                     COALESCE(period_stats.period_prompt, 0) DESC
             LIMIT ?3 OFFSET ?4
             "#,
-        )
-        .bind(start.to_rfc3339())
-        .bind(end.to_rfc3339())
-        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
-        .bind(i64::try_from(offset).unwrap_or(0))
-        .fetch_all(&*self.pool)
-        .await
-        .with_context(|| "failed to load sessions by recent")?;
+            self.root_scope("session_id", "?5"),
+            turn_costs = self.turn_costs(),
+            daily_costs = self.daily_costs("session_daily_costs"),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339())
+                    .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                    .bind(i64::try_from(offset).unwrap_or(0)),
+            )
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load sessions by cost")?;
 
-        Ok(session_aggregates_from_rows(rows)?)
+        session_aggregates_from_rows(rows)
     }
 
-    pub async fn sessions_page_by_cost_between(
+    /// Number of rows the session tree shows for the range: one per root
+    /// session with usage in it, plus the descendants of expanded roots.
+    pub async fn session_tree_rows_count_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        expanded: &[String],
+    ) -> Result<usize> {
+        let query = format!(
+            r#"
+            WITH period_roots AS (
+                SELECT DISTINCT tr.root_session_id AS root_id
+                FROM session_turns t
+                JOIN session_tree tr ON tr.session_id = t.session_id
+                WHERE t.timestamp BETWEEN ?1 AND ?2 {}
+            )
+            SELECT COUNT(*) AS rows
+            FROM session_tree tr
+            JOIN period_roots p ON p.root_id = tr.root_session_id
+            WHERE tr.depth = 0 OR tr.root_session_id IN (SELECT value FROM json_each(?3))
+            "#,
+            self.root_scope("t.session_id", "?4"),
+        );
+        let row = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339())
+                    .bind(serde_json::to_string(expanded)?),
+            )
+            .fetch_one(&*self.pool)
+            .await
+            .with_context(|| "failed to count session tree rows")?;
+        Ok(row.try_get::<i64, _>("rows")?.max(0) as usize)
+    }
+
+    /// A page of root sessions with usage in the range, each followed by its
+    /// subagent sessions when expanded. Roots carry the cost and tokens of
+    /// their whole tree in [`SessionAggregate::tree`].
+    pub async fn session_tree_page_between(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
+        order: SessionTreeOrder,
+        expanded: &[String],
         offset: usize,
         limit: usize,
     ) -> Result<Vec<SessionAggregate>> {
@@ -2282,25 +4324,49 @@ impl Storage {
             return Ok(Vec::new());
         }
 
-        let rows = sqlx::query(
+        let order_by = match order {
+            SessionTreeOrder::Recent => "p.last_in_range DESC",
+            SessionTreeOrder::Cost => "p.period_cost DESC, COALESCE(p.period_prompt, 0) DESC",
+        };
+        let query = format!(
             r#"
-            WITH period_stats AS (
+            WITH period_roots AS (
                 SELECT
-                    session_id,
-                    COALESCE(SUM(cost_usd), 0.0) AS period_cost,
-                    SUM(prompt_tokens) AS period_prompt,
-                    COALESCE(SUM(missing_price), 0) AS missing_price
-                FROM session_turn_costs
-                WHERE timestamp BETWEEN ?1 AND ?2
-                GROUP BY session_id
+                    tr.root_session_id AS root_id,
+                    MAX(t.timestamp) AS last_in_range,
+                    COALESCE(SUM(t.cost), 0.0) AS period_cost,
+                    SUM(t.prompt_tokens) AS period_prompt
+                FROM {turn_costs} t
+                JOIN session_tree tr ON tr.session_id = t.session_id
+                WHERE t.timestamp BETWEEN ?1 AND ?2 {scope}
+                GROUP BY tr.root_session_id
             ),
             session_costs AS (
                 SELECT
                     session_id,
-                    COALESCE(SUM(cost_usd), 0.0) AS cost_usd,
+                    COALESCE(SUM(cost), 0.0) AS cost,
                     COALESCE(SUM(missing_price), 0) AS missing_price
-                FROM session_daily_costs
+                FROM {daily_costs}
                 GROUP BY session_id
+            ),
+            tree_totals AS (
+                SELECT
+                    tr.root_session_id AS root_id,
+                    COUNT(*) - 1 AS descendants,
+                    COALESCE(SUM(s.total_tokens), 0) AS tree_total_tokens,
+                    COALESCE(SUM(c.cost), 0.0) AS tree_cost,
+                    COALESCE(SUM(c.missing_price), 0) AS tree_missing_price
+                FROM session_tree tr
+                JOIN period_roots p ON p.root_id = tr.root_session_id
+                JOIN sessions s ON s.session_id = tr.session_id
+                LEFT JOIN session_costs c ON c.session_id = tr.session_id
+                GROUP BY tr.root_session_id
+            ),
+            members AS (
+                SELECT tr.session_id, tr.root_session_id, tr.depth
+                FROM session_tree tr
+                JOIN period_roots p ON p.root_id = tr.root_session_id
+                WHERE tr.depth = 0 OR tr.root_session_id IN (SELECT value FROM json_each(?5))
             )
             SELECT
                 s.session_id,
@@ -2318,27 +4384,73 @@ impl Storage {
                 s.user_messages,
                 s.title,
                 s.last_summary,
-                session_costs.cost_usd,
+                s.root_label,
+                session_costs.cost,
                 session_costs.missing_price,
-                period_stats.period_cost,
-                period_stats.period_prompt
-            FROM period_stats
-            JOIN sessions s ON s.session_id = period_stats.session_id
+                m.root_session_id,
+                m.depth,
+                tt.descendants,
+                tt.tree_total_tokens,
+                tt.tree_cost,
+                tt.tree_missing_price
+            FROM members m
+            JOIN period_roots p ON p.root_id = m.root_session_id
+            JOIN tree_totals tt ON tt.root_id = m.root_session_id
+            JOIN sessions s ON s.session_id = m.session_id
             LEFT JOIN session_costs ON session_costs.session_id = s.session_id
-            ORDER BY COALESCE(period_stats.period_cost, 0) DESC,
-                     COALESCE(period_stats.period_prompt, 0) DESC
+            ORDER BY {order_by}, m.root_session_id, m.depth > 0, s.started_at, s.session_id
             LIMIT ?3 OFFSET ?4
             "#,
+            scope = self.root_scope("t.session_id", "?6"),
+            turn_costs = self.turn_costs(),
+            daily_costs = self.daily_costs("session_daily_costs"),
+        );
+        let rows = self
+            .bind_root_scope(
+                sqlx::query(&query)
+                    .bind(start.to_rfc3339())
+                    .bind(end.to_rfc3339())
+                    .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                    .bind(i64::try_from(offset).unwrap_or(0))
+                    .bind(serde_json::to_string(expanded)?),
+            )
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load session tree page")?;
+
+        session_aggregates_from_rows(rows)
+    }
+
+    /// Links a subagent session to the session that spawned it. A link read
+    /// from the subagent's own metadata wins over one inferred from a spawn
+    /// event.
+    pub async fn link_session_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        session_id: &str,
+        parent_session_id: &str,
+        source: SessionLinkSource,
+    ) -> Result<()> {
+        if session_id == parent_session_id {
+            return Ok(());
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO session_links (session_id, parent_session_id, source)
+            VALUES (?, ?, ?)
+            ON CONFLICT(session_id) DO UPDATE SET
+                parent_session_id = excluded.parent_session_id,
+                source = excluded.source
+            WHERE excluded.source = 'meta' OR session_links.source <> 'meta'
+            "#,
         )
-        .bind(start.to_rfc3339())
-        .bind(end.to_rfc3339())
-        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
-        .bind(i64::try_from(offset).unwrap_or(0))
-        .fetch_all(&*self.pool)
+        .bind(session_id)
+        .bind(parent_session_id)
+        .bind(source.as_str())
+        .execute(&mut **tx)
         .await
-        .with_context(|| "failed to load sessions by cost")?;
-
-        Ok(session_aggregates_from_rows(rows)?)
+        .with_context(|| "failed to link session to its parent")?;
+        Ok(())
     }
 
     pub async fn session_turns_for_message(
@@ -2348,42 +4460,46 @@ impl Storage {
         limit: usize,
     ) -> Result<Vec<SessionTurn>> {
         let rows = if let Some(message_id) = message_id.filter(|value| *value > 0) {
-            sqlx::query(
+            let query = format!(
                 r#"
                 SELECT timestamp, model, note, context_window, reasoning_effort,
                        prompt_tokens, cached_prompt_tokens, completion_tokens,
                        total_tokens, reasoning_tokens,
-                       cost_usd, missing_price
-                FROM session_turn_costs
+                       cost, missing_price
+                FROM {turn_costs}
                 WHERE message_id = ?
                 ORDER BY timestamp DESC
                 LIMIT ?
                 "#,
-            )
-            .bind(message_id)
-            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load message turns")?
+                turn_costs = self.turn_costs(),
+            );
+            sqlx::query(&query)
+                .bind(message_id)
+                .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load message turns")?
         } else {
-            sqlx::query(
+            let query = format!(
                 r#"
                 SELECT timestamp, model, note, context_window, reasoning_effort,
                        prompt_tokens, cached_prompt_tokens, completion_tokens,
                        total_tokens, reasoning_tokens,
-                       cost_usd, missing_price
-                FROM session_turn_costs
+                       cost, missing_price
+                FROM {turn_costs}
                 WHERE session_id = ?
                   AND message_id IS NULL
                 ORDER BY timestamp DESC
                 LIMIT ?
                 "#,
-            )
-            .bind(session_id)
-            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
-            .fetch_all(&*self.pool)
-            .await
-            .with_context(|| "failed to load unattributed turns")?
+                turn_costs = self.turn_costs(),
+            );
+            sqlx::query(&query)
+                .bind(session_id)
+                .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                .fetch_all(&*self.pool)
+                .await
+                .with_context(|| "failed to load unattributed turns")?
         };
 
         map_session_turn_rows(rows, "message turns")
@@ -2398,10 +4514,10 @@ impl Storage {
             r#"
             INSERT INTO sessions (
                 session_id, started_at, last_event_at, cwd, repo_url, repo_branch,
-                repo_commit, model_provider, subagent, last_model,
+                repo_commit, model_provider, subagent, last_model, root_label,
                 prompt_tokens, cached_prompt_tokens, completion_tokens,
                 reasoning_tokens, total_tokens
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, 0, 0, 0, 0)
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, 0, 0, 0, 0)
             ON CONFLICT(session_id) DO UPDATE SET
                 started_at = MIN(sessions.started_at, excluded.started_at),
                 last_event_at = MAX(sessions.last_event_at, excluded.last_event_at),
@@ -2410,7 +4526,8 @@ impl Storage {
                 repo_branch = COALESCE(excluded.repo_branch, sessions.repo_branch),
                 repo_commit = COALESCE(excluded.repo_commit, sessions.repo_commit),
                 model_provider = COALESCE(excluded.model_provider, sessions.model_provider),
-                subagent = COALESCE(excluded.subagent, sessions.subagent)
+                subagent = COALESCE(excluded.subagent, sessions.subagent),
+                root_label = COALESCE(excluded.root_label, sessions.root_label)
             "#,
         )
         .bind(&meta.session_id)
@@ -2423,6 +4540,7 @@ impl Storage {
         .bind(meta.model_provider.as_deref())
         .bind(meta.subagent.as_deref())
         .bind(meta.last_model.as_deref())
+        .bind(meta.root_label.as_deref())
         .execute(&mut **tx)
         .await
         .with_context(|| "failed to upsert session metadata")?;
@@ -2458,7 +4576,8 @@ impl Storage {
         summary: &str,
         timestamp: DateTime<Utc>,
     ) -> Result<()> {
-        self.ensure_session_stub_tx(tx, session_id, timestamp).await?;
+        self.ensure_session_stub_tx(tx, session_id, timestamp)
+            .await?;
         sqlx::query(
             r#"
             UPDATE sessions
@@ -2576,24 +4695,29 @@ impl Storage {
         Ok(message_id)
     }
 
-    #[allow(clippy::too_many_arguments)]
     pub async fn record_turn_tx(
         &self,
         tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-        session_id: &str,
-        timestamp: DateTime<Utc>,
-        model: &str,
-        note: Option<&str>,
-        context_window: Option<u64>,
-        reasoning_effort: Option<&str>,
-        prompt_tokens: u64,
-        cached_prompt_tokens: u64,
-        completion_tokens: u64,
-        reasoning_tokens: u64,
-        total_tokens: u64,
-        message_id: Option<i64>,
+        turn: &NewTurn<'_>,
     ) -> Result<()> {
+        let NewTurn {
+            session_id,
+            timestamp,
+            model,
+            note,
+            context_window,
+            reasoning_effort,
+            service_tier,
+            policy,
+            prompt_tokens,
+            cached_prompt_tokens,
+            completion_tokens,
+            reasoning_tokens,
+            total_tokens,
+            message_id,
+        } = *turn;
         let date = timestamp.with_timezone(&Local).date_naive();
+        let service_tier = service_tier.unwrap_or(DEFAULT_SERVICE_TIER);
         let timestamp_str = timestamp.to_rfc3339();
         let prompt_tokens = i64::try_from(prompt_tokens).unwrap_or(i64::MAX);
         let cached_prompt_tokens = i64::try_from(cached_prompt_tokens).unwrap_or(i64::MAX);
@@ -2603,7 +4727,7 @@ impl Storage {
         let context_window = context_window.and_then(|value| i64::try_from(value).ok());
         let message_id = message_id.and_then(|value| if value > 0 { Some(value) } else { None });
 
-        self.ensure_model_price_tx(tx, model).await?;
+        self.ensure_model_price_tx(tx, model, service_tier).await?;
 
         sqlx::query(
             r#"
@@ -2628,9 +4752,10 @@ impl Storage {
         let insert_result = sqlx::query(
             r#"
             INSERT OR IGNORE INTO session_turns (
-                session_id, timestamp, model, note, context_window, reasoning_effort, message_id, prompt_tokens,
+                session_id, timestamp, model, note, context_window, reasoning_effort, service_tier,
+                approval_policy, sandbox_policy, cwd, message_id, prompt_tokens,
                 cached_prompt_tokens, completion_tokens, reasoning_tokens, total_tokens
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(session_id)
@@ -2639,6 +4764,10 @@ impl Storage {
         .bind(note)
         .bind(context_window)
         .bind(reasoning_effort)
+        .bind(service_tier)
+        .bind(policy.approval_policy)
+        .bind(policy.sandbox_policy)
+        .bind(policy.cwd)
         .bind(message_id)
         .bind(prompt_tokens)
         .bind(cached_prompt_tokens)
@@ -2655,6 +4784,7 @@ impl Storage {
             if note.is_some()
                 || context_window.is_some()
                 || reasoning_effort.is_some()
+                || !policy.is_empty()
                 || message_id.is_some()
             {
                 sqlx::query(
@@ -2664,6 +4794,9 @@ impl Storage {
                         note = COALESCE(note, ?),
                         context_window = COALESCE(context_window, ?),
                         reasoning_effort = COALESCE(reasoning_effort, ?),
+                        approval_policy = COALESCE(approval_policy, ?),
+                        sandbox_policy = COALESCE(sandbox_policy, ?),
+                        cwd = COALESCE(cwd, ?),
                         message_id = COALESCE(message_id, ?)
                     WHERE session_id = ?
                       AND timestamp = ?
@@ -2678,6 +4811,9 @@ impl Storage {
                 .bind(note)
                 .bind(context_window)
                 .bind(reasoning_effort)
+                .bind(policy.approval_policy)
+                .bind(policy.sandbox_policy)
+                .bind(policy.cwd)
                 .bind(message_id)
                 .bind(session_id)
                 .bind(&timestamp_str)
@@ -2694,28 +4830,63 @@ impl Storage {
             return Ok(());
         }
 
+        let long_context_threshold: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT long_context_threshold
+            FROM model_prices
+            WHERE model = ?
+              AND service_tier = ?
+              AND effective_from <= ?
+            ORDER BY effective_from DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(model)
+        .bind(service_tier)
+        .bind(date.to_string())
+        .fetch_optional(&mut **tx)
+        .await
+        .with_context(|| "failed to load long-context threshold")?
+        .flatten();
+        let long_context =
+            long_context_threshold.is_some_and(|threshold| prompt_tokens > threshold);
+        let (long_prompt_tokens, long_cached_prompt_tokens, long_completion_tokens) =
+            if long_context {
+                (prompt_tokens, cached_prompt_tokens, completion_tokens)
+            } else {
+                (0, 0, 0)
+            };
+
         sqlx::query(
             r#"
             INSERT INTO session_daily_stats (
-                date, session_id, model, prompt_tokens, cached_prompt_tokens,
-                completion_tokens, reasoning_tokens, total_tokens
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(date, session_id, model) DO UPDATE SET
+                date, session_id, model, service_tier, prompt_tokens, cached_prompt_tokens,
+                completion_tokens, reasoning_tokens, total_tokens, long_prompt_tokens,
+                long_cached_prompt_tokens, long_completion_tokens
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(date, session_id, model, service_tier) DO UPDATE SET
                 prompt_tokens = prompt_tokens + excluded.prompt_tokens,
                 cached_prompt_tokens = cached_prompt_tokens + excluded.cached_prompt_tokens,
                 completion_tokens = completion_tokens + excluded.completion_tokens,
                 reasoning_tokens = reasoning_tokens + excluded.reasoning_tokens,
-                total_tokens = total_tokens + excluded.total_tokens
+                total_tokens = total_tokens + excluded.total_tokens,
+                long_prompt_tokens = long_prompt_tokens + excluded.long_prompt_tokens,
+                long_cached_prompt_tokens = long_cached_prompt_tokens + excluded.long_cached_prompt_tokens,
+                long_completion_tokens = long_completion_tokens + excluded.long_completion_tokens
             "#,
         )
         .bind(date.to_string())
         .bind(session_id)
         .bind(model)
+        .bind(service_tier)
         .bind(prompt_tokens)
         .bind(cached_prompt_tokens)
         .bind(completion_tokens)
         .bind(reasoning_tokens)
         .bind(total_tokens)
+        .bind(long_prompt_tokens)
+        .bind(long_cached_prompt_tokens)
+        .bind(long_completion_tokens)
         .execute(&mut **tx)
         .await
         .with_context(|| "failed to upsert session daily stats")?;
@@ -2723,24 +4894,32 @@ impl Storage {
         sqlx::query(
             r#"
             INSERT INTO daily_stats (
-                date, model, prompt_tokens, cached_prompt_tokens, completion_tokens,
-                reasoning_tokens, total_tokens
-            ) VALUES (?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(date, model) DO UPDATE SET
+                date, model, service_tier, prompt_tokens, cached_prompt_tokens, completion_tokens,
+                reasoning_tokens, total_tokens, long_prompt_tokens, long_cached_prompt_tokens,
+                long_completion_tokens
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(date, model, service_tier) DO UPDATE SET
                 prompt_tokens = prompt_tokens + excluded.prompt_tokens,
                 cached_prompt_tokens = cached_prompt_tokens + excluded.cached_prompt_tokens,
                 completion_tokens = completion_tokens + excluded.completion_tokens,
                 reasoning_tokens = reasoning_tokens + excluded.reasoning_tokens,
-                total_tokens = total_tokens + excluded.total_tokens
+                total_tokens = total_tokens + excluded.total_tokens,
+                long_prompt_tokens = long_prompt_tokens + excluded.long_prompt_tokens,
+                long_cached_prompt_tokens = long_cached_prompt_tokens + excluded.long_cached_prompt_tokens,
+                long_completion_tokens = long_completion_tokens + excluded.long_completion_tokens
             "#,
         )
         .bind(date.to_string())
         .bind(model)
+        .bind(service_tier)
         .bind(prompt_tokens)
         .bind(cached_prompt_tokens)
         .bind(completion_tokens)
         .bind(reasoning_tokens)
         .bind(total_tokens)
+        .bind(long_prompt_tokens)
+        .bind(long_cached_prompt_tokens)
+        .bind(long_completion_tokens)
         .execute(&mut **tx)
         .await
         .with_context(|| "failed to upsert daily stats")?;
@@ -2782,127 +4961,46 @@ impl Storage {
         &self,
         tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
         model: &str,
+        service_tier: &str,
     ) -> Result<()> {
-        let exists: Option<i64> =
-            sqlx::query_scalar("SELECT 1 FROM model_prices WHERE model = ? LIMIT 1")
-                .bind(model)
-                .fetch_optional(&mut **tx)
-                .await
-                .with_context(|| "failed to check model_prices")?;
-        if exists.is_some() {
-            return Ok(());
-        }
-
-        let row = sqlx::query(
-            r#"
-            SELECT prompt_per_1m, cached_prompt_per_1m, completion_per_1m
-            FROM prices
-            WHERE ? LIKE model || '%'
-            ORDER BY LENGTH(model) DESC
-            LIMIT 1
-            "#,
+        let exists: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM model_prices WHERE model = ? AND service_tier = ? LIMIT 1",
         )
         .bind(model)
+        .bind(service_tier)
         .fetch_optional(&mut **tx)
         .await
-        .with_context(|| "failed to resolve model price")?;
-
-        if let Some(row) = row {
-            let prompt: Option<f64> = row.try_get("prompt_per_1m").ok();
-            let cached: Option<f64> = row.try_get("cached_prompt_per_1m").ok();
-            let completion: Option<f64> = row.try_get("completion_per_1m").ok();
-            sqlx::query(
-                r#"
-                INSERT INTO model_prices (
-                    model, prompt_per_1m, cached_prompt_per_1m, completion_per_1m
-                ) VALUES (?, ?, ?, ?)
-                "#,
-            )
-            .bind(model)
-            .bind(prompt)
-            .bind(cached)
-            .bind(completion)
-            .execute(&mut **tx)
-            .await
-            .with_context(|| "failed to insert model price")?;
-        } else {
-            sqlx::query(
-                r#"
-                INSERT INTO model_prices (
-                    model, prompt_per_1m, cached_prompt_per_1m, completion_per_1m
-                ) VALUES (?, NULL, NULL, NULL)
-                "#,
-            )
-            .bind(model)
-            .execute(&mut **tx)
-            .await
-            .with_context(|| "failed to insert missing model price")?;
+        .with_context(|| "failed to check model_prices")?;
+        if exists.is_some() {
+            return Ok(());
         }
 
+        let remote = load_price_versions_tx(tx, "prices").await?;
+        let overrides = load_price_versions_tx(tx, "price_overrides").await?;
+        let versions = resolve_tier_price_versions(model, service_tier, &remote, &overrides);
+        insert_model_prices_tx(tx, model, service_tier, &versions).await?;
+
         Ok(())
     }
 
     pub async fn load_ingest_state(&self) -> Result<Vec<IngestStateRow>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT path, session_id, last_offset,
-                   last_seen_input_tokens, last_seen_cached_input_tokens,
-                   last_seen_output_tokens, last_seen_reasoning_output_tokens,
-                   last_seen_total_tokens, last_committed_input_tokens,
-                   last_committed_cached_input_tokens, last_committed_output_tokens,
-                   last_committed_reasoning_output_tokens, last_committed_total_tokens,
-                   current_message_id, current_message_seq,
-                   current_model, current_effort
-            FROM ingest_state
-            "#,
-        )
-        .fetch_all(&*self.pool)
-        .await
-        .with_context(|| "failed to load ingest state")?;
-
-        let mut states = Vec::with_capacity(rows.len());
-        for row in rows {
-            states.push(IngestStateRow {
-                path: PathBuf::from(row.try_get::<String, _>("path")?),
-                session_id: row.try_get::<Option<String>, _>("session_id")?,
-                last_offset: row.try_get::<i64, _>("last_offset").unwrap_or(0) as u64,
-                last_seen_input_tokens: row.try_get::<i64, _>("last_seen_input_tokens").unwrap_or(0)
-                    as u64,
-                last_seen_cached_input_tokens: row
-                    .try_get::<i64, _>("last_seen_cached_input_tokens")
-                    .unwrap_or(0) as u64,
-                last_seen_output_tokens: row
-                    .try_get::<i64, _>("last_seen_output_tokens")
-                    .unwrap_or(0) as u64,
-                last_seen_reasoning_output_tokens: row
-                    .try_get::<i64, _>("last_seen_reasoning_output_tokens")
-                    .unwrap_or(0) as u64,
-                last_seen_total_tokens: row.try_get::<i64, _>("last_seen_total_tokens").unwrap_or(0)
-                    as u64,
-                last_committed_input_tokens: row
-                    .try_get::<i64, _>("last_committed_input_tokens")
-                    .unwrap_or(0) as u64,
-                last_committed_cached_input_tokens: row
-                    .try_get::<i64, _>("last_committed_cached_input_tokens")
-                    .unwrap_or(0) as u64,
-                last_committed_output_tokens: row
-                    .try_get::<i64, _>("last_committed_output_tokens")
-                    .unwrap_or(0) as u64,
-                last_committed_reasoning_output_tokens: row
-                    .try_get::<i64, _>("last_committed_reasoning_output_tokens")
-                    .unwrap_or(0) as u64,
-                last_committed_total_tokens: row
-                    .try_get::<i64, _>("last_committed_total_tokens")
-                    .unwrap_or(0) as u64,
-                current_message_id: row.try_get::<Option<i64>, _>("current_message_id")?,
-                current_message_seq: row.try_get::<i64, _>("current_message_seq").unwrap_or(0)
-                    as u64,
-                current_model: row.try_get::<Option<String>, _>("current_model")?,
-                current_effort: row.try_get::<Option<String>, _>("current_effort")?,
-            });
-        }
+        let rows = sqlx::query(&format!("SELECT {INGEST_STATE_COLUMNS} FROM ingest_state"))
+            .fetch_all(&*self.pool)
+            .await
+            .with_context(|| "failed to load ingest state")?;
+        rows.iter().map(ingest_state_from_row).collect()
+    }
 
-        Ok(states)
+    /// Stored ingest state of one file, if it has any.
+    pub async fn load_ingest_state_for(&self, path: &Path) -> Result<Option<IngestStateRow>> {
+        let row = sqlx::query(&format!(
+            "SELECT {INGEST_STATE_COLUMNS} FROM ingest_state WHERE path = ?1"
+        ))
+        .bind(path.to_string_lossy().as_ref())
+        .fetch_optional(&*self.pool)
+        .await
+        .with_context(|| "failed to load ingest state")?;
+        row.as_ref().map(ingest_state_from_row).transpose()
     }
 
     pub async fn upsert_ingest_state_tx(
@@ -2920,8 +5018,10 @@ impl Storage {
                 last_committed_cached_input_tokens, last_committed_output_tokens,
                 last_committed_reasoning_output_tokens, last_committed_total_tokens,
                 current_message_id, current_message_seq,
-                current_model, current_effort
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                current_model, current_effort, current_service_tier,
+                current_approval_policy, current_sandbox_policy, current_cwd, completed,
+                device, inode, head_fingerprint
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(path) DO UPDATE SET
                 session_id = excluded.session_id,
                 last_offset = excluded.last_offset,
@@ -2939,7 +5039,15 @@ impl Storage {
                 current_message_id = excluded.current_message_id,
                 current_message_seq = excluded.current_message_seq,
                 current_model = excluded.current_model,
-                current_effort = excluded.current_effort
+                current_effort = excluded.current_effort,
+                current_service_tier = excluded.current_service_tier,
+                current_approval_policy = excluded.current_approval_policy,
+                current_sandbox_policy = excluded.current_sandbox_policy,
+                current_cwd = excluded.current_cwd,
+                completed = excluded.completed,
+                device = excluded.device,
+                inode = excluded.inode,
+                head_fingerprint = excluded.head_fingerprint
             "#,
         )
         .bind(state.path.to_string_lossy().as_ref())
@@ -2959,12 +5067,317 @@ impl Storage {
         .bind(i64::try_from(state.current_message_seq).unwrap_or(i64::MAX))
         .bind(state.current_model.as_deref())
         .bind(state.current_effort.as_deref())
+        .bind(state.current_service_tier.as_deref())
+        .bind(state.current_approval_policy.as_deref())
+        .bind(state.current_sandbox_policy.as_deref())
+        .bind(state.current_cwd.as_deref())
+        .bind(state.completed)
+        .bind(state.device.map(|value| value as i64))
+        .bind(state.inode.map(|value| value as i64))
+        .bind(state.head_fingerprint.as_deref())
         .execute(&mut **tx)
         .await
         .with_context(|| "failed to upsert ingest state")?;
         Ok(())
     }
 
+    /// Paths of tracked files with this head fingerprint, which may be an
+    /// earlier location of the file.
+    pub async fn ingest_paths_matching_head(&self, head_fingerprint: &str) -> Result<Vec<PathBuf>> {
+        let paths: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT path
+            FROM ingest_state
+            WHERE head_fingerprint = ?
+            "#,
+        )
+        .bind(head_fingerprint)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to look up moved session files")?;
+        Ok(paths.into_iter().map(PathBuf::from).collect())
+    }
+
+    /// Re-keys a tracked file's ingest state from `old_path` to `state.path`.
+    pub async fn move_ingest_state(&self, old_path: &Path, state: &IngestStateRow) -> Result<()> {
+        let mut tx = self.begin_tx().await?;
+        sqlx::query("DELETE FROM ingest_state WHERE path = ?1")
+            .bind(old_path.to_string_lossy().as_ref())
+            .execute(&mut *tx)
+            .await
+            .with_context(|| "failed to remove moved ingest state")?;
+        self.upsert_ingest_state_tx(&mut tx, state).await?;
+        tx.commit()
+            .await
+            .with_context(|| "failed to commit moved ingest state")?;
+        Ok(())
+    }
+
+    /// Records a line or file that could not be ingested. Errors are grouped
+    /// per file and kind: later occurrences bump `count` and replace the
+    /// offset, message and sample with the latest ones.
+    pub async fn record_ingest_error_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        error: &NewIngestError<'_>,
+        seen_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let sample: String = error
+            .sample
+            .chars()
+            .take(INGEST_ERROR_SAMPLE_CHARS)
+            .collect();
+        sqlx::query(
+            r#"
+            INSERT INTO ingest_errors (
+                path, kind, offset, message, sample, first_seen, last_seen, count
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, 1)
+            ON CONFLICT(path, kind) DO UPDATE SET
+                offset = excluded.offset,
+                message = excluded.message,
+                sample = excluded.sample,
+                last_seen = excluded.last_seen,
+                count = ingest_errors.count + 1
+            "#,
+        )
+        .bind(error.path.to_string_lossy().as_ref())
+        .bind(error.kind)
+        .bind(i64::try_from(error.offset).unwrap_or(i64::MAX))
+        .bind(error.message)
+        .bind(sample)
+        .bind(seen_at.to_rfc3339())
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to record ingest error")?;
+        Ok(())
+    }
+
+    pub async fn record_ingest_error(
+        &self,
+        error: &NewIngestError<'_>,
+        seen_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut tx = self.begin_tx().await?;
+        self.record_ingest_error_tx(&mut tx, error, seen_at).await?;
+        tx.commit()
+            .await
+            .with_context(|| "failed to commit ingest error")?;
+        Ok(())
+    }
+
+    /// Drops every error recorded for `path`, for a file read again from the
+    /// start.
+    pub async fn clear_file_ingest_errors_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        path: &Path,
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM ingest_errors WHERE path = ?1")
+            .bind(path.to_string_lossy().as_ref())
+            .execute(&mut **tx)
+            .await
+            .with_context(|| "failed to clear ingest errors")?;
+        Ok(())
+    }
+
+    pub async fn clear_ingest_errors(&self, path: &Path, kind: &str) -> Result<()> {
+        sqlx::query("DELETE FROM ingest_errors WHERE path = ?1 AND kind = ?2")
+            .bind(path.to_string_lossy().as_ref())
+            .bind(kind)
+            .execute(&*self.pool)
+            .await
+            .with_context(|| "failed to clear ingest errors")?;
+        Ok(())
+    }
+
+    /// Total occurrences across all recorded ingest errors.
+    pub async fn ingest_error_count(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(count), 0) FROM ingest_errors")
+            .fetch_one(&*self.pool)
+            .await
+            .with_context(|| "failed to count ingest errors")?;
+        Ok(count.max(0) as u64)
+    }
+
+    /// Recorded ingest errors, most recently seen first.
+    pub async fn list_ingest_errors(&self) -> Result<Vec<IngestErrorRow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT path, kind, offset, message, sample, first_seen, last_seen, count
+            FROM ingest_errors
+            ORDER BY last_seen DESC, path ASC
+            "#,
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load ingest errors")?;
+
+        let parse_time = |value: String| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|dt| dt.with_timezone(&Utc))
+                .with_context(|| "invalid timestamp in ingest_errors")
+        };
+        let mut errors = Vec::with_capacity(rows.len());
+        for row in rows {
+            errors.push(IngestErrorRow {
+                path: PathBuf::from(row.try_get::<String, _>("path")?),
+                kind: row.try_get("kind")?,
+                offset: row.try_get::<i64, _>("offset")?.max(0) as u64,
+                message: row.try_get("message")?,
+                sample: row.try_get("sample")?,
+                first_seen: parse_time(row.try_get("first_seen")?)?,
+                last_seen: parse_time(row.try_get("last_seen")?)?,
+                count: row.try_get::<i64, _>("count")?.max(0) as u64,
+            });
+        }
+        Ok(errors)
+    }
+
+    /// Records a turn whose reported per-request usage disagrees with the
+    /// difference of the cumulative totals around it.
+    pub async fn record_usage_mismatch_tx(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        mismatch: &NewUsageMismatch<'_>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO usage_mismatches
+                (session_id, timestamp, model, fields, reported_tokens, cumulative_tokens)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(mismatch.session_id)
+        .bind(mismatch.timestamp.to_rfc3339())
+        .bind(mismatch.model)
+        .bind(mismatch.fields)
+        .bind(mismatch.reported_tokens as i64)
+        .bind(mismatch.cumulative_tokens as i64)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to record usage mismatch")?;
+        Ok(())
+    }
+
+    pub async fn usage_mismatch_summary(&self) -> Result<UsageMismatchSummary> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COUNT(*) AS count,
+                COUNT(DISTINCT session_id) AS sessions,
+                COALESCE(SUM(reported_tokens), 0) AS reported_tokens,
+                COALESCE(SUM(cumulative_tokens), 0) AS cumulative_tokens
+            FROM usage_mismatches
+            "#,
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .with_context(|| "failed to summarize usage mismatches")?;
+        Ok(UsageMismatchSummary {
+            count: row.try_get::<i64, _>("count")?.max(0) as u64,
+            sessions: row.try_get::<i64, _>("sessions")?.max(0) as u64,
+            reported_tokens: row.try_get::<i64, _>("reported_tokens")?.max(0) as u64,
+            cumulative_tokens: row.try_get::<i64, _>("cumulative_tokens")?.max(0) as u64,
+        })
+    }
+
+    /// The latest usage mismatches, newest first.
+    pub async fn recent_usage_mismatches(&self, limit: usize) -> Result<Vec<UsageMismatchRow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT session_id, timestamp, model, fields, reported_tokens, cumulative_tokens
+            FROM usage_mismatches
+            ORDER BY timestamp DESC, session_id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit as i64)
+        .fetch_all(&*self.pool)
+        .await
+        .with_context(|| "failed to load usage mismatches")?;
+
+        let mut mismatches = Vec::with_capacity(rows.len());
+        for row in rows {
+            let timestamp: String = row.try_get("timestamp")?;
+            mismatches.push(UsageMismatchRow {
+                session_id: row.try_get("session_id")?,
+                timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .with_context(|| "invalid timestamp in usage_mismatches")?,
+                model: row.try_get("model")?,
+                fields: row.try_get("fields")?,
+                reported_tokens: row.try_get::<i64, _>("reported_tokens")?.max(0) as u64,
+                cumulative_tokens: row.try_get::<i64, _>("cumulative_tokens")?.max(0) as u64,
+            });
+        }
+        Ok(mismatches)
+    }
+
+    /// Stores the progress of a running backfill, or clears it with `None`
+    /// once the backfill has finished.
+    pub async fn set_backfill_progress(&self, progress: Option<&BackfillProgress>) -> Result<()> {
+        let Some(progress) = progress else {
+            sqlx::query("DELETE FROM ingest_backfill")
+                .execute(&*self.pool)
+                .await
+                .with_context(|| "failed to clear backfill progress")?;
+            return Ok(());
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO ingest_backfill (
+                id, started_at, updated_at, files_total, files_done, bytes_total, bytes_done
+            ) VALUES (1, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                started_at = excluded.started_at,
+                updated_at = excluded.updated_at,
+                files_total = excluded.files_total,
+                files_done = excluded.files_done,
+                bytes_total = excluded.bytes_total,
+                bytes_done = excluded.bytes_done
+            "#,
+        )
+        .bind(progress.started_at.to_rfc3339())
+        .bind(progress.updated_at.to_rfc3339())
+        .bind(i64::try_from(progress.files_total).unwrap_or(i64::MAX))
+        .bind(i64::try_from(progress.files_done).unwrap_or(i64::MAX))
+        .bind(i64::try_from(progress.bytes_total).unwrap_or(i64::MAX))
+        .bind(i64::try_from(progress.bytes_done).unwrap_or(i64::MAX))
+        .execute(&*self.pool)
+        .await
+        .with_context(|| "failed to store backfill progress")?;
+        Ok(())
+    }
+
+    pub async fn backfill_progress(&self) -> Result<Option<BackfillProgress>> {
+        let row = sqlx::query(
+            r#"
+            SELECT started_at, updated_at, files_total, files_done, bytes_total, bytes_done
+            FROM ingest_backfill
+            WHERE id = 1
+            "#,
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .with_context(|| "failed to load backfill progress")?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let parse_time = |value: String| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|dt| dt.with_timezone(&Utc))
+                .with_context(|| "invalid timestamp in ingest_backfill")
+        };
+        Ok(Some(BackfillProgress {
+            started_at: parse_time(row.try_get("started_at")?)?,
+            updated_at: parse_time(row.try_get("updated_at")?)?,
+            files_total: row.try_get::<i64, _>("files_total")?.max(0) as u64,
+            files_done: row.try_get::<i64, _>("files_done")?.max(0) as u64,
+            bytes_total: row.try_get::<i64, _>("bytes_total")?.max(0) as u64,
+            bytes_done: row.try_get::<i64, _>("bytes_done")?.max(0) as u64,
+        }))
+    }
+
     pub async fn update_ingest_activity_tx(
         &self,
         tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
@@ -3020,7 +5433,7 @@ pub struct AggregateTotals {
     #[allow(dead_code)]
     pub total_tokens: u64,
     pub reasoning_tokens: u64,
-    pub cost_usd: Option<f64>,
+    pub cost: Option<f64>,
 }
 
 impl Default for AggregateTotals {
@@ -3031,7 +5444,7 @@ impl Default for AggregateTotals {
             completion_tokens: 0,
             total_tokens: 0,
             reasoning_tokens: 0,
-            cost_usd: Some(0.0),
+            cost: Some(0.0),
         }
     }
 }
@@ -3062,9 +5475,52 @@ pub struct SessionAggregate {
     pub total_tokens: u64,
     pub reasoning_tokens: u64,
     pub user_messages: u64,
-    pub cost_usd: Option<f64>,
+    pub cost: Option<f64>,
     pub title: Option<String>,
     pub last_summary: Option<String>,
+    pub root_label: Option<String>,
+    /// Position in the subagent tree; only set by the session tree queries.
+    pub tree: Option<SessionTreeNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionTreeNode {
+    pub root_session_id: String,
+    pub depth: u32,
+    /// Sessions in the tree below the root, counted on every row of the tree.
+    pub descendants: u64,
+    /// Tokens and cost of the whole tree; `None` cost when a price is missing.
+    pub tree_total_tokens: u64,
+    pub tree_cost: Option<f64>,
+}
+
+impl SessionTreeNode {
+    pub fn is_root(&self) -> bool {
+        self.depth == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionTreeOrder {
+    Recent,
+    Cost,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionLinkSource {
+    /// `source.subagent` of the child's session metadata.
+    Meta,
+    /// A spawn event in the parent's transcript.
+    Spawn,
+}
+
+impl SessionLinkSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            SessionLinkSource::Meta => "meta",
+            SessionLinkSource::Spawn => "spawn",
+        }
+    }
 }
 
 impl SessionAggregate {
@@ -3091,7 +5547,7 @@ pub struct SessionTurn {
     pub completion_tokens: u64,
     pub total_tokens: u64,
     pub reasoning_tokens: u64,
-    pub cost_usd: Option<f64>,
+    pub cost: Option<f64>,
     pub usage_included: bool,
 }
 
@@ -3106,7 +5562,7 @@ pub struct SessionMessage {
     pub completion_tokens: u64,
     pub total_tokens: u64,
     pub reasoning_tokens: u64,
-    pub cost_usd: Option<f64>,
+    pub cost: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
@@ -3120,6 +5576,252 @@ pub struct ModelUsageRow {
 pub struct ToolCountRow {
     pub tool: String,
     pub count: u64,
+    /// Calls with a known outcome; failure rates are relative to these.
+    pub finished: u64,
+    pub failed: u64,
+    pub duration_ms: u64,
+    pub output_bytes: u64,
+}
+
+impl ToolCountRow {
+    pub fn failure_rate(&self) -> Option<f64> {
+        (self.finished > 0).then(|| self.failed as f64 / self.finished as f64)
+    }
+}
+
+fn ingest_state_from_row(row: &SqliteRow) -> Result<IngestStateRow> {
+    Ok(IngestStateRow {
+        path: PathBuf::from(row.try_get::<String, _>("path")?),
+        session_id: row.try_get::<Option<String>, _>("session_id")?,
+        last_offset: row.try_get::<i64, _>("last_offset").unwrap_or(0) as u64,
+        last_seen_input_tokens: row.try_get::<i64, _>("last_seen_input_tokens").unwrap_or(0) as u64,
+        last_seen_cached_input_tokens: row
+            .try_get::<i64, _>("last_seen_cached_input_tokens")
+            .unwrap_or(0) as u64,
+        last_seen_output_tokens: row
+            .try_get::<i64, _>("last_seen_output_tokens")
+            .unwrap_or(0) as u64,
+        last_seen_reasoning_output_tokens: row
+            .try_get::<i64, _>("last_seen_reasoning_output_tokens")
+            .unwrap_or(0) as u64,
+        last_seen_total_tokens: row.try_get::<i64, _>("last_seen_total_tokens").unwrap_or(0) as u64,
+        last_committed_input_tokens: row
+            .try_get::<i64, _>("last_committed_input_tokens")
+            .unwrap_or(0) as u64,
+        last_committed_cached_input_tokens: row
+            .try_get::<i64, _>("last_committed_cached_input_tokens")
+            .unwrap_or(0) as u64,
+        last_committed_output_tokens: row
+            .try_get::<i64, _>("last_committed_output_tokens")
+            .unwrap_or(0) as u64,
+        last_committed_reasoning_output_tokens: row
+            .try_get::<i64, _>("last_committed_reasoning_output_tokens")
+            .unwrap_or(0) as u64,
+        last_committed_total_tokens: row
+            .try_get::<i64, _>("last_committed_total_tokens")
+            .unwrap_or(0) as u64,
+        current_message_id: row.try_get::<Option<i64>, _>("current_message_id")?,
+        current_message_seq: row.try_get::<i64, _>("current_message_seq").unwrap_or(0) as u64,
+        current_model: row.try_get::<Option<String>, _>("current_model")?,
+        current_effort: row.try_get::<Option<String>, _>("current_effort")?,
+        current_service_tier: row.try_get::<Option<String>, _>("current_service_tier")?,
+        current_approval_policy: row.try_get::<Option<String>, _>("current_approval_policy")?,
+        current_sandbox_policy: row.try_get::<Option<String>, _>("current_sandbox_policy")?,
+        current_cwd: row.try_get::<Option<String>, _>("current_cwd")?,
+        completed: row.try_get::<i64, _>("completed").unwrap_or(0) != 0,
+        device: row
+            .try_get::<Option<i64>, _>("device")?
+            .map(|value| value as u64),
+        inode: row
+            .try_get::<Option<i64>, _>("inode")?
+            .map(|value| value as u64),
+        head_fingerprint: row.try_get::<Option<String>, _>("head_fingerprint")?,
+    })
+}
+
+fn tool_count_row(row: &SqliteRow) -> Result<ToolCountRow> {
+    Ok(ToolCountRow {
+        tool: row.try_get("tool_name")?,
+        count: row.try_get::<i64, _>("call_count").unwrap_or(0) as u64,
+        finished: row.try_get::<i64, _>("finished").unwrap_or(0) as u64,
+        failed: row.try_get::<i64, _>("failed").unwrap_or(0) as u64,
+        duration_ms: row.try_get::<i64, _>("duration_ms").unwrap_or(0) as u64,
+        output_bytes: row.try_get::<i64, _>("output_bytes").unwrap_or(0) as u64,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct FailingCommandRow {
+    pub tool: String,
+    pub detail: String,
+    pub count: u64,
+    pub failed: u64,
+    pub duration_ms: u64,
+}
+
+/// One file touched by a patch and the lines it added and removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileLineChange {
+    pub path: String,
+    pub added: u64,
+    pub removed: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineChanges {
+    pub files: u64,
+    pub added: u64,
+    pub removed: u64,
+}
+
+impl LineChanges {
+    pub fn lines(self) -> u64 {
+        self.added.saturating_add(self.removed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChangeGroup {
+    Model,
+    Effort,
+    Repo,
+    /// Approval policy and sandbox mode, e.g. `on-request / workspace-write`.
+    Approval,
+}
+
+impl LineChangeGroup {
+    fn key_expr(self) -> &'static str {
+        match self {
+            LineChangeGroup::Model => "t.model",
+            LineChangeGroup::Effort => "COALESCE(NULLIF(t.reasoning_effort, ''), 'default')",
+            LineChangeGroup::Repo => "COALESCE(NULLIF(s.repo_url, ''), NULLIF(s.cwd, ''))",
+            LineChangeGroup::Approval => {
+                "CASE WHEN t.approval_policy IS NULL AND t.sandbox_policy IS NULL THEN 'unknown' \
+                 ELSE COALESCE(t.approval_policy, 'unknown') || ' / ' \
+                 || COALESCE(t.sandbox_policy, 'unknown') END"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LineChangeCostRow {
+    pub key: String,
+    pub total_tokens: u64,
+    /// `None` when no turn in the group has a known price.
+    pub cost: Option<f64>,
+    /// Summed per turn, so a file changed in several turns counts each time.
+    pub files_changed: u64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+
+impl LineChangeCostRow {
+    pub fn lines(&self) -> u64 {
+        self.lines_added.saturating_add(self.lines_removed)
+    }
+
+    pub fn cost_per_100_lines(&self) -> Option<f64> {
+        let lines = self.lines();
+        if lines == 0 {
+            return None;
+        }
+        self.cost.map(|cost| cost * 100.0 / lines as f64)
+    }
+}
+
+/// One turn's token usage and the context it ran in.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NewTurn<'a> {
+    pub session_id: &'a str,
+    pub timestamp: DateTime<Utc>,
+    pub model: &'a str,
+    pub note: Option<&'a str>,
+    pub context_window: Option<u64>,
+    pub reasoning_effort: Option<&'a str>,
+    /// `None` for the default tier.
+    pub service_tier: Option<&'a str>,
+    pub policy: TurnPolicy<'a>,
+    pub prompt_tokens: u64,
+    pub cached_prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub total_tokens: u64,
+    pub message_id: Option<i64>,
+}
+
+/// Approval and sandbox settings a turn ran under, from the latest turn context.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TurnPolicy<'a> {
+    pub approval_policy: Option<&'a str>,
+    pub sandbox_policy: Option<&'a str>,
+    pub cwd: Option<&'a str>,
+}
+
+impl TurnPolicy<'_> {
+    fn is_empty(&self) -> bool {
+        self.approval_policy.is_none() && self.sandbox_policy.is_none() && self.cwd.is_none()
+    }
+}
+
+pub struct ToolOutcome<'a> {
+    pub call_id: &'a str,
+    pub exit_code: Option<i64>,
+    pub success: Option<bool>,
+    pub duration_ms: Option<u64>,
+    pub output_bytes: Option<u64>,
+}
+
+/// A session whose commits are looked up in its working directory.
+#[derive(Debug, Clone)]
+pub struct GitScanTarget {
+    pub session_id: String,
+    pub cwd: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub last_event_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitScanStatus {
+    pub status: String,
+    pub detail: Option<String>,
+}
+
+/// A commit authored while a session ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionCommit {
+    pub sha: String,
+    pub authored_at: DateTime<Utc>,
+    pub branch: Option<String>,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitCostRow {
+    pub sha: String,
+    pub subject: String,
+    pub repo: Option<String>,
+    pub sessions: u64,
+    pub total_tokens: u64,
+    /// `None` when no attributed turn has a known price.
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BranchCostRow {
+    pub repo: Option<String>,
+    pub branch: String,
+    pub sessions: u64,
+    pub commits: u64,
+    pub total_tokens: u64,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: String,
+    pub detail: Option<String>,
 }
 
 impl SessionTurn {
@@ -3145,19 +5847,69 @@ impl SessionMessage {
 #[derive(Debug, Clone)]
 pub struct PriceRow {
     pub model: String,
+    pub service_tier: String,
+    pub effective_from: NaiveDate,
+    pub prompt_per_1m: f64,
+    pub cached_prompt_per_1m: Option<f64>,
+    pub completion_per_1m: f64,
+    pub long_context: Option<LongContextPrice>,
+    pub source: PriceSource,
+}
+
+/// Rates charged for the whole turn once its prompt exceeds `threshold_tokens`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LongContextPrice {
+    pub threshold_tokens: u64,
     pub prompt_per_1m: f64,
     pub cached_prompt_per_1m: Option<f64>,
     pub completion_per_1m: f64,
 }
 
+impl LongContextPrice {
+    fn threshold_i64(self) -> i64 {
+        i64::try_from(self.threshold_tokens).unwrap_or(i64::MAX)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSource {
+    Remote,
+    Override,
+}
+
+impl PriceSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            PriceSource::Remote => "remote",
+            PriceSource::Override => "override",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NewPrice {
     pub model: String,
+    pub service_tier: String,
     pub effective_from: NaiveDate,
     pub currency: String,
     pub prompt_per_1m: f64,
     pub cached_prompt_per_1m: Option<f64>,
     pub completion_per_1m: f64,
+    pub long_context: Option<LongContextPrice>,
+}
+
+/// A configured price for a model prefix; without `effective_from` it also
+/// applies to all past usage.
+#[derive(Debug, Clone)]
+pub struct PriceOverride {
+    pub model: String,
+    pub service_tier: String,
+    pub effective_from: Option<NaiveDate>,
+    pub currency: String,
+    pub prompt_per_1m: f64,
+    pub cached_prompt_per_1m: Option<f64>,
+    pub completion_per_1m: f64,
+    pub long_context: Option<LongContextPrice>,
 }
 
 #[derive(Debug, Clone)]
@@ -3169,6 +5921,7 @@ pub struct PricingMeta {
 #[derive(Debug, Clone)]
 pub struct MissingPriceDetail {
     pub model: String,
+    pub service_tier: String,
     pub first_seen: NaiveDate,
     pub last_seen: NaiveDate,
 }
@@ -3196,14 +5949,37 @@ pub struct DailyTokenTotal {
 pub struct ModelCostTotal {
     pub model: String,
     pub total_tokens: u64,
-    pub cost_usd: f64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RootCostTotal {
+    pub label: String,
+    pub total_tokens: u64,
+    pub cost: f64,
 }
 
 #[derive(Debug, Clone)]
 pub struct RepoCostTotal {
     pub repo: String,
     pub total_tokens: u64,
-    pub cost_usd: f64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitWindow {
+    pub used_percent: f64,
+    pub window_minutes: Option<u64>,
+    pub resets_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitSnapshot {
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub primary: Option<RateLimitWindow>,
+    pub secondary: Option<RateLimitWindow>,
+    pub plan_type: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -3231,6 +6007,7 @@ pub struct SessionMeta {
     pub model_provider: Option<String>,
     pub subagent: Option<String>,
     pub last_model: Option<String>,
+    pub root_label: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -3252,6 +6029,79 @@ pub struct IngestStateRow {
     pub current_message_seq: u64,
     pub current_model: Option<String>,
     pub current_effort: Option<String>,
+    pub current_service_tier: Option<String>,
+    pub current_approval_policy: Option<String>,
+    pub current_sandbox_policy: Option<String>,
+    pub current_cwd: Option<String>,
+    /// Set once an immutable (compressed) file has been read in full.
+    pub completed: bool,
+    pub device: Option<u64>,
+    pub inode: Option<u64>,
+    /// Hash of the file's first line, used to recognise it after a move.
+    pub head_fingerprint: Option<String>,
+}
+
+/// Progress of the ingestor's initial scan over existing session logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackfillProgress {
+    pub files_total: u64,
+    pub files_done: u64,
+    pub bytes_total: u64,
+    pub bytes_done: u64,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct NewUsageMismatch<'a> {
+    pub session_id: &'a str,
+    pub timestamp: DateTime<Utc>,
+    pub model: &'a str,
+    /// Comma-separated token counters that disagree, e.g. `input,total`.
+    pub fields: &'a str,
+    /// Total tokens of the request as reported by the log.
+    pub reported_tokens: u64,
+    /// Total tokens derived from the cumulative snapshots.
+    pub cumulative_tokens: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UsageMismatchRow {
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub model: String,
+    pub fields: String,
+    pub reported_tokens: u64,
+    pub cumulative_tokens: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UsageMismatchSummary {
+    pub count: u64,
+    pub sessions: u64,
+    pub reported_tokens: u64,
+    pub cumulative_tokens: u64,
+}
+
+pub struct NewIngestError<'a> {
+    pub path: &'a Path,
+    pub kind: &'a str,
+    /// Byte offset of the offending line (in the decompressed stream for
+    /// compressed files).
+    pub offset: u64,
+    pub message: &'a str,
+    pub sample: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestErrorRow {
+    pub path: PathBuf,
+    pub kind: String,
+    pub offset: u64,
+    pub message: String,
+    pub sample: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub count: u64,
 }
 
 fn map_session_turn_rows(rows: Vec<SqliteRow>, label: &str) -> Result<Vec<SessionTurn>> {
@@ -3278,7 +6128,7 @@ fn map_session_turn_rows(rows: Vec<SqliteRow>, label: &str) -> Result<Vec<Sessio
             completion_tokens: row.try_get::<i64, _>("completion_tokens").unwrap_or(0) as u64,
             total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
             reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0) as u64,
-            cost_usd: cost_from_row(&row),
+            cost: cost_from_row(&row),
             usage_included: true,
         });
     }
@@ -3286,6 +6136,231 @@ fn map_session_turn_rows(rows: Vec<SqliteRow>, label: &str) -> Result<Vec<Sessio
     Ok(turns)
 }
 
+/// One price version keyed by model prefix, from either `prices` or
+/// `price_overrides`. Rates are `None` for the placeholder of an unpriced model.
+#[derive(Debug, Clone, PartialEq)]
+struct PriceVersion {
+    model: String,
+    service_tier: String,
+    effective_from: String,
+    prompt_per_1m: Option<f64>,
+    cached_prompt_per_1m: Option<f64>,
+    completion_per_1m: Option<f64>,
+    long_context: Option<LongContextPrice>,
+}
+
+async fn load_price_versions_tx(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    table: &str,
+) -> Result<Vec<PriceVersion>> {
+    let query = format!(
+        "SELECT model, service_tier, effective_from, prompt_per_1m, cached_prompt_per_1m, \
+         completion_per_1m, \
+         long_context_threshold, long_prompt_per_1m, long_cached_prompt_per_1m, \
+         long_completion_per_1m \
+         FROM {table} ORDER BY model ASC, effective_from ASC"
+    );
+    let rows = sqlx::query(&query)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("failed to load {table}"))?;
+    let mut versions = Vec::with_capacity(rows.len());
+    for row in rows {
+        versions.push(PriceVersion {
+            model: row.try_get("model")?,
+            service_tier: row.try_get("service_tier")?,
+            effective_from: row.try_get("effective_from")?,
+            prompt_per_1m: row.try_get("prompt_per_1m")?,
+            cached_prompt_per_1m: row.try_get("cached_prompt_per_1m")?,
+            completion_per_1m: row.try_get("completion_per_1m")?,
+            long_context: long_context_price_from_row(&row)?,
+        });
+    }
+    Ok(versions)
+}
+
+async fn insert_model_prices_tx(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    model: &str,
+    service_tier: &str,
+    versions: &[PriceVersion],
+) -> Result<()> {
+    for version in versions {
+        sqlx::query(
+            r#"
+            INSERT INTO model_prices (
+                model, service_tier, effective_from, prompt_per_1m, cached_prompt_per_1m,
+                completion_per_1m, long_context_threshold, long_prompt_per_1m,
+                long_cached_prompt_per_1m, long_completion_per_1m
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(model)
+        .bind(service_tier)
+        .bind(&version.effective_from)
+        .bind(version.prompt_per_1m)
+        .bind(version.cached_prompt_per_1m)
+        .bind(version.completion_per_1m)
+        .bind(version.long_context.map(|tier| tier.threshold_i64()))
+        .bind(version.long_context.map(|tier| tier.prompt_per_1m))
+        .bind(
+            version
+                .long_context
+                .and_then(|tier| tier.cached_prompt_per_1m),
+        )
+        .bind(version.long_context.map(|tier| tier.completion_per_1m))
+        .execute(&mut **tx)
+        .await
+        .with_context(|| "failed to insert model price")?;
+    }
+    Ok(())
+}
+
+/// Picks the price versions for `model` on `service_tier` from the prices
+/// published for that tier only. A tier nobody prices gets the NULL
+/// placeholder, so its turns surface as missing prices rather than being
+/// billed at another tier's rates.
+fn resolve_tier_price_versions(
+    model: &str,
+    service_tier: &str,
+    remote: &[PriceVersion],
+    overrides: &[PriceVersion],
+) -> Vec<PriceVersion> {
+    let for_tier = |versions: &[PriceVersion]| -> Vec<PriceVersion> {
+        versions
+            .iter()
+            .filter(|version| version.service_tier == service_tier)
+            .cloned()
+            .collect()
+    };
+    resolve_model_price_versions(model, &for_tier(remote), &for_tier(overrides))
+}
+
+/// Picks the price versions that apply to `model` within one service tier. Any matching override wins
+/// over remote prices: remote versions only cover dates before the first
+/// override version. Within each source the longest matching prefix is used.
+/// Dates not covered by any version get a NULL placeholder so they surface as
+/// missing prices.
+fn resolve_model_price_versions(
+    model: &str,
+    remote: &[PriceVersion],
+    overrides: &[PriceVersion],
+) -> Vec<PriceVersion> {
+    let override_versions = longest_prefix_versions(model, overrides);
+    let mut versions: Vec<PriceVersion> = match override_versions.first() {
+        Some(first) => longest_prefix_versions(model, remote)
+            .into_iter()
+            .filter(|version| version.effective_from < first.effective_from)
+            .chain(override_versions.iter().cloned())
+            .collect(),
+        None => longest_prefix_versions(model, remote),
+    };
+
+    if versions
+        .first()
+        .is_none_or(|version| version.effective_from.as_str() > PRICE_BASELINE_DATE)
+    {
+        versions.insert(
+            0,
+            PriceVersion {
+                model: model.to_string(),
+                service_tier: DEFAULT_SERVICE_TIER.to_string(),
+                effective_from: PRICE_BASELINE_DATE.to_string(),
+                prompt_per_1m: None,
+                cached_prompt_per_1m: None,
+                completion_per_1m: None,
+                long_context: None,
+            },
+        );
+    }
+    versions
+}
+
+/// Versions of the longest prefix of `model` found in `versions` (matched
+/// case-insensitively), oldest first.
+fn longest_prefix_versions(model: &str, versions: &[PriceVersion]) -> Vec<PriceVersion> {
+    let model = model.to_ascii_lowercase();
+    let Some(prefix) = versions
+        .iter()
+        .map(|version| version.model.as_str())
+        .filter(|prefix| model.starts_with(&prefix.to_ascii_lowercase()))
+        .max_by_key(|prefix| prefix.len())
+    else {
+        return Vec::new();
+    };
+    versions
+        .iter()
+        .filter(|version| version.model == prefix)
+        .cloned()
+        .collect()
+}
+
+fn rates_match(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+fn optional_rates_match(a: Option<f64>, b: Option<f64>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => rates_match(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn long_context_price_from_row(row: &SqliteRow) -> Result<Option<LongContextPrice>> {
+    let threshold: Option<i64> = row.try_get("long_context_threshold")?;
+    let prompt: Option<f64> = row.try_get("long_prompt_per_1m")?;
+    let completion: Option<f64> = row.try_get("long_completion_per_1m")?;
+    let (Some(threshold), Some(prompt_per_1m), Some(completion_per_1m)) =
+        (threshold, prompt, completion)
+    else {
+        return Ok(None);
+    };
+    Ok(Some(LongContextPrice {
+        threshold_tokens: u64::try_from(threshold).unwrap_or(0),
+        prompt_per_1m,
+        cached_prompt_per_1m: row.try_get("long_cached_prompt_per_1m")?,
+        completion_per_1m,
+    }))
+}
+
+fn rate_limit_snapshot_from_row(row: &SqliteRow) -> Result<Option<RateLimitSnapshot>> {
+    let timestamp_str: String = row.try_get("timestamp")?;
+    let Ok(timestamp) = DateTime::parse_from_rfc3339(&timestamp_str) else {
+        return Ok(None);
+    };
+    Ok(Some(RateLimitSnapshot {
+        session_id: row.try_get("session_id")?,
+        timestamp: timestamp.with_timezone(&Utc),
+        primary: rate_limit_window_from_row(row, "primary"),
+        secondary: rate_limit_window_from_row(row, "secondary"),
+        plan_type: row.try_get("plan_type").ok().flatten(),
+    }))
+}
+
+fn rate_limit_window_from_row(row: &SqliteRow, prefix: &str) -> Option<RateLimitWindow> {
+    let used_percent: f64 = row
+        .try_get::<Option<f64>, _>(format!("{prefix}_used_percent").as_str())
+        .ok()
+        .flatten()?;
+    let window_minutes = row
+        .try_get::<Option<i64>, _>(format!("{prefix}_window_minutes").as_str())
+        .ok()
+        .flatten()
+        .and_then(|v| u64::try_from(v).ok());
+    let resets_at = row
+        .try_get::<Option<String>, _>(format!("{prefix}_resets_at").as_str())
+        .ok()
+        .flatten()
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+    Some(RateLimitWindow {
+        used_percent,
+        window_minutes,
+        resets_at,
+    })
+}
+
 fn session_aggregates_from_rows(rows: Vec<SqliteRow>) -> Result<Vec<SessionAggregate>> {
     let mut aggregates = Vec::with_capacity(rows.len());
     for row in rows {
@@ -3309,21 +6384,44 @@ fn session_aggregates_from_rows(rows: Vec<SqliteRow>) -> Result<Vec<SessionAggre
             total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0) as u64,
             reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0) as u64,
             user_messages: row.try_get::<i64, _>("user_messages").unwrap_or(0) as u64,
-            cost_usd: cost_from_row(&row),
+            cost: cost_from_row(&row),
             title: row.try_get::<Option<String>, _>("title")?,
             last_summary: row.try_get::<Option<String>, _>("last_summary")?,
+            root_label: row.try_get::<Option<String>, _>("root_label")?,
+            tree: session_tree_node_from_row(&row),
         });
     }
 
     Ok(aggregates)
 }
 
+fn session_tree_node_from_row(row: &SqliteRow) -> Option<SessionTreeNode> {
+    let root_session_id = row.try_get::<String, _>("root_session_id").ok()?;
+    let missing = row.try_get::<i64, _>("tree_missing_price").unwrap_or(0);
+    Some(SessionTreeNode {
+        root_session_id,
+        depth: row.try_get::<i64, _>("depth").unwrap_or(0).max(0) as u32,
+        descendants: row.try_get::<i64, _>("descendants").unwrap_or(0).max(0) as u64,
+        tree_total_tokens: row
+            .try_get::<i64, _>("tree_total_tokens")
+            .unwrap_or(0)
+            .max(0) as u64,
+        tree_cost: (missing == 0).then(|| row.try_get::<f64, _>("tree_cost").unwrap_or(0.0)),
+    })
+}
+
+fn parse_rfc3339(value: &str, column: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .with_context(|| format!("invalid timestamp in {column}"))
+}
+
 fn cost_from_row(row: &SqliteRow) -> Option<f64> {
     let missing = row.try_get::<i64, _>("missing_price").unwrap_or(0);
     if missing > 0 {
         None
     } else {
-        Some(row.try_get::<f64, _>("cost_usd").unwrap_or(0.0))
+        Some(row.try_get::<f64, _>("cost").unwrap_or(0.0))
     }
 }
 
@@ -3334,7 +6432,7 @@ struct TotalsAccumulator {
     completion_tokens: i64,
     total_tokens: i64,
     reasoning_tokens: i64,
-    cost_usd: f64,
+    cost: f64,
     missing_price: i64,
 }
 
@@ -3345,7 +6443,7 @@ impl TotalsAccumulator {
         self.completion_tokens += other.completion_tokens;
         self.total_tokens += other.total_tokens;
         self.reasoning_tokens += other.reasoning_tokens;
-        self.cost_usd += other.cost_usd;
+        self.cost += other.cost;
         self.missing_price += other.missing_price;
     }
 }
@@ -3357,7 +6455,7 @@ fn totals_from_row(row: &SqliteRow) -> TotalsAccumulator {
         completion_tokens: row.try_get::<i64, _>("completion_tokens").unwrap_or(0),
         total_tokens: row.try_get::<i64, _>("total_tokens").unwrap_or(0),
         reasoning_tokens: row.try_get::<i64, _>("reasoning_tokens").unwrap_or(0),
-        cost_usd: row.try_get::<f64, _>("cost_usd").unwrap_or(0.0),
+        cost: row.try_get::<f64, _>("cost").unwrap_or(0.0),
         missing_price: row.try_get::<i64, _>("missing_price").unwrap_or(0),
     }
 }
@@ -3439,7 +6537,9 @@ fn split_range_local(start: DateTime<Utc>, end: DateTime<Utc>) -> RangeParts {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::FxConfig;
     use chrono::{Duration as ChronoDuration, TimeZone};
+    use std::collections::BTreeMap;
     use tempfile::NamedTempFile;
 
     async fn seed_price(
@@ -3452,14 +6552,16 @@ mod tests {
     ) {
         let price = NewPrice {
             model: model.to_string(),
+            service_tier: DEFAULT_SERVICE_TIER.to_string(),
             effective_from,
             currency: "USD".to_string(),
             prompt_per_1m,
             cached_prompt_per_1m,
             completion_per_1m,
+            long_context: None,
         };
         storage
-            .replace_prices(&[price], "test", Utc::now())
+            .sync_prices(&[price], "test", Utc::now())
             .await
             .unwrap();
     }
@@ -3500,18 +6602,21 @@ mod tests {
         storage
             .record_turn_tx(
                 &mut tx,
-                session_id,
-                timestamp,
-                model,
-                note,
-                context_window,
-                reasoning_effort,
-                prompt_tokens,
-                cached_prompt_tokens,
-                completion_tokens,
-                reasoning_tokens,
-                total_tokens,
-                message_id,
+                &NewTurn {
+                    session_id,
+                    timestamp,
+                    model,
+                    note,
+                    context_window,
+                    reasoning_effort,
+                    prompt_tokens,
+                    cached_prompt_tokens,
+                    completion_tokens,
+                    reasoning_tokens,
+                    total_tokens,
+                    message_id,
+                    ..NewTurn::default()
+                },
             )
             .await
             .unwrap();
@@ -3530,25 +6635,14 @@ mod tests {
         let ts = Utc.from_utc_datetime(&day.and_hms_opt(12, 0, 0).unwrap());
         let local_day = ts.with_timezone(&Local).date_naive();
         record_turn_for_test(
-            &storage,
-            "sess-1",
-            ts,
-            "gpt-test",
-            None,
-            None,
-            None,
-            1_000_000,
-            200_000,
-            300_000,
-            50_000,
-            1_300_000,
-            None,
+            &storage, "sess-1", ts, "gpt-test", None, None, None, 1_000_000, 200_000, 300_000,
+            50_000, 1_300_000, None,
         )
         .await;
 
         let row = sqlx::query(
             r#"
-            SELECT COALESCE(SUM(cost_usd), 0.0) AS cost_usd
+            SELECT COALESCE(SUM(cost_usd), 0.0) AS cost
             FROM daily_stats_costs
             WHERE date = ?
             "#,
@@ -3558,7 +6652,7 @@ mod tests {
         .await
         .unwrap();
         let expected = calc_cost(1_000_000, 200_000, 300_000, 1.0, Some(0.5), 2.0);
-        let cost = row.try_get::<f64, _>("cost_usd").unwrap_or(0.0);
+        let cost = row.try_get::<f64, _>("cost").unwrap_or(0.0);
         assert!((cost - expected).abs() < 1e-9);
     }
 
@@ -3613,4 +6707,398 @@ mod tests {
         assert_eq!(rows[0].session_id, "sess-b");
         assert_eq!(rows[1].session_id, "sess-a");
     }
+
+    #[tokio::test]
+    async fn price_versions_apply_from_effective_date() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let change_day = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        seed_price(&storage, "gpt-test", change_day, 1.0, None, 2.0).await;
+        seed_price(&storage, "gpt-test", change_day, 3.0, None, 6.0).await;
+
+        let before = Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(2025, 12, 10)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+        );
+        let after = Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(2025, 12, 20)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+        );
+        for (session_id, ts) in [("sess-old", before), ("sess-new", after)] {
+            record_turn_for_test(
+                &storage, session_id, ts, "gpt-test", None, None, None, 1_000_000, 0, 0, 0,
+                1_000_000, None,
+            )
+            .await;
+        }
+
+        let cost_for = |session_id: &'static str| {
+            let storage = storage.clone();
+            async move {
+                let row = sqlx::query(
+                    "SELECT SUM(cost_usd) AS cost FROM session_turn_costs WHERE session_id = ?",
+                )
+                .bind(session_id)
+                .fetch_one(&*storage.pool)
+                .await
+                .unwrap();
+                row.try_get::<f64, _>("cost").unwrap()
+            }
+        };
+        assert!((cost_for("sess-old").await - 1.0).abs() < 1e-9);
+        assert!((cost_for("sess-new").await - 3.0).abs() < 1e-9);
+
+        let daily = storage
+            .totals_between_timestamps(
+                local_start_of_day(before.with_timezone(&Local).date_naive()),
+                local_start_of_day(
+                    after.with_timezone(&Local).date_naive() + ChronoDuration::days(1),
+                ),
+            )
+            .await
+            .unwrap();
+        assert!((daily.cost.unwrap() - 4.0).abs() < 1e-9);
+
+        // Re-syncing unchanged rates must not add a version or reprice history.
+        let unchanged = NewPrice {
+            model: "gpt-test".to_string(),
+            service_tier: DEFAULT_SERVICE_TIER.to_string(),
+            effective_from: change_day + ChronoDuration::days(30),
+            currency: "USD".to_string(),
+            prompt_per_1m: 3.0,
+            cached_prompt_per_1m: None,
+            completion_per_1m: 6.0,
+            long_context: None,
+        };
+        let changed = storage
+            .sync_prices(&[unchanged], "test", Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(changed, 0);
+        assert_eq!(storage.prices_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn exchange_rates_apply_from_their_date() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let day = |d| NaiveDate::from_ymd_opt(2025, 12, d).unwrap();
+        seed_price(&storage, "gpt-test", day(1), 1.0, None, 1.0).await;
+        let before = Utc.from_utc_datetime(&day(10).and_hms_opt(12, 0, 0).unwrap());
+        let after = Utc.from_utc_datetime(&day(20).and_hms_opt(12, 0, 0).unwrap());
+        for (session_id, ts) in [("sess-old", before), ("sess-new", after)] {
+            record_turn_for_test(
+                &storage, session_id, ts, "gpt-test", None, None, None, 1_000_000, 0, 0, 0,
+                1_000_000, None,
+            )
+            .await;
+        }
+        let start = local_start_of_day(before.with_timezone(&Local).date_naive());
+        let end = local_start_of_day(after.with_timezone(&Local).date_naive())
+            + ChronoDuration::hours(13);
+
+        let rates_file = NamedTempFile::new().unwrap();
+        std::fs::write(
+            rates_file.path(),
+            "date,currency,rate\n2025-12-15,EUR,0.9\n2025-12-25,EUR,2.0\n",
+        )
+        .unwrap();
+        let fx = FxConfig {
+            display_currency: Some("EUR".to_string()),
+            rates: BTreeMap::from([("EUR".to_string(), 0.5)]),
+            rates_file: Some(rates_file.path().to_path_buf()),
+        };
+        let eur = Storage::connect(db_file.path())
+            .await
+            .unwrap()
+            .with_display_currency(DisplayCurrency::from_config(&fx).unwrap());
+
+        // Each day uses its own rate; the rate from the 25th applies to neither.
+        let totals = eur.totals_between_timestamps(start, end).await.unwrap();
+        assert!((totals.cost.unwrap() - 1.4).abs() < 1e-9);
+        let old = eur.session_turn_totals("sess-old").await.unwrap();
+        assert!((old.cost.unwrap() - 0.5).abs() < 1e-9);
+
+        // Another process on the same database still reads USD.
+        let totals = storage.totals_between_timestamps(start, end).await.unwrap();
+        assert!((totals.cost.unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn price_overrides_win_over_remote_prices() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let override_day = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        seed_price(&storage, "gpt-test", override_day, 1.0, None, 2.0).await;
+        let at_noon = |date: NaiveDate| Utc.from_utc_datetime(&date.and_hms_opt(12, 0, 0).unwrap());
+        for (session_id, ts) in [
+            ("sess-old", at_noon(override_day - ChronoDuration::days(5))),
+            ("sess-new", at_noon(override_day + ChronoDuration::days(5))),
+            (
+                "sess-custom",
+                at_noon(override_day - ChronoDuration::days(5)),
+            ),
+        ] {
+            let model = if session_id == "sess-custom" {
+                "custom-model-large"
+            } else {
+                "gpt-test-mini"
+            };
+            record_turn_for_test(
+                &storage, session_id, ts, model, None, None, None, 1_000_000, 0, 0, 0, 1_000_000,
+                None,
+            )
+            .await;
+        }
+
+        storage
+            .replace_price_overrides(&[
+                PriceOverride {
+                    model: "gpt-test".to_string(),
+                    service_tier: DEFAULT_SERVICE_TIER.to_string(),
+                    effective_from: Some(override_day),
+                    currency: "USD".to_string(),
+                    prompt_per_1m: 5.0,
+                    cached_prompt_per_1m: None,
+                    completion_per_1m: 10.0,
+                    long_context: None,
+                },
+                PriceOverride {
+                    model: "custom-model".to_string(),
+                    service_tier: DEFAULT_SERVICE_TIER.to_string(),
+                    effective_from: None,
+                    currency: "USD".to_string(),
+                    prompt_per_1m: 7.0,
+                    cached_prompt_per_1m: None,
+                    completion_per_1m: 9.0,
+                    long_context: None,
+                },
+            ])
+            .await
+            .unwrap();
+        // A later remote change must not replace the override.
+        seed_price(
+            &storage,
+            "gpt-test",
+            override_day + ChronoDuration::days(1),
+            2.0,
+            None,
+            4.0,
+        )
+        .await;
+
+        for (session_id, expected) in [("sess-old", 1.0), ("sess-new", 5.0), ("sess-custom", 7.0)] {
+            let cost: f64 = sqlx::query_scalar(
+                "SELECT SUM(cost_usd) FROM session_turn_costs WHERE session_id = ?",
+            )
+            .bind(session_id)
+            .fetch_one(&*storage.pool)
+            .await
+            .unwrap();
+            assert!((cost - expected).abs() < 1e-9, "{session_id}: {cost}");
+        }
+
+        let prices = storage.list_prices().await.unwrap();
+        let sources: Vec<(&str, PriceSource)> = prices
+            .iter()
+            .map(|price| (price.model.as_str(), price.source))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("custom-model", PriceSource::Override),
+                ("gpt-test", PriceSource::Override),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn long_context_rates_apply_per_turn() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let day = NaiveDate::from_ymd_opt(2025, 12, 10).unwrap();
+        let at = |hour: u32| Utc.from_utc_datetime(&day.and_hms_opt(hour, 0, 0).unwrap());
+        // Recorded before any price exists, so the tier split comes from the
+        // rebuild that runs on sync.
+        record_turn_for_test(
+            &storage,
+            "sess-a",
+            at(10),
+            "gpt-test",
+            None,
+            None,
+            None,
+            300_000,
+            100_000,
+            10_000,
+            0,
+            310_000,
+            None,
+        )
+        .await;
+
+        let price = NewPrice {
+            model: "gpt-test".to_string(),
+
+            service_tier: DEFAULT_SERVICE_TIER.to_string(),
+            effective_from: day,
+            currency: "USD".to_string(),
+            prompt_per_1m: 1.0,
+            cached_prompt_per_1m: Some(0.1),
+            completion_per_1m: 10.0,
+            long_context: Some(LongContextPrice {
+                threshold_tokens: 200_000,
+                prompt_per_1m: 2.0,
+                cached_prompt_per_1m: Some(0.2),
+                completion_per_1m: 20.0,
+            }),
+        };
+        storage
+            .sync_prices(&[price], "test", Utc::now())
+            .await
+            .unwrap();
+
+        record_turn_for_test(
+            &storage,
+            "sess-a",
+            at(11),
+            "gpt-test",
+            None,
+            None,
+            None,
+            100_000,
+            50_000,
+            10_000,
+            0,
+            110_000,
+            None,
+        )
+        .await;
+        record_turn_for_test(
+            &storage,
+            "sess-b",
+            at(12),
+            "gpt-test",
+            None,
+            None,
+            None,
+            250_000,
+            0,
+            0,
+            0,
+            250_000,
+            None,
+        )
+        .await;
+
+        // Long turns: 200k*2 + 100k*0.2 + 10k*20 = 0.62; 250k*2 = 0.5.
+        // Short turn: 50k*1 + 50k*0.1 + 10k*10 = 0.155.
+        let expected = 0.62 + 0.5 + 0.155;
+        let turn_cost: f64 = sqlx::query_scalar("SELECT SUM(cost_usd) FROM session_turn_costs")
+            .fetch_one(&*storage.pool)
+            .await
+            .unwrap();
+        assert!((turn_cost - expected).abs() < 1e-9, "{turn_cost}");
+
+        for view in ["session_daily_costs", "daily_stats_costs"] {
+            let cost: f64 = sqlx::query_scalar(&format!("SELECT SUM(cost_usd) FROM {view}"))
+                .fetch_one(&*storage.pool)
+                .await
+                .unwrap();
+            assert!((cost - expected).abs() < 1e-9, "{view}: {cost}");
+        }
+    }
+
+    #[tokio::test]
+    async fn service_tier_turns_use_tier_rates() {
+        let db_file = NamedTempFile::new().unwrap();
+        let storage = Storage::connect(db_file.path()).await.unwrap();
+        storage.ensure_schema().await.unwrap();
+
+        let day = NaiveDate::from_ymd_opt(2025, 12, 20).unwrap();
+        let price = |service_tier: &str, prompt_per_1m: f64| NewPrice {
+            model: "gpt-test".to_string(),
+            service_tier: service_tier.to_string(),
+            effective_from: day,
+            currency: "USD".to_string(),
+            prompt_per_1m,
+            cached_prompt_per_1m: None,
+            completion_per_1m: 0.0,
+            long_context: None,
+        };
+        storage
+            .sync_prices(
+                &[price("default", 2.0), price("flex", 1.0)],
+                "test",
+                Utc::now(),
+            )
+            .await
+            .unwrap();
+
+        let ts = Utc.from_utc_datetime(&day.and_hms_opt(12, 0, 0).unwrap());
+        for (session_id, service_tier) in [
+            ("sess-default", None),
+            ("sess-flex", Some("flex")),
+            ("sess-priority", Some("priority")),
+        ] {
+            let mut tx = storage.begin_tx().await.unwrap();
+            storage
+                .record_turn_tx(
+                    &mut tx,
+                    &NewTurn {
+                        session_id,
+                        timestamp: ts,
+                        model: "gpt-test",
+                        service_tier,
+                        prompt_tokens: 1_000_000,
+                        total_tokens: 1_000_000,
+                        ..NewTurn::default()
+                    },
+                )
+                .await
+                .unwrap();
+            tx.commit().await.unwrap();
+        }
+
+        // Priority has no price of its own, so its cost is unknown rather
+        // than billed at the default tier's rates.
+        for (session_id, expected) in [
+            ("sess-default", Some(2.0)),
+            ("sess-flex", Some(1.0)),
+            ("sess-priority", None),
+        ] {
+            let cost: Option<f64> = sqlx::query_scalar(
+                "SELECT SUM(cost_usd) FROM session_daily_costs WHERE session_id = ?",
+            )
+            .bind(session_id)
+            .fetch_one(&*storage.pool)
+            .await
+            .unwrap();
+            match (cost, expected) {
+                (Some(cost), Some(expected)) => {
+                    assert!((cost - expected).abs() < 1e-9, "{session_id}: {cost}")
+                }
+                (cost, expected) => assert_eq!(cost, expected, "{session_id}"),
+            }
+        }
+
+        let missing = storage.missing_price_details(10).await.unwrap();
+        let missing: Vec<(&str, &str)> = missing
+            .iter()
+            .map(|detail| (detail.model.as_str(), detail.service_tier.as_str()))
+            .collect();
+        assert_eq!(missing, [("gpt-test", "priority")]);
+    }
 }
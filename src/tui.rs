@@ -1,9 +1,14 @@
 use crate::{
     config::{AppConfig, PricingConfig},
+    fx, git,
+    ingest::TOKEN_RESET_EVENT,
     pricing_remote,
     storage::{
-        AggregateTotals, DailyTokenTotal, MissingPriceDetail, ModelUsageRow, PriceRow, PricingMeta,
-        SessionAggregate, SessionMessage, SessionTurn, Storage, ToolCountRow, TopModelShare,
+        AggregateTotals, BackfillProgress, DEFAULT_SERVICE_TIER, DailyTokenTotal,
+        FailingCommandRow, GitScanStatus, LineChangeCostRow, LineChangeGroup, LineChanges,
+        LongContextPrice, MissingPriceDetail, ModelUsageRow, PriceRow, PricingMeta,
+        RateLimitSnapshot, RateLimitWindow, SessionAggregate, SessionCommit, SessionEvent,
+        SessionMessage, SessionTreeOrder, SessionTurn, Storage, ToolCountRow, TopModelShare,
     },
 };
 use anyhow::Result;
@@ -75,12 +80,22 @@ const STREAK_COLORS: [Color; 7] = [
     Color::Rgb(210, 140, 55),
     Color::Rgb(235, 165, 70),
 ];
+const RATE_LIMIT_HISTORY_HOURS: i64 = 24;
+const RATE_LIMIT_MIN_BURN_SPAN_SECS: i64 = 5 * 60;
 const SUMMARY_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
 const RECENT_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
 const STATS_REFRESH_INTERVAL: Duration = Duration::from_millis(3000);
+const TOOLS_REFRESH_INTERVAL: Duration = Duration::from_millis(3000);
+const FAILING_COMMANDS_LIMIT: usize = 10;
+const LINE_COST_ROWS_PER_GROUP: usize = 5;
+const MODAL_COMMITS_LIMIT: usize = 5;
+const APPROVAL_ROWS_LIMIT: usize = 6;
 const PRICING_REFRESH_INTERVAL: Duration = Duration::from_millis(8000);
 const MODAL_MESSAGES_REFRESH_INTERVAL: Duration = Duration::from_millis(1000);
 const WRAPPED_REFRESH_INTERVAL: Duration = Duration::from_millis(8000);
+/// The ingestor refreshes backfill progress every second; older progress was
+/// left behind by one that stopped.
+const BACKFILL_STALE_SECS: i64 = 30;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 enum ViewMode {
@@ -88,6 +103,7 @@ enum ViewMode {
     Sessions,
     Stats,
     Pricing,
+    Tools,
 }
 
 impl ViewMode {
@@ -96,13 +112,14 @@ impl ViewMode {
             ViewMode::Overview => ViewMode::Sessions,
             ViewMode::Sessions => ViewMode::Stats,
             ViewMode::Stats => ViewMode::Pricing,
-            ViewMode::Pricing => ViewMode::Overview,
+            ViewMode::Pricing => ViewMode::Tools,
+            ViewMode::Tools => ViewMode::Overview,
         }
     }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-enum TimeRange {
+pub(crate) enum TimeRange {
     Day,
     Week,
     Month,
@@ -224,12 +241,16 @@ struct UiDataCache {
     sessions_offset: usize,
     sessions_limit: usize,
     sessions_last: Option<Instant>,
-    sessions_key: Option<(TimeRange, NaiveDate, SessionSort)>,
+    sessions_key: Option<(TimeRange, NaiveDate, SessionSort, u64)>,
     sessions_ingest_at: Option<DateTime<Utc>>,
     stats_data: Option<StatsRangeData>,
     stats_last: Option<Instant>,
     stats_key: Option<(TimeRange, NaiveDate)>,
     stats_ingest_at: Option<DateTime<Utc>>,
+    tools_data: Option<ToolStatsData>,
+    tools_last: Option<Instant>,
+    tools_key: Option<(TimeRange, NaiveDate)>,
+    tools_ingest_at: Option<DateTime<Utc>>,
     pricing_rows: Vec<PriceRow>,
     pricing_missing: Vec<MissingPriceDetail>,
     pricing_meta: Option<PricingMeta>,
@@ -239,6 +260,8 @@ struct UiDataCache {
     last_event: Option<DateTime<Utc>>,
     ingest_last: Option<Instant>,
     ingest_flash: Option<Instant>,
+    ingest_errors: u64,
+    backfill: Option<BackfillProgress>,
     wrapped_data: Option<WrappedStats>,
     wrapped_year: Option<i32>,
     wrapped_last: Option<Instant>,
@@ -253,6 +276,10 @@ struct UiDataCache {
     modal_turn_totals: Option<AggregateTotals>,
     modal_model_mix: Vec<ModelUsageRow>,
     modal_tool_counts: Vec<ToolCountRow>,
+    modal_events: Vec<SessionEvent>,
+    modal_line_changes: LineChanges,
+    modal_commits: Vec<SessionCommit>,
+    modal_git_scan: Option<GitScanStatus>,
     modal_ingest_at: Option<DateTime<Utc>>,
     modal_key: Option<String>,
     modal_last: Option<Instant>,
@@ -276,6 +303,10 @@ impl UiDataCache {
             stats_last: None,
             stats_key: None,
             stats_ingest_at: None,
+            tools_data: None,
+            tools_last: None,
+            tools_key: None,
+            tools_ingest_at: None,
             pricing_rows: Vec::new(),
             pricing_missing: Vec::new(),
             pricing_meta: None,
@@ -285,6 +316,8 @@ impl UiDataCache {
             last_event: None,
             ingest_last: None,
             ingest_flash: None,
+            ingest_errors: 0,
+            backfill: None,
             wrapped_data: None,
             wrapped_year: None,
             wrapped_last: None,
@@ -299,6 +332,10 @@ impl UiDataCache {
             modal_turn_totals: None,
             modal_model_mix: Vec::new(),
             modal_tool_counts: Vec::new(),
+            modal_events: Vec::new(),
+            modal_line_changes: LineChanges::default(),
+            modal_commits: Vec::new(),
+            modal_git_scan: None,
             modal_ingest_at: None,
             modal_key: None,
             modal_last: None,
@@ -332,6 +369,9 @@ impl UiDataCache {
             ViewMode::Pricing => {
                 self.pricing_last = None;
             }
+            ViewMode::Tools => {
+                self.tools_last = None;
+            }
         }
     }
 
@@ -379,12 +419,23 @@ impl UiDataCache {
         view: &mut SessionsViewState,
         max_limit: usize,
     ) {
-        let key = (view.nav.range, view.nav.anchor, view.sort);
+        let key = (
+            view.nav.range,
+            view.nav.anchor,
+            view.sort,
+            view.expanded_rev,
+        );
         let key_changed = self.sessions_key != Some(key);
         if key_changed {
+            // Expanding or collapsing a tree keeps the selection in place.
+            let tree_toggled = self
+                .sessions_key
+                .is_some_and(|last| (last.0, last.1, last.2) == (key.0, key.1, key.2));
             self.sessions_key = Some(key);
             self.sessions_last = None;
-            view.reset();
+            if !tree_toggled {
+                view.reset();
+            }
         }
 
         let ingest_changed =
@@ -394,7 +445,11 @@ impl UiDataCache {
         let refresh_active = ingest_active && refresh_due;
         let period = period_for_range(view.nav.range, view.nav.anchor, now_local);
         if ingest_changed || self.sessions_total == 0 || key_changed || refresh_active {
-            match runtime.block_on(storage.sessions_count_between(period.start, period.end)) {
+            match runtime.block_on(storage.session_tree_rows_count_between(
+                period.start,
+                period.end,
+                &view.expanded,
+            )) {
                 Ok(total) => self.sessions_total = total,
                 Err(err) => tracing::warn!(error = %err, "failed to count sessions"),
             }
@@ -403,13 +458,12 @@ impl UiDataCache {
         let (offset, limit) = sessions_window_for(view, self.sessions_total, max_limit);
         let window_changed = offset != self.sessions_offset || limit != self.sessions_limit;
 
-        if !(
-            ingest_changed
-                || key_changed
-                || window_changed
-                || self.sessions_last.is_none()
-                || refresh_active
-        ) {
+        if !(ingest_changed
+            || key_changed
+            || window_changed
+            || self.sessions_last.is_none()
+            || refresh_active)
+        {
             return;
         }
 
@@ -421,20 +475,18 @@ impl UiDataCache {
             self.sessions_last = Some(now);
             updated = true;
         } else {
-            let result = match view.sort {
-                SessionSort::Recent => runtime.block_on(storage.sessions_page_by_recent_between(
-                    period.start,
-                    period.end,
-                    offset,
-                    limit,
-                )),
-                SessionSort::Cost => runtime.block_on(storage.sessions_page_by_cost_between(
-                    period.start,
-                    period.end,
-                    offset,
-                    limit,
-                )),
+            let order = match view.sort {
+                SessionSort::Recent => SessionTreeOrder::Recent,
+                SessionSort::Cost => SessionTreeOrder::Cost,
             };
+            let result = runtime.block_on(storage.session_tree_page_between(
+                period.start,
+                period.end,
+                order,
+                &view.expanded,
+                offset,
+                limit,
+            ));
             match result {
                 Ok(rows) => {
                     self.sessions_rows = rows;
@@ -490,6 +542,43 @@ impl UiDataCache {
         }
     }
 
+    fn refresh_tools(
+        &mut self,
+        now: Instant,
+        now_local: DateTime<Local>,
+        runtime: &Handle,
+        storage: &Storage,
+        nav: &TimeNavState,
+    ) {
+        let key = (nav.range, nav.anchor);
+        let key_changed = self.tools_key != Some(key);
+        if key_changed {
+            self.tools_key = Some(key);
+            self.tools_last = None;
+        }
+        let ingest_changed = self.last_ingest.is_some() && self.tools_ingest_at != self.last_ingest;
+        let needs_refresh = self.tools_last.is_none() || key_changed || ingest_changed;
+        if !needs_refresh {
+            return;
+        }
+        if !Self::should_refresh(self.tools_last, TOOLS_REFRESH_INTERVAL, now) && !key_changed {
+            return;
+        }
+        let period = period_for_range(nav.range, nav.anchor, now_local);
+        let mut updated = false;
+        match runtime.block_on(ToolStatsData::gather(storage, &period)) {
+            Ok(data) => {
+                self.tools_data = Some(data);
+                updated = true;
+            }
+            Err(err) => tracing::warn!(error = %err, "failed to gather tool stats"),
+        }
+        self.tools_last = Some(now);
+        if updated {
+            self.tools_ingest_at = self.last_ingest;
+        }
+    }
+
     fn refresh_wrapped(
         &mut self,
         now: Instant,
@@ -578,6 +667,20 @@ impl UiDataCache {
                 }
                 Err(err) => tracing::warn!(error = %err, "failed to load last ingest timestamp"),
             }
+            match runtime.block_on(storage.ingest_error_count()) {
+                Ok(count) => self.ingest_errors = count,
+                Err(err) => tracing::warn!(error = %err, "failed to load ingest error count"),
+            }
+            match runtime.block_on(storage.backfill_progress()) {
+                // Progress left by an ingestor that stopped mid-backfill goes stale.
+                Ok(progress) => {
+                    self.backfill = progress.filter(|progress| {
+                        Utc::now().signed_duration_since(progress.updated_at)
+                            < ChronoDuration::seconds(BACKFILL_STALE_SECS)
+                    })
+                }
+                Err(err) => tracing::warn!(error = %err, "failed to load backfill progress"),
+            }
             self.ingest_last = Some(now);
         }
     }
@@ -601,6 +704,10 @@ impl UiDataCache {
             self.modal_turn_totals = None;
             self.modal_model_mix.clear();
             self.modal_tool_counts.clear();
+            self.modal_events.clear();
+            self.modal_line_changes = LineChanges::default();
+            self.modal_commits.clear();
+            self.modal_git_scan = None;
             self.modal_ingest_at = last_ingest;
             self.modal_key = None;
             self.modal_last = None;
@@ -666,6 +773,22 @@ impl UiDataCache {
                 Ok(rows) => self.modal_tool_counts = rows,
                 Err(err) => tracing::warn!(error = %err, "failed to load session tool counts"),
             }
+            match runtime.block_on(storage.session_events(selected.session_id.as_str())) {
+                Ok(events) => self.modal_events = events,
+                Err(err) => tracing::warn!(error = %err, "failed to load session events"),
+            }
+            match runtime.block_on(storage.session_line_changes(selected.session_id.as_str())) {
+                Ok(changes) => self.modal_line_changes = changes,
+                Err(err) => tracing::warn!(error = %err, "failed to load session line changes"),
+            }
+            match runtime.block_on(storage.session_commits(selected.session_id.as_str())) {
+                Ok(commits) => self.modal_commits = commits,
+                Err(err) => tracing::warn!(error = %err, "failed to load session commits"),
+            }
+            match runtime.block_on(storage.session_git_scan(selected.session_id.as_str())) {
+                Ok(scan) => self.modal_git_scan = scan,
+                Err(err) => tracing::warn!(error = %err, "failed to load session git scan"),
+            }
             if ingest_changed && let Some(key) = expanded {
                 self.modal_turns_by_message.remove(&key);
             }
@@ -713,12 +836,15 @@ pub async fn run(config: Arc<AppConfig>, storage: Storage) -> Result<()> {
 fn run_blocking(
     runtime: Handle,
     config: Arc<AppConfig>,
-    storage: Storage,
+    base_storage: Storage,
     tick_rate: Duration,
 ) -> Result<()> {
     let mut terminal = setup_terminal()?;
+    let mut root_filter: Option<String> = None;
+    let mut storage = base_storage.clone();
     let mut sessions_view = SessionsViewState::new();
     let mut stats_view = StatsViewState::new();
+    let mut tools_view = ToolsViewState::new();
     let mut pricing_view = PricingViewState::new();
     let mut wrapped_view = WrappedViewState::new(Local::now().date_naive());
     let mut session_modal = SessionModalState::new();
@@ -743,11 +869,13 @@ fn run_blocking(
                     &mut view_mode,
                     &mut sessions_view,
                     &mut stats_view,
+                    &mut tools_view,
                     &mut pricing_view,
                     &mut wrapped_view,
                     &mut session_modal,
                     &mut missing_modal,
                     &mut help_modal,
+                    &mut root_filter,
                     &cache.sessions_rows,
                     cache.sessions_total,
                     cache.sessions_offset,
@@ -775,11 +903,13 @@ fn run_blocking(
                         &mut view_mode,
                         &mut sessions_view,
                         &mut stats_view,
+                        &mut tools_view,
                         &mut pricing_view,
                         &mut wrapped_view,
                         &mut session_modal,
                         &mut missing_modal,
                         &mut help_modal,
+                        &mut root_filter,
                         &cache.sessions_rows,
                         cache.sessions_total,
                         cache.sessions_offset,
@@ -810,6 +940,20 @@ fn run_blocking(
                 previous_view_mode = view_mode;
             }
 
+            if storage.root_label() != root_filter.as_deref() {
+                storage = base_storage.with_root_label(root_filter.as_deref());
+                for view in [
+                    ViewMode::Overview,
+                    ViewMode::Sessions,
+                    ViewMode::Stats,
+                    ViewMode::Pricing,
+                    ViewMode::Tools,
+                ] {
+                    cache.invalidate_for_view(view);
+                }
+                sessions_view.reset();
+            }
+
             let now = Instant::now();
             let session_limit = config.display.recent_events_capacity.max(50);
             cache.refresh_ingest(now, &runtime, &storage);
@@ -845,6 +989,9 @@ fn run_blocking(
                     cache.refresh_pricing(now, &runtime, &storage);
                     pricing_view.sync(cache.pricing_rows.len());
                 }
+                ViewMode::Tools => {
+                    cache.refresh_tools(now, now_local, &runtime, &storage, &tools_view.nav);
+                }
             }
 
             let selected_session = match view_mode {
@@ -908,6 +1055,7 @@ fn run_blocking(
                     cache.sessions_offset,
                     &mut sessions_view,
                     &stats_view,
+                    &tools_view,
                     &mut pricing_view,
                     &wrapped_view,
                     &missing_modal,
@@ -920,6 +1068,7 @@ fn run_blocking(
                     cache.modal_turn_total,
                     &cache.modal_model_mix,
                     &cache.modal_tool_counts,
+                    &cache.modal_events,
                     &mut session_modal,
                     stats_data,
                     wrapped_data,
@@ -927,6 +1076,7 @@ fn run_blocking(
                     pricing_missing,
                     pricing_meta,
                     view_mode,
+                    root_filter.as_deref(),
                     &cache,
                 );
             })?;
@@ -969,6 +1119,7 @@ fn draw_ui(
     sessions_offset: usize,
     sessions_view: &mut SessionsViewState,
     stats_view: &StatsViewState,
+    tools_view: &ToolsViewState,
     pricing_view: &mut PricingViewState,
     wrapped_view: &WrappedViewState,
     missing_modal: &MissingPriceModalState,
@@ -981,6 +1132,7 @@ fn draw_ui(
     turn_total: usize,
     model_mix: &[ModelUsageRow],
     tool_counts: &[ToolCountRow],
+    session_events: &[SessionEvent],
     session_modal: &mut SessionModalState,
     stats_data: Option<&StatsRangeData>,
     wrapped_data: Option<&WrappedStats>,
@@ -988,6 +1140,7 @@ fn draw_ui(
     pricing_missing: Option<&[MissingPriceDetail]>,
     pricing_meta: Option<&PricingMeta>,
     view_mode: ViewMode,
+    root_filter: Option<&str>,
     cache: &UiDataCache,
 ) {
     let dim_background = session_modal.is_open() || missing_modal.is_open() || help_modal.is_open();
@@ -1001,7 +1154,7 @@ fn draw_ui(
             Constraint::Length(1),
         ])
         .split(frame.size());
-    render_navbar(frame, layout[0], view_mode, dim_background);
+    render_navbar(frame, layout[0], view_mode, root_filter, dim_background);
 
     match view_mode {
         ViewMode::Overview => draw_overview(
@@ -1010,6 +1163,7 @@ fn draw_ui(
             hero,
             wrapped_data,
             wrapped_view,
+            cache.backfill.is_some(),
             dim_background,
         ),
         ViewMode::Sessions => draw_sessions_view(
@@ -1034,6 +1188,13 @@ fn draw_ui(
             pricing_view,
             dim_background,
         ),
+        ViewMode::Tools => draw_tools_view(
+            frame,
+            layout[1],
+            cache.tools_data.as_ref(),
+            tools_view,
+            dim_background,
+        ),
     }
 
     render_status_bar(
@@ -1042,6 +1203,8 @@ fn draw_ui(
         cache.last_event,
         cache.ingest_flash,
         cache.pricing_missing.len(),
+        cache.ingest_errors,
+        cache.backfill.as_ref(),
         help_modal.is_open(),
         dim_background,
     );
@@ -1059,6 +1222,10 @@ fn draw_ui(
             cache.modal_turn_totals.as_ref(),
             model_mix,
             tool_counts,
+            session_events,
+            cache.modal_line_changes,
+            &cache.modal_commits,
+            cache.modal_git_scan.as_ref(),
             session_modal,
         );
     }
@@ -1079,18 +1246,32 @@ fn draw_overview(
     stats: &HeroStats,
     wrapped_data: Option<&WrappedStats>,
     wrapped_view: &WrappedViewState,
+    backfilling: bool,
     dim: bool,
 ) {
+    let rate_limit_height = stats
+        .rate_limits
+        .as_ref()
+        .map(rate_limit_panel_height)
+        .unwrap_or(0);
     let layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(6), Constraint::Length(1), Constraint::Min(0)])
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Length(rate_limit_height),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
         .split(area);
 
-    render_hero_cards(frame, layout[0], stats, dim);
-    frame.render_widget(Paragraph::new(Line::from("")), layout[1]);
-
+    render_hero_cards(frame, layout[0], stats, backfilling, dim);
     let theme = ui_theme(dim);
-    let wrapped_area = layout[2];
+    if let Some(status) = stats.rate_limits.as_ref() {
+        render_rate_limits(frame, layout[1], status, &theme);
+    }
+    frame.render_widget(Paragraph::new(Line::from("")), layout[2]);
+
+    let wrapped_area = layout[3];
     let nav_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -1143,6 +1324,7 @@ fn draw_overview(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_sessions_view(
     frame: &mut Frame,
     area: Rect,
@@ -1226,8 +1408,11 @@ fn render_wrapped_hero(frame: &mut Frame, area: Rect, stats: &WrappedStats, them
         .split(inner);
 
     let usage_items = vec![
-        ("Cost".to_string(), format_cost_short(stats.totals.cost_usd)),
-        ("Tokens".to_string(), format_tokens(stats.totals.total_tokens)),
+        ("Cost".to_string(), format_cost_short(stats.totals.cost)),
+        (
+            "Tokens".to_string(),
+            format_tokens(stats.totals.total_tokens),
+        ),
         ("Messages".to_string(), format_tokens(stats.total_messages)),
         ("Sessions".to_string(), format_tokens(stats.total_sessions)),
     ];
@@ -1340,8 +1525,7 @@ fn render_wrapped_heatmap(frame: &mut Frame, area: Rect, stats: &WrappedStats, t
         } else {
             0.0
         };
-        let weekday_intensity =
-            heatmap_intensity((weekday_ratio * 1000.0).round() as u64, 1000);
+        let weekday_intensity = heatmap_intensity((weekday_ratio * 1000.0).round() as u64, 1000);
         let weekday_color = MODEL_BAR_COLORS[weekday_intensity];
         let pct_label = pad_right(&format!("{:.0}%", weekday_ratio * 100.0), 4);
         spans.push(Span::raw(" "));
@@ -1528,7 +1712,7 @@ fn render_wrapped_top_models(frame: &mut Frame, area: Rect, stats: &WrappedStats
             let name = pad_right(&truncate_text(&model.model, model_width), model_width);
             let label = format!("{:>2}. {}", idx + 1, name);
             let tokens = align_right(format_tokens(model.tokens), tokens_width as u16);
-            let cost = align_right(format_cost_short(Some(model.cost_usd)), cost_width as u16);
+            let cost = align_right(format_cost_short(Some(model.cost)), cost_width as u16);
             let pct_label = align_right(format!("{pct:.1}%"), pct_width as u16);
             let bar = if bar_width > 0 {
                 ratio_bar(model.share, bar_width)
@@ -1593,7 +1777,7 @@ fn render_wrapped_top_repos(frame: &mut Frame, area: Rect, stats: &WrappedStats,
             let name = pad_right(&truncate_text(&repo_label, repo_width), repo_width);
             let label = format!("{:>2}. {}", idx + 1, name);
             let tokens = align_right(format_tokens(repo.tokens), tokens_width as u16);
-            let cost = align_right(format_cost_short(Some(repo.cost_usd)), cost_width as u16);
+            let cost = align_right(format_cost_short(Some(repo.cost)), cost_width as u16);
             let pct_label = align_right(format!("{pct:.1}%"), pct_width as u16);
             let bar = if bar_width > 0 {
                 ratio_bar(repo.share, bar_width)
@@ -1628,9 +1812,21 @@ fn render_wrapped_top_repos(frame: &mut Frame, area: Rect, stats: &WrappedStats,
 
 fn render_stats_table(frame: &mut Frame, area: Rect, stats: Option<&StatsRangeData>, dim: bool) {
     let theme = ui_theme(dim);
+    // Logs from before approval policies were recorded only add an
+    // "unknown" row; skip the breakdown until there is something to compare.
+    let approval_height = match stats {
+        Some(stats) if stats.approval.iter().any(|row| row.key != "unknown") => {
+            (stats.approval.len() as u16).saturating_add(3)
+        }
+        _ => 0,
+    };
     let layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(approval_height),
+        ])
         .split(area);
 
     if let Some(stats) = stats {
@@ -1663,17 +1859,17 @@ fn render_stats_table(frame: &mut Frame, area: Rect, stats: Option<&StatsRangeDa
             .iter()
             .rev()
             .map(|row| {
-                let cost_label = format_cost_short(row.totals.cost_usd);
+                let cost_label = format_cost_short(row.totals.cost);
                 let cost_label = align_right(cost_label, 10);
-                let cost_style = cost_style(row.totals.cost_usd, max_cost);
-                let is_zero_cost = matches!(row.totals.cost_usd, Some(cost) if cost <= 0.0);
+                let cost_style = cost_style(row.totals.cost, max_cost);
+                let is_zero_cost = matches!(row.totals.cost, Some(cost) if cost <= 0.0);
                 let cost_cell_style = if is_zero_cost {
                     Style::default().fg(Color::DarkGray)
                 } else {
                     cost_style
                 };
                 let budget_cell = if row.over_budget {
-                    if let (Some(limit), Some(cost)) = (row.budget_limit, row.totals.cost_usd) {
+                    if let (Some(limit), Some(cost)) = (row.budget_limit, row.totals.cost) {
                         if let Some(bar) = budget_bar_with_percent(cost, limit, 12) {
                             Cell::from(Line::from(bar))
                         } else {
@@ -1731,6 +1927,9 @@ fn render_stats_table(frame: &mut Frame, area: Rect, stats: Option<&StatsRangeDa
             .style(Style::default().fg(theme.text_fg));
 
         frame.render_widget(table, layout[1]);
+        if approval_height > 0 {
+            render_approval_table(frame, layout[2], &stats.approval, &theme);
+        }
     } else {
         let paragraph = Paragraph::new("Loading stats…")
             .block(gray_block("Stats", &theme))
@@ -1739,11 +1938,62 @@ fn render_stats_table(frame: &mut Frame, area: Rect, stats: Option<&StatsRangeDa
     }
 }
 
+fn render_approval_table(
+    frame: &mut Frame,
+    area: Rect,
+    rows: &[LineChangeCostRow],
+    theme: &UiTheme,
+) {
+    let total_cost: f64 = rows.iter().filter_map(|row| row.cost).sum();
+    let table_rows: Vec<Row> = rows
+        .iter()
+        .map(|row| {
+            let share = row
+                .cost
+                .filter(|_| total_cost > 0.0)
+                .map(|cost| format!("{:.0}%", cost / total_cost * 100.0))
+                .unwrap_or_else(|| "—".to_string());
+            Row::new(vec![
+                Cell::from(truncate_text(&row.key, 36)),
+                Cell::from(align_right(format_cost_short(row.cost), 10)),
+                Cell::from(align_right(share, 6)),
+                Cell::from(align_right(format_tokens(row.total_tokens), 10)),
+                Cell::from(align_right(format_tokens(row.lines()), 9)),
+                Cell::from(align_right(format_cost(row.cost_per_100_lines()), 12)),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(36),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Length(9),
+        Constraint::Length(12),
+    ];
+    let table = Table::new(table_rows, widths)
+        .header(light_blue_header(
+            vec![
+                "Approval / sandbox",
+                "Cost",
+                "Share",
+                "API",
+                "Lines",
+                "/100 lines",
+            ],
+            theme,
+        ))
+        .block(gray_block("By approval mode", theme))
+        .column_spacing(1)
+        .style(Style::default().fg(theme.text_fg));
+    frame.render_widget(table, area);
+}
+
 fn render_stats_trend(frame: &mut Frame, area: Rect, stats: &StatsRangeData, theme: &UiTheme) {
     let spark = sparkline(&stats.trend_values);
     let mut parts = vec![
-        format!("max: ${:.2}", stats.max_cost),
-        format!("avg: ${:.2}", stats.avg_cost),
+        format!("max: {}", fx::display().format(stats.max_cost, 2)),
+        format!("avg: {}", fx::display().format(stats.avg_cost, 2)),
     ];
     if let Some(top) = stats.top_model.as_ref() {
         let pct = (top.share * 100.0).round() as u64;
@@ -1760,6 +2010,199 @@ fn render_stats_trend(frame: &mut Frame, area: Rect, stats: &StatsRangeData, the
     frame.render_widget(paragraph, area);
 }
 
+fn draw_tools_view(
+    frame: &mut Frame,
+    area: Rect,
+    data: Option<&ToolStatsData>,
+    view: &ToolsViewState,
+    dim: bool,
+) {
+    let theme = ui_theme(dim);
+    let Some(data) = data else {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        render_time_nav(frame, layout[0], &view.nav, dim);
+        let paragraph = Paragraph::new("Loading tool stats…")
+            .block(gray_block("Tools", &theme))
+            .style(Style::default().fg(theme.text_fg));
+        frame.render_widget(paragraph, layout[1]);
+        return;
+    };
+
+    let table_height = |len: usize| {
+        if len == 0 {
+            0
+        } else {
+            (len as u16).saturating_add(3)
+        }
+    };
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(table_height(data.line_costs.len())),
+            Constraint::Length(table_height(data.failing.len())),
+        ])
+        .split(area);
+    render_time_nav(frame, layout[0], &view.nav, dim);
+
+    let rows: Vec<Row> = if data.tools.is_empty() {
+        vec![Row::new(vec!["No tool calls", "", "", "", "", "", ""])]
+    } else {
+        data.tools
+            .iter()
+            .map(|tool| {
+                let failure_rate = tool.failure_rate();
+                let avg_ms = (tool.finished > 0).then(|| tool.duration_ms / tool.finished);
+                let mut row = Row::new(vec![
+                    Cell::from(truncate_text(&tool.tool, 24)),
+                    Cell::from(align_right(format_tokens(tool.count), 8)),
+                    Cell::from(align_right(format_tokens(tool.failed), 8)),
+                    Cell::from(align_right(format_failure_rate(failure_rate), 8)),
+                    Cell::from(align_right(format_tool_time(tool.duration_ms), 10)),
+                    Cell::from(align_right(
+                        avg_ms
+                            .map(format_tool_time)
+                            .unwrap_or_else(|| "—".to_string()),
+                        10,
+                    )),
+                    Cell::from(align_right(format_bytes(tool.output_bytes), 10)),
+                ]);
+                if failure_rate.is_some_and(|rate| rate >= 0.25) {
+                    row = row.style(Style::default().fg(Color::LightRed));
+                }
+                row
+            })
+            .collect()
+    };
+    let widths = [
+        Constraint::Length(24),
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(light_blue_header(
+            vec!["Tool", "Calls", "Failed", "Fail %", "Time", "Avg", "Output"],
+            &theme,
+        ))
+        .block(gray_block(format!("Tools – {}", data.label), &theme))
+        .column_spacing(1)
+        .style(Style::default().fg(theme.text_fg));
+    frame.render_widget(table, layout[1]);
+
+    if !data.line_costs.is_empty() {
+        let rows: Vec<Row> = data
+            .line_costs
+            .iter()
+            .map(|(group, row)| {
+                Row::new(vec![
+                    Cell::from(*group),
+                    Cell::from(align_right(format_tokens(row.files_changed), 7)),
+                    Cell::from(align_right(
+                        format!("+{}", format_tokens(row.lines_added)),
+                        9,
+                    )),
+                    Cell::from(align_right(
+                        format!("-{}", format_tokens(row.lines_removed)),
+                        9,
+                    )),
+                    Cell::from(align_right(format_cost_short(row.cost), 10)),
+                    Cell::from(align_right(format_cost(row.cost_per_100_lines()), 12)),
+                    Cell::from(row.key.clone()),
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Min(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(light_blue_header(
+                vec![
+                    "By",
+                    "Files",
+                    "Added",
+                    "Removed",
+                    "Cost",
+                    "/100 lines",
+                    "Group",
+                ],
+                &theme,
+            ))
+            .block(gray_block("Cost per 100 lines changed", &theme))
+            .column_spacing(1)
+            .style(Style::default().fg(theme.text_fg));
+        frame.render_widget(table, layout[2]);
+    }
+
+    if data.failing.is_empty() {
+        return;
+    }
+    let rows: Vec<Row> = data
+        .failing
+        .iter()
+        .map(|command| {
+            Row::new(vec![
+                Cell::from(align_right(
+                    format!("{}/{}", command.failed, command.count),
+                    8,
+                )),
+                Cell::from(align_right(format_tool_time(command.duration_ms), 10)),
+                Cell::from(truncate_text(&command.tool, 14)),
+                Cell::from(command.detail.clone()),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(14),
+        Constraint::Min(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(light_blue_header(
+            vec!["Failed", "Time", "Tool", "Command"],
+            &theme,
+        ))
+        .block(gray_block("Most failing commands", &theme))
+        .column_spacing(1)
+        .style(Style::default().fg(theme.text_fg));
+    frame.render_widget(table, layout[3]);
+}
+
+fn format_failure_rate(rate: Option<f64>) -> String {
+    match rate {
+        Some(rate) => format!("{:.0}%", rate * 100.0),
+        None => "—".to_string(),
+    }
+}
+
+/// Compact duration for tool time: `850ms`, `12.3s`, `4m 12s`, `2h 05m`.
+fn format_tool_time(ms: u64) -> String {
+    let secs = ms / 1000;
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else if secs < 60 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, (secs / 60) % 60)
+    }
+}
+
 fn draw_pricing_view(
     frame: &mut Frame,
     area: Rect,
@@ -1782,12 +2225,28 @@ fn draw_pricing_view(
     view.list.set_visible_rows(visible_rows, total);
     let (page, pages) = view.list.page_info(total);
     let header = light_blue_header(
-        vec!["Model", "Prompt /1M", "Cached /1M", "Completion /1M"],
+        vec![
+            "Model",
+            "Prompt /1M",
+            "Cached /1M",
+            "Completion /1M",
+            "Since",
+            "Source",
+            "Long context",
+        ],
         &theme,
     );
 
     let rows: Vec<Row> = if prices.is_empty() {
-        vec![Row::new(vec!["No prices available", "", "", ""])]
+        vec![Row::new(vec![
+            "No prices available",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ])]
     } else {
         let start = view.list.scroll_offset;
         let end = (start + visible_rows).min(total);
@@ -1797,7 +2256,7 @@ fn draw_pricing_view(
             .map(|(offset, price)| {
                 let idx = start + offset;
                 let mut row = Row::new(vec![
-                    Cell::from(truncate_text(&price.model, 28)),
+                    Cell::from(truncate_text(&format_price_model(price), 28)),
                     Cell::from(align_right(format_rate(price.prompt_per_1m), 14)),
                     Cell::from(align_right(
                         price
@@ -1807,6 +2266,9 @@ fn draw_pricing_view(
                         14,
                     )),
                     Cell::from(align_right(format_rate(price.completion_per_1m), 14)),
+                    Cell::from(format_price_since(price.effective_from)),
+                    Cell::from(price.source.label()),
+                    Cell::from(format_long_context_price(price.long_context.as_ref())),
                 ]);
                 if idx == view.list.selected_row {
                     row = row.style(
@@ -1826,12 +2288,18 @@ fn draw_pricing_view(
         Constraint::Length(14),
         Constraint::Length(14),
         Constraint::Length(14),
+        Constraint::Length(11),
+        Constraint::Length(8),
+        Constraint::Length(20),
     ];
 
     let table = Table::new(rows, widths)
         .header(header)
         .block(gray_block(
-            format!("Pricing — {total} models • page {page}/{pages} (R refresh; ↑/↓ PgUp/PgDn)"),
+            // Rates stay in USD: they are list prices, not dated spend.
+            format!(
+                "Pricing (USD per 1M tokens) — {total} models • page {page}/{pages} (R refresh; ↑/↓ PgUp/PgDn)"
+            ),
             &theme,
         ))
         .column_spacing(1)
@@ -1840,6 +2308,42 @@ fn draw_pricing_view(
     frame.render_widget(table, layout[1]);
 }
 
+fn format_price_since(effective_from: NaiveDate) -> String {
+    // The baseline version predates any sync and has no meaningful start date.
+    if effective_from.year() <= 1970 {
+        "—".to_string()
+    } else {
+        effective_from.to_string()
+    }
+}
+
+fn format_price_model(price: &PriceRow) -> String {
+    format_model_tier(&price.model, &price.service_tier)
+}
+
+/// The model name, followed by its service tier unless that is the default,
+/// e.g. `gpt-5 · flex`.
+fn format_model_tier(model: &str, service_tier: &str) -> String {
+    if service_tier == DEFAULT_SERVICE_TIER {
+        model.to_string()
+    } else {
+        format!("{model} · {service_tier}")
+    }
+}
+
+/// Threshold plus prompt/completion rates, e.g. `>200k 2.5000/15.0000`.
+fn format_long_context_price(tier: Option<&LongContextPrice>) -> String {
+    match tier {
+        Some(tier) => format!(
+            ">{}k {}/{}",
+            tier.threshold_tokens / 1000,
+            format_rate(tier.prompt_per_1m),
+            format_rate(tier.completion_per_1m)
+        ),
+        None => "—".to_string(),
+    }
+}
+
 fn format_pricing_meta_line(meta: &PricingMeta) -> String {
     let now = Utc::now();
     let age = now.signed_duration_since(meta.last_fetch_at);
@@ -1894,7 +2398,7 @@ fn render_missing_prices(
     lines.push(Line::from(status));
     let max_lines = area.height.saturating_sub(2) as usize;
     for entry in missing.iter().take(max_lines.max(1)) {
-        let model = truncate_text(&entry.model, 24);
+        let model = truncate_text(&format_model_tier(&entry.model, &entry.service_tier), 24);
         let since = entry.first_seen.format("%Y-%m-%d").to_string();
         let last = entry.last_seen.format("%Y-%m-%d").to_string();
         lines.push(Line::from(format!("{model} since {since} (last {last})")));
@@ -1935,7 +2439,7 @@ fn render_missing_prices_modal(
         lines.push(Line::from("No missing prices detected."));
     } else {
         for (idx, entry) in missing.iter().enumerate() {
-            let model = truncate_text(&entry.model, 30);
+            let model = truncate_text(&format_model_tier(&entry.model, &entry.service_tier), 30);
             let since = entry.first_seen.format("%Y-%m-%d").to_string();
             let last = entry.last_seen.format("%Y-%m-%d").to_string();
             let line = Line::from(vec![
@@ -1991,19 +2495,24 @@ fn render_help_modal(frame: &mut Frame, view_mode: ViewMode) {
         Line::from("Navigation"),
         Line::from("  j/k or ↑/↓   move"),
         Line::from("  PgUp/PgDn    page"),
-        Line::from("  1–4          switch views"),
+        Line::from("  1–5          switch views"),
         Line::from("  Tab          next view"),
         Line::from("  q            quit"),
         Line::from(""),
         Line::from("Actions"),
         Line::from("  Enter        open details / expand message"),
+        Line::from("  Space        expand/collapse subagent sessions"),
         Line::from("  y            copy (in modal)"),
         Line::from("  !            missing price details"),
         Line::from("  r            refresh pricing (pricing view)"),
+        Line::from("  f            cycle session root filter"),
         Line::from("  ?            toggle help"),
     ];
 
-    if matches!(view_mode, ViewMode::Sessions | ViewMode::Stats) {
+    if matches!(
+        view_mode,
+        ViewMode::Sessions | ViewMode::Stats | ViewMode::Tools
+    ) {
         lines.push(Line::from(""));
         lines.push(Line::from("Time Navigation"));
         lines.push(Line::from("  h/l or ←/→   prev/next period"));
@@ -2231,13 +2740,20 @@ fn sparkline(values: &[f64]) -> String {
     out
 }
 
-fn render_navbar(frame: &mut Frame, area: Rect, view_mode: ViewMode, dim: bool) {
+fn render_navbar(
+    frame: &mut Frame,
+    area: Rect,
+    view_mode: ViewMode,
+    root_filter: Option<&str>,
+    dim: bool,
+) {
     let theme = ui_theme(dim);
     let tabs = [
         (ViewMode::Overview, "1 Overview"),
         (ViewMode::Sessions, "2 Sessions"),
         (ViewMode::Stats, "3 Stats"),
         (ViewMode::Pricing, "4 Pricing"),
+        (ViewMode::Tools, "5 Tools"),
     ];
     let mut spans = Vec::new();
     for (idx, (mode, label)) in tabs.iter().enumerate() {
@@ -2262,8 +2778,12 @@ fn render_navbar(frame: &mut Frame, area: Rect, view_mode: ViewMode, dim: bool)
         }
     }
     let line = Line::from(spans);
+    let title = match root_filter {
+        Some(label) => format!(" Codex Dashboard · root: {label} "),
+        None => " Codex Dashboard ".to_string(),
+    };
     let block = Block::default()
-        .title(" Codex Dashboard ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(theme.border_fg));
     let paragraph = Paragraph::new(line)
@@ -2353,12 +2873,15 @@ fn range_tab_spans(
     (spans, padded.chars().count())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_status_bar(
     frame: &mut Frame,
     area: Rect,
     last_event: Option<DateTime<Utc>>,
     ingest_flash: Option<Instant>,
     missing_count: usize,
+    ingest_errors: u64,
+    backfill: Option<&BackfillProgress>,
     help_open: bool,
     dim: bool,
 ) {
@@ -2379,7 +2902,8 @@ fn render_status_bar(
         }
         None => "—".to_string(),
     };
-    let ingest_elapsed = ingest_flash.and_then(|instant| now_instant.checked_duration_since(instant));
+    let ingest_elapsed =
+        ingest_flash.and_then(|instant| now_instant.checked_duration_since(instant));
     let ingest_active = ingest_elapsed
         .map(|elapsed| elapsed <= Duration::from_secs(5))
         .unwrap_or(false);
@@ -2390,8 +2914,6 @@ fn render_status_bar(
         .map(|elapsed| {
             if elapsed < Duration::from_millis(500) {
                 Color::Green
-            } else if elapsed < Duration::from_millis(1100) {
-                Color::LightGreen
             } else if elapsed <= Duration::from_secs(5) {
                 Color::LightGreen
             } else {
@@ -2426,6 +2948,31 @@ fn render_status_bar(
         Span::styled("  |  ", Style::default().fg(Color::DarkGray)),
         Span::styled(tab_text, Style::default().fg(Color::DarkGray)),
     ];
+    // Shown right after the ingest state so narrow terminals don't cut them off.
+    if let Some(progress) = backfill {
+        spans.splice(
+            4..4,
+            [
+                Span::styled("  |  ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format_backfill_progress(progress),
+                    Style::default().fg(Color::Cyan),
+                ),
+            ],
+        );
+    }
+    if ingest_errors > 0 {
+        spans.splice(
+            4..4,
+            [
+                Span::styled("  |  ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("Ingest errors: {ingest_errors} (run `doctor`)"),
+                    Style::default().fg(Color::LightRed),
+                ),
+            ],
+        );
+    }
     if missing_count > 0 {
         spans.push(Span::styled("  |  ", Style::default().fg(Color::DarkGray)));
         spans.push(Span::styled(
@@ -2444,7 +2991,13 @@ fn render_status_bar(
     frame.render_widget(paragraph, area);
 }
 
-fn render_hero_cards(frame: &mut Frame, area: Rect, stats: &HeroStats, dim: bool) {
+fn render_hero_cards(
+    frame: &mut Frame,
+    area: Rect,
+    stats: &HeroStats,
+    backfilling: bool,
+    dim: bool,
+) {
     let theme = ui_theme(dim);
     let block = gray_block("", &theme);
     let inner = block.inner(area);
@@ -2459,15 +3012,44 @@ fn render_hero_cards(frame: &mut Frame, area: Rect, stats: &HeroStats, dim: bool
         ])
         .split(inner);
 
-    render_hero_card(frame, layout[0], "TODAY", &stats.today, &theme, true);
+    // Totals are still growing while existing logs are backfilled.
+    let title = |title: &str| {
+        if backfilling {
+            format!("{title} · backfilling…")
+        } else {
+            title.to_string()
+        }
+    };
+    render_hero_card(
+        frame,
+        layout[0],
+        &title("TODAY"),
+        &stats.today,
+        &theme,
+        true,
+    );
     render_vertical_divider(frame, layout[1], &theme);
 
     let right = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(layout[2]);
-    render_hero_card(frame, right[0], "THIS WEEK", &stats.week, &theme, false);
-    render_hero_card(frame, right[1], "THIS MONTH", &stats.month, &theme, false);
+    render_hero_card(
+        frame,
+        right[0],
+        &title("THIS WEEK"),
+        &stats.week,
+        &theme,
+        false,
+    );
+    render_hero_card(
+        frame,
+        right[1],
+        &title("THIS MONTH"),
+        &stats.month,
+        &theme,
+        false,
+    );
 }
 
 fn render_hero_card(
@@ -2501,9 +3083,9 @@ fn render_hero_card(
     }
     let cost_width = 7u16; // $NNN.NN
     let cost = if primary {
-        format_cost_short(metric.total.cost_usd)
+        format_cost_short(metric.total.cost)
     } else {
-        align_right(format_cost_short(metric.total.cost_usd), cost_width)
+        align_right(format_cost_short(metric.total.cost), cost_width)
     };
     let cost_spans = if primary {
         let style = Style::default()
@@ -2531,9 +3113,12 @@ fn render_hero_card(
             } else {
                 ("▼", Color::Green)
             };
-            let value = format!("${:.2}", delta.abs());
+            let value = fx::display().format(delta.abs(), 2);
             let text = align_right(format!("{symbol} {value}"), delta_width);
-            Span::styled(text, Style::default().fg(color).add_modifier(Modifier::BOLD))
+            Span::styled(
+                text,
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )
         }
         None => {
             let text = align_right("—".to_string(), delta_width);
@@ -2572,7 +3157,7 @@ fn render_hero_card(
         " messages"
     };
     metric_spans.push(Span::styled(message_suffix, label_style));
-    if let (Some(budget), Some(cost)) = (metric.budget, metric.total.cost_usd)
+    if let (Some(budget), Some(cost)) = (metric.budget, metric.total.cost)
         && let Some(bar_spans) = budget_bar_with_percent(cost, budget, 10)
     {
         metric_spans.push(Span::raw("  "));
@@ -2605,6 +3190,131 @@ fn render_hero_card(
     frame.render_widget(paragraph, content);
 }
 
+fn rate_limit_panel_height(status: &RateLimitStatus) -> u16 {
+    let rows = status.primary.is_some() as u16 + status.secondary.is_some() as u16;
+    rows + 2
+}
+
+fn render_rate_limits(frame: &mut Frame, area: Rect, status: &RateLimitStatus, theme: &UiTheme) {
+    let now = Utc::now();
+    let age = now.signed_duration_since(status.captured_at);
+    let title = format!(" RATE LIMITS · updated {} ago ", format_duration_short(age));
+    let block = gray_block(title, theme);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let label_style = Style::default()
+        .fg(theme.label_fg)
+        .add_modifier(Modifier::DIM);
+    let lines: Vec<Line> = [status.primary.as_ref(), status.secondary.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|gauge| {
+            let mut spans = vec![Span::styled(
+                format!(" {:<7}", gauge.label),
+                Style::default()
+                    .fg(theme.header_fg)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            if let Some(bar) = budget_bar_with_percent(gauge.used_percent, 100.0, 10) {
+                spans.extend(bar);
+            }
+            let until_reset = gauge
+                .resets_at
+                .map(|resets_at| resets_at.signed_duration_since(now));
+            spans.push(Span::styled("  resets ", label_style));
+            spans.push(Span::styled(
+                until_reset
+                    .map(|remaining| format!("in {}", format_duration_short(remaining)))
+                    .unwrap_or_else(|| "—".to_string()),
+                Style::default().fg(theme.text_fg),
+            ));
+            if let Some(burn) = gauge.burn_per_hour {
+                spans.push(Span::styled("  burn ", label_style));
+                spans.push(Span::styled(
+                    format!("{burn:.1}%/h"),
+                    Style::default().fg(theme.text_fg),
+                ));
+                let remaining = (100.0 - gauge.used_percent).max(0.0);
+                if burn > 0.0 {
+                    let to_limit = ChronoDuration::seconds((remaining / burn * 3600.0) as i64);
+                    if until_reset.is_none_or(|until_reset| to_limit < until_reset) {
+                        spans.push(Span::styled(
+                            format!("  limit in ~{}", format_duration_short(to_limit)),
+                            Style::default()
+                                .fg(Color::Indexed(160))
+                                .add_modifier(Modifier::BOLD),
+                        ));
+                    }
+                }
+            }
+            Line::from(spans)
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Status bar gauge: files and bytes done, with an ETA extrapolated from the
+/// byte rate so far.
+fn format_backfill_progress(progress: &BackfillProgress) -> String {
+    let ratio = if progress.bytes_total > 0 {
+        progress.bytes_done as f64 / progress.bytes_total as f64
+    } else if progress.files_total > 0 {
+        progress.files_done as f64 / progress.files_total as f64
+    } else {
+        0.0
+    };
+    let elapsed = progress
+        .updated_at
+        .signed_duration_since(progress.started_at)
+        .num_milliseconds();
+    let eta = if progress.bytes_done > 0 && elapsed > 0 {
+        let remaining = progress.bytes_total.saturating_sub(progress.bytes_done);
+        let millis = remaining as f64 * elapsed as f64 / progress.bytes_done as f64;
+        let eta = ChronoDuration::milliseconds(millis as i64);
+        if eta < ChronoDuration::minutes(1) {
+            "<1m".to_string()
+        } else {
+            format_duration_short(eta)
+        }
+    } else {
+        "—".to_string()
+    };
+    format!(
+        "Backfill {} {}/{} files · {}/{} · ETA {eta}",
+        ratio_bar(ratio, 10),
+        progress.files_done,
+        progress.files_total,
+        format_bytes(progress.bytes_done),
+        format_bytes(progress.bytes_total),
+    )
+}
+
+fn format_bytes(value: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if value < 1024 {
+        return format!("{value} B");
+    }
+    let mut scaled = value as f64 / 1024.0;
+    let mut unit = 0;
+    while scaled >= 1024.0 && unit + 1 < UNITS.len() {
+        scaled /= 1024.0;
+        unit += 1;
+    }
+    format!("{scaled:.1} {}", UNITS[unit])
+}
+
+fn format_duration_short(duration: ChronoDuration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    if minutes < 60 {
+        format!("{minutes}m")
+    } else if minutes < 24 * 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}d {}h", minutes / (24 * 60), (minutes / 60) % 24)
+    }
+}
+
 fn budget_bar_with_percent(cost: f64, budget: f64, width: usize) -> Option<Vec<Span<'static>>> {
     if budget <= 0.0 {
         return None;
@@ -2668,6 +3378,7 @@ fn render_horizontal_separator(frame: &mut Frame, area: Rect, color: Color) {
     frame.render_widget(paragraph, area);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_sessions_table(
     frame: &mut Frame,
     area: Rect,
@@ -2685,7 +3396,7 @@ fn render_sessions_table(
     let (page, pages) = view.list.page_info(total);
     let status = if ingest_active { " (updating…)" } else { "" };
     let title = format!(
-        "Sessions: {total} total{status} • page {page}/{pages} (↑/↓ PgUp/PgDn navigate, Enter details)"
+        "Sessions: {total} total{status} • page {page}/{pages} (↑/↓ PgUp/PgDn navigate, Enter details, Space subagents)"
     );
     let empty_state = if total > 0 && sessions.is_empty() {
         Some(EmptyState::Loading)
@@ -2696,6 +3407,7 @@ fn render_sessions_table(
         frame,
         area,
         sessions,
+        &view.expanded,
         &mut view.list,
         title,
         &theme,
@@ -2719,12 +3431,7 @@ fn year_control_line(year: i32, theme: &UiTheme) -> Line<'static> {
     ])
 }
 
-fn render_overview_placeholder(
-    frame: &mut Frame,
-    area: Rect,
-    message: String,
-    theme: &UiTheme,
-) {
+fn render_overview_placeholder(frame: &mut Frame, area: Rect, message: String, theme: &UiTheme) {
     let block = gray_block("Overview", theme);
     let inner = block.inner(area);
     frame.render_widget(block, area);
@@ -2738,6 +3445,7 @@ fn render_session_list_table(
     frame: &mut Frame,
     area: Rect,
     sessions: &[SessionAggregate],
+    expanded: &[String],
     list: &mut ListState,
     title: String,
     theme: &UiTheme,
@@ -2758,7 +3466,7 @@ fn render_session_list_table(
 
     let max_cost = sessions
         .iter()
-        .filter_map(|row| row.cost_usd)
+        .filter_map(session_row_cost)
         .fold(0.0_f64, f64::max);
 
     let rows: Vec<Row> = if total_rows == 0 || sessions.is_empty() {
@@ -2796,14 +3504,11 @@ fn render_session_list_table(
                 let idx = window_offset + local_start + offset;
                 let local_time = aggregate.last_activity.with_timezone(&Local);
                 let time_label = local_time.format("%b %d %H:%M").to_string();
-                let title = aggregate
-                    .title
-                    .as_ref()
-                    .map(|value| truncate_text(value, LIST_TITLE_MAX_CHARS))
-                    .unwrap_or_else(|| "—".to_string());
-                let cost_style = cost_style(aggregate.cost_usd, max_cost);
-                let cost_label = align_right(format_cost_short(aggregate.cost_usd), cost_width);
-                let is_zero_cost = matches!(aggregate.cost_usd, Some(cost) if cost <= 0.0);
+                let title = format_session_tree_title(aggregate, expanded);
+                let row_cost = session_row_cost(aggregate);
+                let cost_style = cost_style(row_cost, max_cost);
+                let cost_label = align_right(format_cost_short(row_cost), cost_width);
+                let is_zero_cost = matches!(row_cost, Some(cost) if cost <= 0.0);
                 let cost_cell_style = if is_zero_cost {
                     Style::default().fg(Color::DarkGray)
                 } else {
@@ -2879,6 +3584,47 @@ enum EmptyState {
     Loading,
 }
 
+/// Cost shown in the session list: the whole tree's for a root with
+/// subagents, the session's own otherwise.
+fn session_row_cost(aggregate: &SessionAggregate) -> Option<f64> {
+    match aggregate.tree.as_ref() {
+        Some(node) if node.is_root() && node.descendants > 0 => node.tree_cost,
+        _ => aggregate.cost,
+    }
+}
+
+/// Session title with its place in the subagent tree: `▸`/`▾` and the number
+/// of subagent sessions on roots, indentation and the subagent kind below.
+fn format_session_tree_title(aggregate: &SessionAggregate, expanded: &[String]) -> String {
+    let title = aggregate.title.as_deref().unwrap_or("—");
+    let Some(node) = aggregate.tree.as_ref() else {
+        return truncate_text(title, LIST_TITLE_MAX_CHARS);
+    };
+    if !node.is_root() {
+        let indent = "  ".repeat(node.depth.saturating_sub(1) as usize);
+        let kind = aggregate
+            .subagent
+            .as_deref()
+            .map(|kind| format!("[{kind}] "))
+            .unwrap_or_default();
+        return truncate_text(&format!("{indent}└ {kind}{title}"), LIST_TITLE_MAX_CHARS);
+    }
+    if node.descendants == 0 {
+        return truncate_text(title, LIST_TITLE_MAX_CHARS);
+    }
+    let marker = if expanded.contains(&node.root_session_id) {
+        '▾'
+    } else {
+        '▸'
+    };
+    let suffix = format!(" (+{})", node.descendants);
+    let title = truncate_text(
+        title,
+        LIST_TITLE_MAX_CHARS.saturating_sub(suffix.chars().count() + 2),
+    );
+    format!("{marker} {title}{suffix}")
+}
+
 fn render_session_metadata(
     frame: &mut Frame,
     area: Rect,
@@ -2907,6 +3653,10 @@ fn render_session_modal(
     summary_totals: Option<&AggregateTotals>,
     model_mix: &[ModelUsageRow],
     tool_counts: &[ToolCountRow],
+    events: &[SessionEvent],
+    line_changes: LineChanges,
+    commits: &[SessionCommit],
+    git_scan: Option<&GitScanStatus>,
     modal: &mut SessionModalState,
 ) {
     let Some(selected) = selected else {
@@ -2928,7 +3678,16 @@ fn render_session_modal(
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let detail_rows = session_detail_rows(selected, &theme, model_mix, tool_counts);
+    let detail_rows = session_detail_rows(
+        selected,
+        &theme,
+        model_mix,
+        tool_counts,
+        events,
+        line_changes,
+        commits,
+        git_scan,
+    );
     let detail_height = (detail_rows.len().saturating_add(2)) as u16;
 
     let layout = Layout::default()
@@ -3225,10 +3984,7 @@ fn session_message_row(
         Cell::from(time_label),
         Cell::from(info_label),
         Cell::from(snippet),
-        Cell::from(align_right(
-            format_turn_cost(included, message.cost_usd),
-            10,
-        )),
+        Cell::from(align_right(format_turn_cost(included, message.cost), 10)),
         Cell::from(align_right(
             format_turn_tokens(included, message.prompt_tokens),
             7,
@@ -3275,7 +4031,7 @@ fn session_turn_row(turn: &SessionTurn, note_max: usize, info_max: usize) -> Row
         Cell::from(model).style(dim_style),
         Cell::from(format!(" {}", note)).style(dim_style),
         Cell::from(align_right(
-            format_turn_cost(turn.usage_included, turn.cost_usd),
+            format_turn_cost(turn.usage_included, turn.cost),
             10,
         ))
         .style(dim_style),
@@ -3349,7 +4105,7 @@ fn session_day_totals_row(date: NaiveDate, totals: Option<&AggregateTotals>) ->
         Cell::from(""),
     ];
     if let Some(totals) = totals {
-        cells[3] = Cell::from(align_right(format_cost(totals.cost_usd), 10));
+        cells[3] = Cell::from(align_right(format_cost(totals.cost), 10));
         cells[4] = Cell::from(align_right(format_tokens(totals.prompt_tokens), 7));
         cells[5] = Cell::from(align_right(format_tokens(totals.cached_prompt_tokens), 7));
         cells[6] = Cell::from(align_right(format_tokens(totals.blended_total()), 7));
@@ -3369,7 +4125,7 @@ fn session_summary_row(totals: &AggregateTotals) -> Row<'static> {
         Cell::from("Total"),
         Cell::from(""),
         Cell::from(""),
-        Cell::from(align_right(format_cost(totals.cost_usd), 10)),
+        Cell::from(align_right(format_cost(totals.cost), 10)),
         Cell::from(align_right(format_tokens(totals.prompt_tokens), 7)),
         Cell::from(align_right(format_tokens(totals.cached_prompt_tokens), 7)),
         Cell::from(align_right(format_tokens(totals.blended_total()), 7)),
@@ -3415,16 +4171,16 @@ fn format_message_count_compact(value: u64) -> String {
     }
 }
 
-fn format_cost(cost: Option<f64>) -> String {
+pub(crate) fn format_cost(cost: Option<f64>) -> String {
     match cost {
-        Some(value) => format!("${:.4}", value),
+        Some(value) => fx::display().format(value, 4),
         None => "unknown".to_string(),
     }
 }
 
 fn format_cost_short(cost: Option<f64>) -> String {
     match cost {
-        Some(value) => format!("${:.2}", value),
+        Some(value) => fx::display().format(value, 2),
         None => "?".to_string(),
     }
 }
@@ -3642,11 +4398,13 @@ fn handle_key_event(
     view_mode: &mut ViewMode,
     sessions_view: &mut SessionsViewState,
     stats_view: &mut StatsViewState,
+    tools_view: &mut ToolsViewState,
     pricing_view: &mut PricingViewState,
     wrapped_view: &mut WrappedViewState,
     session_modal: &mut SessionModalState,
     missing_modal: &mut MissingPriceModalState,
     help_modal: &mut HelpModalState,
+    root_filter: &mut Option<String>,
     sessions_rows: &[SessionAggregate],
     sessions_total: usize,
     sessions_offset: usize,
@@ -3734,6 +4492,9 @@ fn handle_key_event(
         KeyCode::Char('4') => {
             *view_mode = ViewMode::Pricing;
         }
+        KeyCode::Char('5') => {
+            *view_mode = ViewMode::Tools;
+        }
         KeyCode::Tab => {
             *view_mode = view_mode.next();
         }
@@ -3751,6 +4512,9 @@ fn handle_key_event(
             ViewMode::Stats => {
                 stats_view.nav.move_prev();
             }
+            ViewMode::Tools => {
+                tools_view.nav.move_prev();
+            }
             _ => {}
         },
         KeyCode::Right | KeyCode::Char('l') => match *view_mode {
@@ -3764,6 +4528,9 @@ fn handle_key_event(
             ViewMode::Stats => {
                 stats_view.nav.move_next();
             }
+            ViewMode::Tools => {
+                tools_view.nav.move_next();
+            }
             _ => {}
         },
         KeyCode::Up | KeyCode::Char('k') => match *view_mode {
@@ -3802,23 +4569,32 @@ fn handle_key_event(
             }
             _ => {}
         },
-        KeyCode::Enter => match *view_mode {
-            ViewMode::Sessions => {
-                if let Some(selected) = sessions_view.selected(sessions_rows, sessions_offset) {
-                    session_modal.open_for(session_key(selected));
-                }
+        KeyCode::Enter => {
+            if *view_mode == ViewMode::Sessions
+                && let Some(selected) = sessions_view.selected(sessions_rows, sessions_offset)
+            {
+                session_modal.open_for(session_key(selected));
             }
-            _ => {}
-        },
+        }
+        KeyCode::Char(' ') if *view_mode == ViewMode::Sessions => {
+            sessions_view.toggle_tree(sessions_rows, sessions_offset);
+        }
+        KeyCode::Char('f') | KeyCode::Char('F') => {
+            match runtime.block_on(storage.session_root_labels()) {
+                Ok(labels) => *root_filter = next_root_filter(&labels, root_filter.as_deref()),
+                Err(err) => tracing::warn!(error = %err, "failed to load session root labels"),
+            }
+        }
         KeyCode::Char(ch) => {
-            if matches!(view_mode, ViewMode::Sessions | ViewMode::Stats)
-                && let Some(range) = TimeRange::from_key(ch)
-            {
-                if matches!(view_mode, ViewMode::Sessions) {
-                    sessions_view.nav.set_range(range, today);
-                    sessions_view.reset();
-                } else {
-                    stats_view.nav.set_range(range, today);
+            if let Some(range) = TimeRange::from_key(ch) {
+                match view_mode {
+                    ViewMode::Sessions => {
+                        sessions_view.nav.set_range(range, today);
+                        sessions_view.reset();
+                    }
+                    ViewMode::Stats => stats_view.nav.set_range(range, today),
+                    ViewMode::Tools => tools_view.nav.set_range(range, today),
+                    _ => {}
                 }
             }
             if matches!(view_mode, ViewMode::Sessions)
@@ -3833,6 +4609,18 @@ fn handle_key_event(
     false
 }
 
+/// Cycles All -> each label -> All; a filter whose label vanished resets to All.
+fn next_root_filter(labels: &[String], current: Option<&str>) -> Option<String> {
+    let next_idx = match current {
+        None => 0,
+        Some(current) => match labels.iter().position(|label| label == current) {
+            Some(idx) => idx + 1,
+            None => return None,
+        },
+    };
+    labels.get(next_idx).cloned()
+}
+
 fn handle_missing_modal_input(
     modal: &mut MissingPriceModalState,
     key: KeyEvent,
@@ -3980,24 +4768,55 @@ fn handle_pricing_keys(
 }
 
 struct AlertSettings {
-    daily_budget_usd: Option<f64>,
-    monthly_budget_usd: Option<f64>,
+    daily_budget: Option<Budget>,
+    monthly_budget: Option<Budget>,
+}
+
+/// A budget limit as configured: in the display currency, or in USD and
+/// converted at the rate in force on the first day of the budgeted period.
+#[derive(Clone, Copy)]
+enum Budget {
+    Display(f64),
+    Usd(f64),
+}
+
+impl Budget {
+    fn from_config(display: Option<f64>, usd: Option<f64>) -> Option<Self> {
+        display.map(Budget::Display).or(usd.map(Budget::Usd))
+    }
+
+    fn limit_from(self, start: NaiveDate, currency: &fx::DisplayCurrency) -> f64 {
+        match self {
+            Budget::Display(limit) => limit,
+            Budget::Usd(limit) => limit * currency.rate_on(start),
+        }
+    }
 }
 
 impl AlertSettings {
     fn from_config(config: &crate::config::AlertConfig) -> Self {
         Self {
-            daily_budget_usd: config.daily_budget_usd,
-            monthly_budget_usd: config.monthly_budget_usd,
+            daily_budget: Budget::from_config(config.daily_budget, config.daily_budget_usd),
+            monthly_budget: Budget::from_config(config.monthly_budget, config.monthly_budget_usd),
         }
     }
+
+    fn daily_limit(&self, day: NaiveDate) -> Option<f64> {
+        self.daily_budget
+            .map(|budget| budget.limit_from(day, fx::display()))
+    }
+
+    fn monthly_limit(&self, month_start: NaiveDate) -> Option<f64> {
+        self.monthly_budget
+            .map(|budget| budget.limit_from(month_start, fx::display()))
+    }
 }
 
 #[derive(Clone)]
-struct Period {
-    label: String,
-    start: DateTime<Utc>,
-    end: DateTime<Utc>,
+pub(crate) struct Period {
+    pub(crate) label: String,
+    pub(crate) start: DateTime<Utc>,
+    pub(crate) end: DateTime<Utc>,
 }
 
 #[derive(Clone)]
@@ -4013,6 +4832,7 @@ struct HeroStats {
     today: HeroMetric,
     week: HeroMetric,
     month: HeroMetric,
+    rate_limits: Option<RateLimitStatus>,
 }
 
 impl Default for HeroStats {
@@ -4039,6 +4859,7 @@ impl Default for HeroStats {
                 session_count: 0,
                 message_count: 0,
             },
+            rate_limits: None,
         }
     }
 }
@@ -4091,29 +4912,179 @@ impl HeroStats {
         let month_prev_totals = storage
             .totals_between_timestamps(month_prev.start, month_prev.end)
             .await?;
+        let rate_limits = RateLimitStatus::gather(storage, Utc::now()).await?;
 
         Ok(Self {
             today: HeroMetric {
                 total: today_totals.clone(),
-                delta: cost_delta(today_totals.cost_usd, today_prev_totals.cost_usd),
-                budget: alerts.daily_budget_usd,
+                delta: cost_delta(today_totals.cost, today_prev_totals.cost),
+                budget: alerts.daily_limit(today),
                 session_count: today_counts.session_count,
                 message_count: today_counts.message_count,
             },
             week: HeroMetric {
                 total: week_totals.clone(),
-                delta: cost_delta(week_totals.cost_usd, week_prev_totals.cost_usd),
+                delta: cost_delta(week_totals.cost, week_prev_totals.cost),
                 budget: None,
                 session_count: week_counts.session_count,
                 message_count: week_counts.message_count,
             },
             month: HeroMetric {
                 total: month_totals.clone(),
-                delta: cost_delta(month_totals.cost_usd, month_prev_totals.cost_usd),
-                budget: alerts.monthly_budget_usd,
+                delta: cost_delta(month_totals.cost, month_prev_totals.cost),
+                budget: alerts.monthly_limit(today.with_day(1).unwrap_or(today)),
                 session_count: month_counts.session_count,
                 message_count: month_counts.message_count,
             },
+            rate_limits,
+        })
+    }
+}
+
+struct RateLimitGauge {
+    label: String,
+    used_percent: f64,
+    resets_at: Option<DateTime<Utc>>,
+    burn_per_hour: Option<f64>,
+}
+
+struct RateLimitStatus {
+    captured_at: DateTime<Utc>,
+    primary: Option<RateLimitGauge>,
+    secondary: Option<RateLimitGauge>,
+}
+
+impl RateLimitStatus {
+    async fn gather(storage: &Storage, now: DateTime<Utc>) -> Result<Option<Self>> {
+        let Some(latest) = storage.latest_rate_limits().await? else {
+            return Ok(None);
+        };
+        let history = storage
+            .rate_limit_snapshots_since(now - ChronoDuration::hours(RATE_LIMIT_HISTORY_HOURS))
+            .await?;
+        let primary = latest.primary.as_ref().map(|window| {
+            rate_limit_gauge(window, "primary", &history, |s| s.primary.as_ref(), now)
+        });
+        let secondary = latest.secondary.as_ref().map(|window| {
+            rate_limit_gauge(window, "secondary", &history, |s| s.secondary.as_ref(), now)
+        });
+        Ok(Some(Self {
+            captured_at: latest.timestamp,
+            primary,
+            secondary,
+        }))
+    }
+}
+
+fn rate_limit_gauge(
+    window: &RateLimitWindow,
+    fallback_label: &str,
+    history: &[RateLimitSnapshot],
+    pick: fn(&RateLimitSnapshot) -> Option<&RateLimitWindow>,
+    now: DateTime<Utc>,
+) -> RateLimitGauge {
+    let label = window
+        .window_minutes
+        .map(rate_limit_window_label)
+        .unwrap_or_else(|| fallback_label.to_string());
+    // Once the reset time has passed, the stored percentage describes a window that is gone.
+    if window.resets_at.is_some_and(|resets_at| resets_at <= now) {
+        return RateLimitGauge {
+            label,
+            used_percent: 0.0,
+            resets_at: None,
+            burn_per_hour: None,
+        };
+    }
+    // Look back a fifth of the window (at most a day) to smooth out bursts.
+    let lookback_minutes = window
+        .window_minutes
+        .map(|minutes| (minutes / 5) as i64)
+        .unwrap_or(60)
+        .clamp(1, RATE_LIMIT_HISTORY_HOURS * 60);
+    let points: Vec<(DateTime<Utc>, f64)> = history
+        .iter()
+        .filter_map(|snapshot| pick(snapshot).map(|w| (snapshot.timestamp, w.used_percent)))
+        .collect();
+    RateLimitGauge {
+        label,
+        used_percent: window.used_percent,
+        resets_at: window.resets_at,
+        burn_per_hour: rate_limit_burn_per_hour(&points, ChronoDuration::minutes(lookback_minutes)),
+    }
+}
+
+/// Percentage points consumed per hour over the trailing `lookback`, ignoring
+/// anything before the most recent window reset.
+fn rate_limit_burn_per_hour(
+    points: &[(DateTime<Utc>, f64)],
+    lookback: ChronoDuration,
+) -> Option<f64> {
+    let &(latest_ts, latest_used) = points.last()?;
+    let cutoff = latest_ts - lookback;
+    let mut start = (latest_ts, latest_used);
+    for &(ts, used) in points.iter().rev().skip(1) {
+        if ts < cutoff || used > start.1 {
+            break;
+        }
+        start = (ts, used);
+    }
+    let span_secs = latest_ts.signed_duration_since(start.0).num_seconds();
+    if span_secs < RATE_LIMIT_MIN_BURN_SPAN_SECS {
+        return None;
+    }
+    let hours = span_secs as f64 / 3600.0;
+    Some(((latest_used - start.1) / hours).max(0.0))
+}
+
+fn rate_limit_window_label(minutes: u64) -> String {
+    let hours = (minutes + 30) / 60;
+    if hours == 0 {
+        format!("{minutes}m")
+    } else if (167..=169).contains(&hours) {
+        "weekly".to_string()
+    } else if hours >= 24 && hours.is_multiple_of(24) {
+        format!("{}d", hours / 24)
+    } else {
+        format!("{hours}h")
+    }
+}
+
+struct ToolStatsData {
+    label: String,
+    tools: Vec<ToolCountRow>,
+    failing: Vec<FailingCommandRow>,
+    /// Groups that changed lines, by model, reasoning effort and repo.
+    line_costs: Vec<(&'static str, LineChangeCostRow)>,
+}
+
+impl ToolStatsData {
+    async fn gather(storage: &Storage, period: &Period) -> Result<Self> {
+        let mut line_costs = Vec::new();
+        for (name, group) in [
+            ("model", LineChangeGroup::Model),
+            ("effort", LineChangeGroup::Effort),
+            ("repo", LineChangeGroup::Repo),
+        ] {
+            let rows = storage
+                .line_change_costs_between(period.start, period.end, group, usize::MAX)
+                .await?;
+            line_costs.extend(
+                rows.into_iter()
+                    .filter(|row| row.lines() > 0)
+                    .take(LINE_COST_ROWS_PER_GROUP)
+                    .map(|row| (name, row)),
+            );
+        }
+        Ok(Self {
+            line_costs,
+            label: period.label.clone(),
+            tools: storage
+                .tool_counts_between(period.start, period.end)
+                .await?,
+            failing: storage
+                .failing_tool_commands_between(period.start, period.end, FAILING_COMMANDS_LIMIT)
+                .await?,
         })
     }
 }
@@ -4125,6 +5096,8 @@ struct StatsRangeData {
     avg_cost: f64,
     max_cost: f64,
     top_model: Option<TopModelStat>,
+    /// Spend by approval policy and sandbox mode, most expensive first.
+    approval: Vec<LineChangeCostRow>,
 }
 
 impl StatsRangeData {
@@ -4139,6 +5112,14 @@ impl StatsRangeData {
             .aggregates_by_bucket(period.start, period.end, bucket_expr)
             .await?;
         let top_model_share = storage.top_model_share(period.start, period.end).await?;
+        let approval = storage
+            .line_change_costs_between(
+                period.start,
+                period.end,
+                LineChangeGroup::Approval,
+                APPROVAL_ROWS_LIMIT,
+            )
+            .await?;
         let mut map = HashMap::new();
         for entry in rows {
             map.insert(entry.bucket, (entry.totals, entry.session_count));
@@ -4154,7 +5135,7 @@ impl StatsRangeData {
             let (totals, session_count) = map
                 .remove(&bucket.key)
                 .unwrap_or((AggregateTotals::default(), 0));
-            let cost = totals.cost_usd.unwrap_or(0.0);
+            let cost = totals.cost.unwrap_or(0.0);
             if cost > 0.0 {
                 sum_cost += cost;
                 count_cost += 1;
@@ -4164,8 +5145,14 @@ impl StatsRangeData {
             }
             trend.push(cost);
             let budget_limit = match bucket.granularity {
-                BucketGranularity::Day => alerts.daily_budget_usd,
-                BucketGranularity::Month => alerts.monthly_budget_usd,
+                BucketGranularity::Day => NaiveDate::parse_from_str(&bucket.key, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|day| alerts.daily_limit(day)),
+                BucketGranularity::Month => {
+                    NaiveDate::parse_from_str(&format!("{}-01", bucket.key), "%Y-%m-%d")
+                        .ok()
+                        .and_then(|month_start| alerts.monthly_limit(month_start))
+                }
                 _ => None,
             };
             let over_budget = budget_limit.map(|limit| cost > limit).unwrap_or(false);
@@ -4195,6 +5182,7 @@ impl StatsRangeData {
             avg_cost,
             max_cost,
             top_model,
+            approval,
         })
     }
 }
@@ -4202,14 +5190,14 @@ impl StatsRangeData {
 struct WrappedModelStat {
     model: String,
     tokens: u64,
-    cost_usd: f64,
+    cost: f64,
     share: f64,
 }
 
 struct WrappedRepoStat {
     repo: String,
     tokens: u64,
-    cost_usd: f64,
+    cost: f64,
     share: f64,
 }
 
@@ -4277,9 +5265,9 @@ impl WrappedStats {
         let (max_streak, current_streak, max_streak_days) =
             streak_stats_for_year(&daily_tokens, end_date);
 
-        let total_cost = totals.cost_usd.unwrap_or(0.0);
-        let model_total_cost: f64 = model_totals.iter().map(|row| row.cost_usd).sum();
-        let repo_total_cost: f64 = repo_totals.iter().map(|row| row.cost_usd).sum();
+        let total_cost = totals.cost.unwrap_or(0.0);
+        let model_total_cost: f64 = model_totals.iter().map(|row| row.cost).sum();
+        let repo_total_cost: f64 = repo_totals.iter().map(|row| row.cost).sum();
         let model_denominator = if total_cost > 0.0 {
             total_cost
         } else {
@@ -4295,9 +5283,9 @@ impl WrappedStats {
             .map(|row| WrappedModelStat {
                 model: row.model,
                 tokens: row.total_tokens,
-                cost_usd: row.cost_usd,
+                cost: row.cost,
                 share: if model_denominator > 0.0 {
-                    row.cost_usd / model_denominator
+                    row.cost / model_denominator
                 } else {
                     0.0
                 },
@@ -4308,9 +5296,9 @@ impl WrappedStats {
             .map(|row| WrappedRepoStat {
                 repo: row.repo,
                 tokens: row.total_tokens,
-                cost_usd: row.cost_usd,
+                cost: row.cost,
                 share: if repo_denominator > 0.0 {
-                    row.cost_usd / repo_denominator
+                    row.cost / repo_denominator
                 } else {
                     0.0
                 },
@@ -4507,7 +5495,7 @@ fn streak_stats_for_year(
         loop {
             if active_days.contains(&cursor) {
                 streak += 1;
-                cursor = cursor - ChronoDuration::days(1);
+                cursor -= ChronoDuration::days(1);
             } else {
                 break;
             }
@@ -4573,7 +5561,11 @@ fn heatmap_intensity(count: u64, max: u64) -> usize {
     }
 }
 
-fn period_for_range(range: TimeRange, anchor: NaiveDate, now_local: DateTime<Local>) -> Period {
+pub(crate) fn period_for_range(
+    range: TimeRange,
+    anchor: NaiveDate,
+    now_local: DateTime<Local>,
+) -> Period {
     match range {
         TimeRange::Day => {
             let start = local_start_of_day(anchor);
@@ -4784,6 +5776,9 @@ struct SessionsViewState {
     list: ListState,
     sort: SessionSort,
     initialized: bool,
+    /// Root sessions whose subagent sessions are listed below them.
+    expanded: Vec<String>,
+    expanded_rev: u64,
 }
 
 impl SessionsViewState {
@@ -4794,7 +5789,30 @@ impl SessionsViewState {
             list: ListState::new(),
             sort: SessionSort::Recent,
             initialized: false,
+            expanded: Vec::new(),
+            expanded_rev: 0,
+        }
+    }
+
+    /// Expands or collapses the tree of the selected session. Collapsing from
+    /// a subagent row moves the selection to its root.
+    fn toggle_tree(&mut self, sessions: &[SessionAggregate], offset: usize) {
+        let Some(selected) = self.selected(sessions, offset) else {
+            return;
+        };
+        let Some(node) = selected.tree.as_ref().filter(|node| node.descendants > 0) else {
+            return;
+        };
+        let root = node.root_session_id.clone();
+        if let Some(idx) = self.expanded.iter().position(|id| *id == root) {
+            self.expanded.swap_remove(idx);
+            if let Some(root_idx) = sessions.iter().position(|row| row.session_id == root) {
+                self.list.selected_row = offset + root_idx;
+            }
+        } else {
+            self.expanded.push(root);
         }
+        self.expanded_rev = self.expanded_rev.wrapping_add(1);
     }
 
     fn reset(&mut self) {
@@ -5138,6 +6156,19 @@ impl StatsViewState {
     }
 }
 
+struct ToolsViewState {
+    nav: TimeNavState,
+}
+
+impl ToolsViewState {
+    fn new() -> Self {
+        let today = Local::now().date_naive();
+        Self {
+            nav: TimeNavState::new(TimeRange::Week, today),
+        }
+    }
+}
+
 struct WrappedViewState {
     year: i32,
 }
@@ -5339,11 +6370,16 @@ fn full_session_label(id: &str) -> String {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn session_detail_rows(
     aggregate: &SessionAggregate,
     theme: &UiTheme,
     model_mix: &[ModelUsageRow],
     tool_counts: &[ToolCountRow],
+    events: &[SessionEvent],
+    line_changes: LineChanges,
+    commits: &[SessionCommit],
+    git_scan: Option<&GitScanStatus>,
 ) -> Vec<Row<'static>> {
     let cwd_spans = format_cwd_spans(aggregate.cwd.as_ref(), theme);
     let repo_spans = format_repo_branch_spans(
@@ -5369,12 +6405,180 @@ fn session_detail_rows(
             format_detail_snippet(aggregate.subagent.as_ref()),
             theme,
         ),
+        detail_row(
+            "Root",
+            format_detail_snippet(aggregate.root_label.as_ref()),
+            theme,
+        ),
     ];
+    if let Some(row) = format_session_tree_detail_row(aggregate, theme) {
+        rows.push(row);
+    }
     rows.extend(format_model_detail_rows(model_mix, theme));
     rows.extend(format_tool_detail_rows(tool_counts, theme));
+    if line_changes.files > 0 {
+        rows.push(detail_row_spans(
+            "Changes",
+            format_line_change_spans(line_changes, aggregate.cost),
+            theme,
+        ));
+    }
+    rows.extend(format_commit_detail_rows(commits, git_scan, theme));
+    rows.extend(format_reset_detail_rows(events, theme));
+    rows
+}
+
+/// Commits produced during the session, newest last. When the last scan could
+/// not read the repo, says so; commits found earlier are still listed.
+fn format_commit_detail_rows(
+    commits: &[SessionCommit],
+    git_scan: Option<&GitScanStatus>,
+    theme: &UiTheme,
+) -> Vec<Row<'static>> {
+    let scan_note = git_scan.and_then(|scan| match scan.status.as_str() {
+        git::SCAN_MISSING => Some("repo no longer at cwd".to_string()),
+        git::SCAN_FAILED => Some(format!(
+            "git log failed: {}",
+            scan.detail.as_deref().unwrap_or("unknown error")
+        )),
+        _ => None,
+    });
+    if commits.is_empty() {
+        return scan_note
+            .map(|note| vec![detail_row("Commits", format!("— ({note})"), theme)])
+            .unwrap_or_default();
+    }
+
+    let mut rows: Vec<Row<'static>> = commits
+        .iter()
+        .rev()
+        .take(MODAL_COMMITS_LIMIT)
+        .rev()
+        .enumerate()
+        .map(|(idx, commit)| {
+            let label = if idx == 0 { "Commits" } else { "" };
+            let mut spans = vec![
+                Span::styled(
+                    commit.sha.chars().take(7).collect::<String>(),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw("  "),
+            ];
+            if let Some(branch) = &commit.branch {
+                spans.push(Span::styled(
+                    format!("{branch}  "),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            spans.push(Span::raw(commit.subject.clone()));
+            detail_row_spans(label, spans, theme)
+        })
+        .collect();
+    if commits.len() > 1 || scan_note.is_some() {
+        let noun = if commits.len() == 1 {
+            "commit"
+        } else {
+            "commits"
+        };
+        let mut summary = format!("{} {noun}", commits.len());
+        let hidden = commits.len().saturating_sub(MODAL_COMMITS_LIMIT);
+        if hidden > 0 {
+            summary.push_str(&format!(" ({hidden} earlier not shown)"));
+        }
+        if let Some(note) = scan_note {
+            summary.push_str(&format!(" • {note}"));
+        }
+        rows.push(detail_row("", summary, theme));
+    }
     rows
 }
 
+/// Rolled-up usage of a root's subagent tree, or the root a subagent session
+/// belongs to.
+fn format_session_tree_detail_row(
+    aggregate: &SessionAggregate,
+    theme: &UiTheme,
+) -> Option<Row<'static>> {
+    let node = aggregate.tree.as_ref()?;
+    if !node.is_root() {
+        return Some(detail_row(
+            "Root Session",
+            node.root_session_id.clone(),
+            theme,
+        ));
+    }
+    if node.descendants == 0 {
+        return None;
+    }
+    let sessions = if node.descendants == 1 {
+        "session"
+    } else {
+        "sessions"
+    };
+    Some(detail_row(
+        "Subagents",
+        format!(
+            "{} {sessions} • tree total {} tokens, {}",
+            node.descendants,
+            format_tokens(node.tree_total_tokens),
+            format_cost(node.tree_cost)
+        ),
+        theme,
+    ))
+}
+
+/// Files and lines changed by applied patches, with the session's cost per
+/// 100 changed lines.
+fn format_line_change_spans(changes: LineChanges, cost: Option<f64>) -> Vec<Span<'static>> {
+    let files = if changes.files == 1 { "file" } else { "files" };
+    let mut spans = vec![
+        Span::raw(format!("{} {files}  ", changes.files)),
+        Span::styled(
+            format!("+{}", changes.added),
+            Style::default().fg(Color::Green),
+        ),
+        Span::raw(" "),
+        Span::styled(
+            format!("-{}", changes.removed),
+            Style::default().fg(Color::LightRed),
+        ),
+    ];
+    if changes.lines() > 0
+        && let Some(cost) = cost
+    {
+        spans.push(Span::styled(
+            format!(
+                "  {} per 100 lines",
+                format_cost(Some(cost * 100.0 / changes.lines() as f64))
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    spans
+}
+
+/// One row per token counter reset, so compactions and restarts stand out.
+/// Omitted entirely for sessions that never reset.
+fn format_reset_detail_rows(events: &[SessionEvent], theme: &UiTheme) -> Vec<Row<'static>> {
+    events
+        .iter()
+        .filter(|event| event.kind == TOKEN_RESET_EVENT)
+        .enumerate()
+        .map(|(idx, event)| {
+            let label = if idx == 0 { "Resets" } else { "" };
+            let when = event
+                .timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M");
+            let text = match event.detail.as_deref() {
+                Some(detail) => format!("{when}  {detail}"),
+                None => when.to_string(),
+            };
+            detail_row(label, text, theme)
+        })
+        .collect()
+}
+
 fn format_model_detail_rows(model_mix: &[ModelUsageRow], theme: &UiTheme) -> Vec<Row<'static>> {
     if model_mix.is_empty() {
         return vec![detail_row("Models", "—".to_string(), theme)];
@@ -5386,7 +6590,7 @@ fn format_model_detail_rows(model_mix: &[ModelUsageRow], theme: &UiTheme) -> Vec
     }
 
     let mut groups = group_models(model_mix);
-    groups.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens));
+    groups.sort_by_key(|b| std::cmp::Reverse(b.total_tokens));
     let total = total_tokens(model_mix);
     let max_model_len = groups
         .iter()
@@ -5550,6 +6754,43 @@ fn format_tool_detail_rows(tools: &[ToolCountRow], theme: &UiTheme) -> Vec<Row<'
         let label = if row_idx == 0 { "Tools" } else { "" };
         rows.push(detail_row_spans(label, spans, theme));
     }
+    rows.extend(format_tool_outcome_rows(tools, theme));
+    rows
+}
+
+/// Time spent and failures per tool, for tools whose outputs were paired with
+/// their calls. Omitted when no outcome is known.
+fn format_tool_outcome_rows(tools: &[ToolCountRow], theme: &UiTheme) -> Vec<Row<'static>> {
+    let mut finished: Vec<&ToolCountRow> = tools.iter().filter(|tool| tool.finished > 0).collect();
+    finished.sort_by_key(|tool| std::cmp::Reverse(tool.duration_ms));
+    let mut rows = Vec::new();
+    for (row_idx, chunk) in finished.chunks(4).enumerate() {
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        for (idx, tool) in chunk.iter().enumerate() {
+            if idx > 0 {
+                spans.push(Span::raw("  "));
+            }
+            spans.push(Span::styled(
+                truncate_text(&tool.tool, 14),
+                Style::default().fg(Color::Green),
+            ));
+            spans.push(Span::raw(format!(
+                " {}",
+                format_tool_time(tool.duration_ms)
+            )));
+            let failed_style = if tool.failed > 0 {
+                Style::default().fg(Color::LightRed)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            spans.push(Span::styled(
+                format!(" {}/{} failed", tool.failed, tool.finished),
+                failed_style,
+            ));
+        }
+        let label = if row_idx == 0 { "Tool time" } else { "" };
+        rows.push(detail_row_spans(label, spans, theme));
+    }
     rows
 }
 
@@ -5632,7 +6873,7 @@ fn group_models(model_mix: &[ModelUsageRow]) -> Vec<ModelGroup> {
     }
     let mut groups = Vec::with_capacity(map.len());
     for (model, mut efforts) in map {
-        efforts.sort_by(|a, b| b.tokens.cmp(&a.tokens));
+        efforts.sort_by_key(|b| std::cmp::Reverse(b.tokens));
         let total = efforts.iter().map(|effort| effort.tokens).sum();
         groups.push(ModelGroup {
             model,
@@ -5723,7 +6964,7 @@ fn build_session_share_text(
     tool_counts: &[ToolCountRow],
 ) -> String {
     let mut lines = Vec::new();
-    lines.push(format!("Cost: {}", format_cost(aggregate.cost_usd)));
+    lines.push(format!("Cost: {}", format_cost(aggregate.cost)));
     lines.push(format!("Messages: {}", aggregate.user_messages));
     lines.push(format!("Turns: {turn_count}"));
     lines.push(format!("Tokens: {}", session_token_summary(aggregate)));
@@ -5869,5 +7110,71 @@ fn gray_block(title: impl Into<String>, theme: &UiTheme) -> Block<'static> {
 
 #[cfg(test)]
 mod tests {
-    // No TUI-specific tests at the moment.
+    use super::*;
+
+    #[test]
+    fn usd_budgets_convert_at_the_period_rate_and_display_budgets_win() {
+        let config = crate::config::FxConfig {
+            display_currency: Some("EUR".to_string()),
+            rates: std::collections::BTreeMap::from([("EUR".to_string(), 0.5)]),
+            rates_file: None,
+        };
+        let currency = fx::DisplayCurrency::from_config(&config).unwrap();
+        let day = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+
+        let usd = Budget::from_config(None, Some(100.0)).unwrap();
+        assert_eq!(usd.limit_from(day, &currency), 50.0);
+        let display = Budget::from_config(Some(80.0), Some(100.0)).unwrap();
+        assert_eq!(display.limit_from(day, &currency), 80.0);
+        assert!(Budget::from_config(None, None).is_none());
+    }
+
+    #[test]
+    fn rate_limit_burn_ignores_points_before_reset() {
+        let base = Utc.with_ymd_and_hms(2025, 12, 1, 10, 0, 0).unwrap();
+        let points = vec![
+            (base, 80.0),
+            (base + ChronoDuration::minutes(10), 2.0),
+            (base + ChronoDuration::minutes(40), 12.0),
+        ];
+        let burn = rate_limit_burn_per_hour(&points, ChronoDuration::hours(1)).unwrap();
+        assert!((burn - 20.0).abs() < 1e-9);
+
+        assert_eq!(
+            rate_limit_burn_per_hour(&points[2..], ChronoDuration::hours(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn root_filter_cycles_through_labels_back_to_all() {
+        let labels = vec!["home".to_string(), "work".to_string()];
+        assert_eq!(next_root_filter(&labels, None).as_deref(), Some("home"));
+        assert_eq!(
+            next_root_filter(&labels, Some("home")).as_deref(),
+            Some("work")
+        );
+        assert_eq!(next_root_filter(&labels, Some("work")), None);
+        assert_eq!(next_root_filter(&labels, Some("gone")), None);
+        assert_eq!(next_root_filter(&[], None), None);
+    }
+
+    #[test]
+    fn backfill_progress_extrapolates_eta_from_byte_rate() {
+        let started_at = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let mut progress = BackfillProgress {
+            files_total: 40,
+            files_done: 10,
+            bytes_total: 4 * 1024 * 1024,
+            bytes_done: 1024 * 1024,
+            started_at,
+            updated_at: started_at + ChronoDuration::minutes(2),
+        };
+        assert_eq!(
+            format_backfill_progress(&progress),
+            "Backfill ███░░░░░░░ 10/40 files · 1.0 MB/4.0 MB · ETA 6m"
+        );
+        progress.bytes_done = 0;
+        assert!(format_backfill_progress(&progress).ends_with("0 B/4.0 MB · ETA —"));
+    }
 }